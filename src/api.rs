@@ -0,0 +1,215 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::spawn;
+
+use crate::tokenizer::ProfileSwitch;
+use crate::{reload_mouses_config, ButtonConfigs, ConnectedDevices, DeviceRequest, MousesConfig};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+use util::config::ConfigManager;
+use util::thread::MutexTrait;
+
+// the default profile is addressed with this name, like in the profile token
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub serial_number: String,
+    pub model: Option<String>,
+    pub nickname: Option<String>,
+    pub connected: bool,
+    pub active_profile: Option<String>,
+}
+
+// shared with the rest of the driver
+pub struct ApiState {
+    pub device_list_mutex: Arc<Mutex<ConnectedDevices>>,
+    pub mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    pub mouses_config_state_id: Arc<AtomicU32>,
+}
+
+// only listens on localhost, every request is handled on the server thread
+pub fn run_api(port: u16, api_state: ApiState) {
+    let server = match Server::http(("127.0.0.1", port)) {
+        Ok(server) => server,
+        Err(err) => {
+            println!("REST API not started : {}", err);
+            return;
+        }
+    };
+
+    println!("REST API listening on 127.0.0.1:{}", port);
+
+    spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+
+            request.as_reader().read_to_string(&mut body).ok();
+
+            let (status_code, response) = handle_request(&api_state, &request, body);
+
+            respond(request, status_code, response);
+        }
+    });
+}
+
+fn respond(request: Request, status_code: u16, response: String) {
+    let content_type =
+        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("valid header");
+
+    request
+        .respond(
+            Response::from_string(response)
+                .with_status_code(status_code)
+                .with_header(content_type),
+        )
+        .ok();
+}
+
+fn json<T: Serialize>(value: &T) -> (u16, String) {
+    match serde_json::to_string(value) {
+        Ok(json) => (200, json),
+        Err(err) => error(500, &err.to_string()),
+    }
+}
+
+fn error(status_code: u16, message: &str) -> (u16, String) {
+    (
+        status_code,
+        serde_json::json!({ "error": message }).to_string(),
+    )
+}
+
+fn handle_request(api_state: &ApiState, request: &Request, body: String) -> (u16, String) {
+    let path: Vec<&str> = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match (request.method(), path.as_slice()) {
+        (Method::Get, ["devices"]) => list_devices(api_state),
+        (Method::Get, ["devices", serial_number, "profiles"]) => {
+            let mouses_config = api_state.mouses_config_mutex.blocking_lock();
+
+            match mouses_config.config.get(*serial_number) {
+                Some(mouse_config) => {
+                    let mut profiles = mouse_config.profiles.clone();
+
+                    profiles.insert(
+                        DEFAULT_PROFILE.to_string(),
+                        mouse_config.button_configs.clone(),
+                    );
+
+                    json(&profiles)
+                }
+                None => error(404, "unknown device"),
+            }
+        }
+        (Method::Get, ["devices", serial_number, "profiles", profile]) => {
+            let mouses_config = api_state.mouses_config_mutex.blocking_lock();
+
+            match mouses_config.config.get(*serial_number) {
+                Some(mouse_config) if *profile == DEFAULT_PROFILE => {
+                    json(&mouse_config.button_configs)
+                }
+                Some(mouse_config) => match mouse_config.profiles.get(*profile) {
+                    Some(button_configs) => json(button_configs),
+                    None => error(404, "unknown profile"),
+                },
+                None => error(404, "unknown device"),
+            }
+        }
+        (Method::Put, ["devices", serial_number, "profiles", profile]) => {
+            let button_configs = match serde_json::from_str::<ButtonConfigs>(&body) {
+                Ok(button_configs) => button_configs,
+                Err(err) => return error(400, &err.to_string()),
+            };
+            let mut mouses_config = api_state.mouses_config_mutex.blocking_lock();
+
+            match mouses_config.config.get_mut(*serial_number) {
+                Some(mouse_config) => {
+                    if *profile == DEFAULT_PROFILE {
+                        mouse_config.button_configs = button_configs;
+                    } else {
+                        mouse_config
+                            .profiles
+                            .insert(profile.to_string(), button_configs);
+                    }
+
+                    api_state
+                        .mouses_config_state_id
+                        .fetch_add(1, Ordering::SeqCst);
+                    mouses_config.save();
+
+                    json(&())
+                }
+                None => error(404, "unknown device"),
+            }
+        }
+        // takes the same values as the profile token
+        (Method::Put, ["devices", serial_number, "active_profile"]) => {
+            let profile_switch = ProfileSwitch::parse(&body);
+            let mut mouses_config = api_state.mouses_config_mutex.blocking_lock();
+
+            match mouses_config.config.get_mut(*serial_number) {
+                Some(mouse_config) => {
+                    mouse_config.switch_profile(&profile_switch);
+                    api_state
+                        .mouses_config_state_id
+                        .fetch_add(1, Ordering::SeqCst);
+
+                    json(&mouse_config.active_profile)
+                }
+                None => error(404, "unknown device"),
+            }
+        }
+        // the macro is played by the device thread, like a button binding
+        (Method::Post, ["devices", serial_number, "macro"]) => {
+            match api_state
+                .device_list_mutex
+                .lock_poisoned()
+                .get(*serial_number)
+            {
+                Some(device_request_tx) => {
+                    device_request_tx.send(DeviceRequest::PlayMacro(body)).ok();
+
+                    json(&())
+                }
+                None => error(404, "device not connected"),
+            }
+        }
+        // picks up the profiles file changes without waiting for the next check
+        (Method::Post, ["reload"]) => {
+            reload_mouses_config(&mut api_state.mouses_config_mutex.blocking_lock());
+            api_state
+                .mouses_config_state_id
+                .fetch_add(1, Ordering::SeqCst);
+
+            json(&())
+        }
+        _ => error(404, "unknown endpoint"),
+    }
+}
+
+fn list_devices(api_state: &ApiState) -> (u16, String) {
+    let device_list = api_state.device_list_mutex.lock_poisoned();
+    let mouses_config = api_state.mouses_config_mutex.blocking_lock();
+    let device_info_vec: Vec<DeviceInfo> = mouses_config
+        .config
+        .iter()
+        .map(|(serial_number, mouse_config)| DeviceInfo {
+            serial_number: serial_number.clone(),
+            model: mouse_config.model.clone(),
+            nickname: mouse_config.nickname.clone(),
+            connected: device_list.contains_key(serial_number),
+            active_profile: mouse_config.active_profile.clone(),
+        })
+        .collect();
+
+    json(&device_info_vec)
+}
@@ -0,0 +1,109 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// 256 bits, written in hexadecimal
+const TOKEN_BYTES: usize = 32;
+
+// empty when it couldn't be created, none until configured
+static TOKEN: OnceLock<String> = OnceLock::new();
+// the remote clients always authenticate, the local ones only when asked to
+static LOCAL_REQUIRED: OnceLock<bool> = OnceLock::new();
+
+// shared with the client app, readable by the user only
+fn token_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("mad-rust").join("mmo7_client_token"))
+}
+
+// the token is created on first use, then kept so the client app only reads it once,
+// even when no client has to authenticate, as some commands always need it
+pub fn configure(client_auth: bool) {
+    LOCAL_REQUIRED.set(client_auth).ok();
+
+    match load_or_create_token() {
+        Ok(token) => {
+            TOKEN.set(token).ok();
+        }
+        // refusing every client rather than accepting them all
+        Err(err) => {
+            println!("Client token not created : {}", err);
+            TOKEN.set(String::new()).ok();
+        }
+    }
+}
+
+pub fn is_required(remote: bool) -> bool {
+    TOKEN.get().is_some() && (remote || LOCAL_REQUIRED.get() == Some(&true))
+}
+
+// compared in constant time, an empty token never matching
+pub fn verify(token: &str) -> bool {
+    match TOKEN.get() {
+        Some(expected) if !expected.is_empty() && expected.len() == token.len() => {
+            expected
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |difference, (a, b)| difference | (a ^ b))
+                == 0
+        }
+        Some(_) => false,
+        None => true,
+    }
+}
+
+fn load_or_create_token() -> io::Result<String> {
+    let path = token_path().ok_or_else(|| io::Error::other("no config directory"))?;
+
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim().to_string();
+
+        if !token.is_empty() {
+            restrict_permissions(&path)?;
+
+            return Ok(token);
+        }
+    }
+
+    let token = generate_token()?;
+    let mut options = OpenOptions::new();
+
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = options.open(&path)?;
+
+    // the mode only applies to new files, not to an empty one being overwritten
+    restrict_permissions(&path)?;
+    file.write_all(token.as_bytes())?;
+
+    Ok(token)
+}
+
+// readable by the user only, even when the file was made or copied by something else
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// from the random generator of the os
+fn generate_token() -> io::Result<String> {
+    let mut bytes = [0; TOKEN_BYTES];
+
+    getrandom::getrandom(&mut bytes).map_err(|err| io::Error::other(err.to_string()))?;
+
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,139 @@
+// name of the entry starting the driver at login
+const AUTOSTART_NAME: &str = "mad-rust-mmo7-driver";
+
+// registers or removes the current executable to be started when the user logs in
+pub fn run_autostart(install: bool) -> bool {
+    let result = if install {
+        std::env::current_exe().and_then(|path| platform::install(&path.to_string_lossy()))
+    } else {
+        platform::uninstall()
+    };
+
+    match (&result, install) {
+        (Ok(()), true) => println!("Autostart installed"),
+        (Ok(()), false) => println!("Autostart uninstalled"),
+        (Err(err), true) => println!("Autostart not installed : {}", err),
+        (Err(err), false) => println!("Autostart not uninstalled : {}", err),
+    }
+
+    result.is_ok()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::AUTOSTART_NAME;
+
+    // xdg autostart entry, started by the desktop session
+    fn desktop_entry_path() -> io::Result<PathBuf> {
+        dirs::config_dir()
+            .map(|config_dir| {
+                config_dir
+                    .join("autostart")
+                    .join(format!("{}.desktop", AUTOSTART_NAME))
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))
+    }
+
+    pub fn install(executable: &str) -> io::Result<()> {
+        let path = desktop_entry_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(
+            path,
+            format!(
+                "[Desktop Entry]\n\
+                Type=Application\n\
+                Name=Mad Rust MMO7 Driver\n\
+                Exec=\"{}\"\n\
+                Icon=input-mouse\n\
+                NoDisplay=true\n\
+                X-GNOME-Autostart-enabled=true\n",
+                executable
+            ),
+        )
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        match fs::remove_file(desktop_entry_path()?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::io;
+
+    use super::AUTOSTART_NAME;
+
+    use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use windows_sys::Win32::System::Registry::{
+        RegDeleteKeyValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ,
+    };
+
+    // run key of the current user, read at login
+    const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+    fn wide(string: &str) -> Vec<u16> {
+        string.encode_utf16().chain([0]).collect()
+    }
+
+    pub fn install(executable: &str) -> io::Result<()> {
+        let command = wide(&format!("\"{}\"", executable));
+        let error = unsafe {
+            RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                wide(RUN_KEY).as_ptr(),
+                wide(AUTOSTART_NAME).as_ptr(),
+                REG_SZ,
+                command.as_ptr() as *const _,
+                (command.len() * 2) as u32,
+            )
+        };
+
+        match error {
+            ERROR_SUCCESS => Ok(()),
+            error => Err(io::Error::from_raw_os_error(error as i32)),
+        }
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let error = unsafe {
+            RegDeleteKeyValueW(
+                HKEY_CURRENT_USER,
+                wide(RUN_KEY).as_ptr(),
+                wide(AUTOSTART_NAME).as_ptr(),
+            )
+        };
+
+        match error {
+            ERROR_SUCCESS | ERROR_FILE_NOT_FOUND => Ok(()),
+            error => Err(io::Error::from_raw_os_error(error as i32)),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::io;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "not supported on this system")
+    }
+
+    pub fn install(_executable: &str) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,362 @@
+use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use crate::scancode::type_char;
+#[cfg(target_os = "windows")]
+use crate::sendinput::SendInputBackend;
+use crate::status;
+use crate::tokenizer::Button;
+#[cfg(target_os = "linux")]
+use crate::uinput::UinputDevice;
+#[cfg(target_os = "linux")]
+use crate::wayland::WaylandDevice;
+
+// high resolution scroll deltas are in fractions of a notch, like the wheels reporting them
+pub const NOTCH_DELTA: i32 = 120;
+
+// output side of the mapper, mirrors the enigo api
+pub trait Backend {
+    // inputs between the start and the end of a batch may be sent at once
+    fn start_batch(&mut self) {}
+    fn end_batch(&mut self) {}
+    fn mouse_move_relative(&mut self, x: i32, y: i32);
+    // in screen coordinates, through enigo for the backends without absolute positioning
+    fn mouse_move_to(&mut self, x: i32, y: i32) {
+        Enigo::new().mouse_move_to(x, y);
+    }
+    fn mouse_down(&mut self, button: Button);
+    fn mouse_up(&mut self, button: Button);
+    fn mouse_click(&mut self, button: Button);
+    fn mouse_scroll_x(&mut self, length: i32);
+    fn mouse_scroll_y(&mut self, length: i32);
+    // in fractions of a notch delta, positive deltas scroll right and down like the lengths
+    fn mouse_smooth_scroll_x(&mut self, delta: i32);
+    fn mouse_smooth_scroll_y(&mut self, delta: i32);
+    fn key_sequence(&mut self, sequence: &str);
+    fn key_down(&mut self, key: Key);
+    fn key_up(&mut self, key: Key);
+    fn key_click(&mut self, key: Key);
+    // the key at the position of the character on a us keyboard, whatever the layout
+    fn scancode_click(&mut self, char: char);
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    // sendinput on windows, on linux uinput when /dev/uinput is writable,
+    // then wayland in wayland sessions, enigo otherwise
+    #[default]
+    Auto,
+    Enigo,
+    Uinput,
+    Wayland,
+    SendInput,
+}
+
+// backends are created on the thread using them, as enigo can't be sent between threads
+#[derive(Clone)]
+pub enum BackendFactory {
+    Enigo,
+    #[cfg(target_os = "windows")]
+    SendInput,
+    // a single virtual device is shared by all the workers of a mouse
+    #[cfg(target_os = "linux")]
+    Uinput(Arc<UinputDevice>),
+    #[cfg(target_os = "linux")]
+    Wayland(Arc<WaylandDevice>),
+}
+
+impl BackendFactory {
+    pub fn new(backend_kind: BackendKind) -> Self {
+        #[cfg(target_os = "linux")]
+        if matches!(backend_kind, BackendKind::Auto | BackendKind::Uinput) {
+            match UinputDevice::new() {
+                Ok(uinput_device) => return Self::Uinput(Arc::new(uinput_device)),
+                Err(err) => status::error(format!("uinput backend unavailable : {}", err)),
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if backend_kind == BackendKind::Wayland
+            || (backend_kind == BackendKind::Auto && std::env::var_os("WAYLAND_DISPLAY").is_some())
+        {
+            match WaylandDevice::new() {
+                Ok(wayland_device) => return Self::Wayland(Arc::new(wayland_device)),
+                Err(err) => status::error(format!("wayland backend unavailable : {}", err)),
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        if matches!(backend_kind, BackendKind::Auto | BackendKind::SendInput) {
+            return Self::SendInput;
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if matches!(backend_kind, BackendKind::Uinput | BackendKind::Wayland) {
+            status::error(format!(
+                "{:?} backend unavailable on this platform",
+                backend_kind
+            ));
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        if backend_kind == BackendKind::SendInput {
+            status::error(format!(
+                "{:?} backend unavailable on this platform",
+                backend_kind
+            ));
+        }
+
+        Self::Enigo
+    }
+
+    // the backend in use, auto being resolved
+    pub fn kind(&self) -> BackendKind {
+        match self {
+            Self::Enigo => BackendKind::Enigo,
+            #[cfg(target_os = "windows")]
+            Self::SendInput => BackendKind::SendInput,
+            #[cfg(target_os = "linux")]
+            Self::Uinput(_) => BackendKind::Uinput,
+            #[cfg(target_os = "linux")]
+            Self::Wayland(_) => BackendKind::Wayland,
+        }
+    }
+
+    pub fn create(&self) -> Box<dyn Backend> {
+        match self {
+            Self::Enigo => Box::new(EnigoBackend {
+                enigo: Enigo::new(),
+                side_buttons: SideButtons::default(),
+                scroll_remainder: (0, 0),
+            }),
+            #[cfg(target_os = "windows")]
+            Self::SendInput => Box::new(SendInputBackend::default()),
+            #[cfg(target_os = "linux")]
+            Self::Uinput(uinput_device) => Box::new(uinput_device.clone()),
+            #[cfg(target_os = "linux")]
+            Self::Wayland(wayland_device) => Box::new(wayland_device.clone()),
+        }
+    }
+}
+
+struct EnigoBackend {
+    enigo: Enigo,
+    side_buttons: SideButtons,
+    // smooth scroll deltas not adding up to a notch yet, enigo only scrolling by notches
+    scroll_remainder: (i32, i32),
+}
+
+// enigo only knows the left, middle and right buttons besides the scroll steps
+fn enigo_button(button: Button) -> Option<MouseButton> {
+    match button {
+        Button::Left => Some(MouseButton::Left),
+        Button::Middle => Some(MouseButton::Middle),
+        Button::Right => Some(MouseButton::Right),
+        Button::ScrollUp => Some(MouseButton::ScrollUp),
+        Button::ScrollDown => Some(MouseButton::ScrollDown),
+        Button::ScrollLeft => Some(MouseButton::ScrollLeft),
+        Button::ScrollRight => Some(MouseButton::ScrollRight),
+        Button::Back | Button::Forward => None,
+    }
+}
+
+impl Backend for EnigoBackend {
+    fn mouse_move_relative(&mut self, x: i32, y: i32) {
+        self.enigo.mouse_move_relative(x, y);
+    }
+
+    fn mouse_move_to(&mut self, x: i32, y: i32) {
+        self.enigo.mouse_move_to(x, y);
+    }
+
+    fn mouse_down(&mut self, button: Button) {
+        match enigo_button(button) {
+            Some(enigo_button) => self.enigo.mouse_down(enigo_button),
+            None => self.side_buttons.press(button, true),
+        }
+    }
+
+    fn mouse_up(&mut self, button: Button) {
+        match enigo_button(button) {
+            Some(enigo_button) => self.enigo.mouse_up(enigo_button),
+            None => self.side_buttons.press(button, false),
+        }
+    }
+
+    fn mouse_click(&mut self, button: Button) {
+        match enigo_button(button) {
+            Some(enigo_button) => self.enigo.mouse_click(enigo_button),
+            None => {
+                self.side_buttons.press(button, true);
+                self.side_buttons.press(button, false);
+            }
+        }
+    }
+
+    fn mouse_scroll_x(&mut self, length: i32) {
+        self.enigo.mouse_scroll_x(length);
+    }
+
+    fn mouse_scroll_y(&mut self, length: i32) {
+        self.enigo.mouse_scroll_y(length);
+    }
+
+    fn mouse_smooth_scroll_x(&mut self, delta: i32) {
+        self.scroll_remainder.0 += delta;
+
+        let length = self.scroll_remainder.0 / NOTCH_DELTA;
+
+        if length != 0 {
+            self.scroll_remainder.0 -= length * NOTCH_DELTA;
+            self.enigo.mouse_scroll_x(length);
+        }
+    }
+
+    fn mouse_smooth_scroll_y(&mut self, delta: i32) {
+        self.scroll_remainder.1 += delta;
+
+        let length = self.scroll_remainder.1 / NOTCH_DELTA;
+
+        if length != 0 {
+            self.scroll_remainder.1 -= length * NOTCH_DELTA;
+            self.enigo.mouse_scroll_y(length);
+        }
+    }
+
+    fn key_sequence(&mut self, sequence: &str) {
+        self.enigo.key_sequence(sequence);
+    }
+
+    fn key_down(&mut self, key: Key) {
+        self.enigo.key_down(key);
+    }
+
+    fn key_up(&mut self, key: Key) {
+        self.enigo.key_up(key);
+    }
+
+    fn key_click(&mut self, key: Key) {
+        self.enigo.key_click(key);
+    }
+
+    // xdo takes the x11 keycodes, offset by 8 from the evdev codes
+    #[cfg(target_os = "linux")]
+    fn scancode_click(&mut self, char: char) {
+        type_char(char, |scancode, pressed| {
+            let key = Key::Raw(scancode + 8);
+
+            if pressed {
+                self.enigo.key_down(key);
+            } else {
+                self.enigo.key_up(key);
+            }
+        });
+    }
+
+    // enigo takes virtual keys, mapped from the scancodes through the current layout
+    #[cfg(target_os = "windows")]
+    fn scancode_click(&mut self, char: char) {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, MAPVK_VSC_TO_VK};
+
+        type_char(char, |scancode, pressed| {
+            let key = Key::Raw(unsafe { MapVirtualKeyW(scancode as u32, MAPVK_VSC_TO_VK) } as u16);
+
+            if pressed {
+                self.enigo.key_down(key);
+            } else {
+                self.enigo.key_up(key);
+            }
+        });
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    fn scancode_click(&mut self, char: char) {
+        self.enigo.key_click(Key::Layout(char));
+    }
+}
+
+use platform::SideButtons;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT};
+    use x11rb::protocol::xtest::ConnectionExt;
+    use x11rb::rust_connection::RustConnection;
+
+    use crate::tokenizer::Button;
+
+    // xdo can't press the side buttons, they are the buttons 8 and 9 of the xtest extension
+    #[derive(Default)]
+    pub struct SideButtons {
+        x11: Option<RustConnection>,
+    }
+
+    impl SideButtons {
+        pub fn press(&mut self, button: Button, pressed: bool) {
+            let detail = match button {
+                Button::Back => 8,
+                Button::Forward => 9,
+                _ => return,
+            };
+            let type_ = if pressed {
+                BUTTON_PRESS_EVENT
+            } else {
+                BUTTON_RELEASE_EVENT
+            };
+
+            if self.x11.is_none() {
+                self.x11 = x11rb::connect(None).ok().map(|(connection, _)| connection);
+            }
+
+            if let Some(connection) = &self.x11 {
+                // the connection might be broken, retry on next press
+                if connection
+                    .xtest_fake_input(type_, detail, 0, x11rb::NONE, 0, 0, 0)
+                    .is_err()
+                    || connection.flush().is_err()
+                {
+                    self.x11 = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::backend::Backend;
+    use crate::sendinput::SendInputBackend;
+    use crate::tokenizer::Button;
+
+    // enigo has no side buttons, they are sent through SendInput
+    #[derive(Default)]
+    pub struct SideButtons;
+
+    impl SideButtons {
+        pub fn press(&mut self, button: Button, pressed: bool) {
+            if pressed {
+                SendInputBackend::default().mouse_down(button);
+            } else {
+                SendInputBackend::default().mouse_up(button);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use crate::tokenizer::Button;
+
+    #[derive(Default)]
+    pub struct SideButtons;
+
+    impl SideButtons {
+        pub fn press(&mut self, _button: Button, _pressed: bool) {}
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config_watcher::profiles_path;
+use crate::MousesConfig;
+
+// the oldest backups are removed past this count
+const MAX_BACKUPS: usize = 20;
+const PREFIX: &str = "mmo7_profiles-";
+
+fn backup_dir() -> Option<PathBuf> {
+    Some(profiles_path()?.parent()?.join("mmo7_profiles_backups"))
+}
+
+// utc date and time, like `20240131-235959`, so the names sort by age
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // civil date from the days since 1970-01-01
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// names of the backups, newest first
+pub fn list() -> Vec<String> {
+    let mut name_vec: Vec<String> = backup_dir()
+        .and_then(|backup_dir| fs::read_dir(backup_dir).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.starts_with(PREFIX))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    name_vec.sort_unstable_by(|a, b| b.cmp(a));
+    name_vec
+}
+
+// copies the profiles file before it's overwritten, several saves within a second keep
+// the file as it was before the first one
+pub fn backup() {
+    let (profiles_path, backup_dir) = match (profiles_path(), backup_dir()) {
+        (Some(profiles_path), Some(backup_dir)) => (profiles_path, backup_dir),
+        _ => return,
+    };
+
+    if !profiles_path.exists() {
+        return;
+    }
+
+    let backup_path = backup_dir.join(format!("{}{}.json", PREFIX, timestamp()));
+
+    if backup_path.exists() {
+        return;
+    }
+
+    if let Err(err) =
+        fs::create_dir_all(&backup_dir).and_then(|_| fs::copy(&profiles_path, &backup_path))
+    {
+        println!("Profiles not backed up : {}", err);
+        return;
+    }
+
+    for name in list().iter().skip(MAX_BACKUPS) {
+        fs::remove_file(backup_dir.join(name)).ok();
+    }
+}
+
+// the current file is backed up too, so a restore can be undone
+pub fn restore(name: &str) -> Result<(), String> {
+    if !list().iter().any(|backup_name| backup_name == name) {
+        return Err(format!("unknown backup {}", name));
+    }
+
+    let (profiles_path, backup_dir) = profiles_path()
+        .zip(backup_dir())
+        .ok_or_else(|| "no config directory".to_string())?;
+    let data = fs::read_to_string(backup_dir.join(name)).map_err(|err| err.to_string())?;
+
+    serde_json::from_str::<MousesConfig>(&data)
+        .map_err(|err| format!("backup {} can't be parsed : {}", name, err))?;
+    backup();
+    fs::write(profiles_path, data).map_err(|err| err.to_string())
+}
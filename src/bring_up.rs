@@ -0,0 +1,219 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rusb::{Device, UsbContext};
+
+use crate::command::{DeviceError, DeviceErrorKind};
+use crate::model::DeviceModel;
+
+// the claim conflicts are retried after 1, 2, 4, 8, 16, 30 and 30 seconds, then given up
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_CLAIM_ATTEMPTS: u32 = 8;
+
+// step of the device bring up which failed, with its usb error
+#[derive(Debug, Clone, Copy)]
+pub enum BringUpError {
+    Open(rusb::Error),
+    SerialNumber(rusb::Error),
+    Descriptor(rusb::Error),
+    KernelDriver(rusb::Error),
+    Configuration(rusb::Error),
+    Claim(rusb::Error),
+}
+
+impl BringUpError {
+    fn usb_error(&self) -> rusb::Error {
+        match *self {
+            Self::Open(err)
+            | Self::SerialNumber(err)
+            | Self::Descriptor(err)
+            | Self::KernelDriver(err)
+            | Self::Configuration(err)
+            | Self::Claim(err) => err,
+        }
+    }
+
+    pub fn kind(&self) -> DeviceErrorKind {
+        match (self, self.usb_error()) {
+            (_, rusb::Error::Access) => DeviceErrorKind::Permission,
+            (Self::KernelDriver(_), _) => DeviceErrorKind::KernelDriver,
+            (Self::Configuration(_) | Self::Claim(_), rusb::Error::Busy) => DeviceErrorKind::Busy,
+            // libusb can't open the mice without a winusb driver on windows
+            (Self::Open(_), rusb::Error::NotSupported) => DeviceErrorKind::NoDriver,
+            _ => DeviceErrorKind::Other,
+        }
+    }
+
+    pub fn into_device_error<T: UsbContext>(
+        self,
+        device_model: &DeviceModel,
+        device: &Device<T>,
+        serial_number: Option<String>,
+    ) -> DeviceError {
+        DeviceError::new(
+            device_model.name.to_string(),
+            device.bus_number(),
+            device.address(),
+            serial_number,
+            self.kind(),
+            self.to_string(),
+        )
+    }
+}
+
+impl fmt::Display for BringUpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind() {
+            DeviceErrorKind::Permission => write!(f, "USB permission denied"),
+            DeviceErrorKind::Busy => {
+                write!(f, "USB interface busy, another program is using the mouse")
+            }
+            DeviceErrorKind::KernelDriver => {
+                write!(f, "kernel driver not detached : {}", self.usb_error())
+            }
+            DeviceErrorKind::NoDriver => write!(f, "no WinUSB driver installed for the mouse"),
+            DeviceErrorKind::Other => {
+                let step = match self {
+                    Self::Open(_) => "not opened",
+                    Self::SerialNumber(_) => "serial number not read",
+                    Self::Descriptor(_) => "descriptors not read",
+                    Self::KernelDriver(_) => "kernel driver not detached",
+                    Self::Configuration(_) => "configuration not set",
+                    Self::Claim(_) => "interface not claimed",
+                };
+
+                write!(f, "{} : {}", step, self.usb_error())
+            }
+        }
+    }
+}
+
+// last bring up failure of a device, a program holding the interface being unlikely to
+// release it right away, so the device isn't claimed again every second
+#[derive(Debug, Clone)]
+pub struct BringUpFailure {
+    pub error: BringUpError,
+    // program holding the interface, when it can be found
+    holder: Option<String>,
+    attempts: u32,
+    failed_at: Instant,
+}
+
+impl BringUpFailure {
+    pub fn new(
+        error: BringUpError,
+        last_failure: Option<&BringUpFailure>,
+        bus_number: u8,
+        address: u8,
+    ) -> Self {
+        let is_conflict = error.kind() == DeviceErrorKind::Busy;
+
+        Self {
+            error,
+            holder: if is_conflict {
+                interface_holder(bus_number, address)
+            } else {
+                None
+            },
+            attempts: match last_failure {
+                Some(last_failure) if is_conflict && last_failure.is_conflict() => {
+                    last_failure.attempts + 1
+                }
+                _ => 1,
+            },
+            failed_at: Instant::now(),
+        }
+    }
+
+    fn is_conflict(&self) -> bool {
+        self.error.kind() == DeviceErrorKind::Busy
+    }
+
+    pub fn gave_up(&self) -> bool {
+        self.is_conflict() && self.attempts >= MAX_CLAIM_ATTEMPTS
+    }
+
+    // the other errors are retried every second, as the user is likely fixing them
+    pub fn is_due(&self) -> bool {
+        if !self.is_conflict() {
+            return true;
+        }
+
+        let retry_delay = FIRST_RETRY_DELAY
+            .saturating_mul(1 << (self.attempts - 1).min(16))
+            .min(MAX_RETRY_DELAY);
+
+        !self.gave_up() && self.failed_at.elapsed() >= retry_delay
+    }
+
+    pub fn to_device_error<T: UsbContext>(
+        &self,
+        device_model: &DeviceModel,
+        device: &Device<T>,
+        serial_number: Option<String>,
+    ) -> DeviceError {
+        let mut device_error = self
+            .error
+            .into_device_error(device_model, device, serial_number);
+
+        if let Some(holder) = &self.holder {
+            device_error.message = format!("USB interface busy, {} is using the mouse", holder);
+        }
+        if self.gave_up() {
+            device_error.message = format!(
+                "{}, gave up after {} attempts, replug the mouse to retry",
+                device_error.message, self.attempts
+            );
+        }
+
+        device_error
+    }
+}
+
+use platform::interface_holder;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    // process with the usbfs node of the device open, only the processes of the same user
+    // being readable
+    pub fn interface_holder(bus_number: u8, address: u8) -> Option<String> {
+        let node = PathBuf::from(format!("/dev/bus/usb/{:03}/{:03}", bus_number, address));
+        let own_pid = std::process::id().to_string();
+
+        for entry in fs::read_dir("/proc").ok()?.flatten() {
+            let pid = entry.file_name().to_string_lossy().to_string();
+
+            if pid == own_pid || !pid.bytes().all(|byte| byte.is_ascii_digit()) {
+                continue;
+            }
+
+            let fds = match fs::read_dir(entry.path().join("fd")) {
+                Ok(fds) => fds,
+                Err(_) => continue,
+            };
+
+            if fds.flatten().any(|fd| {
+                fs::read_link(fd.path())
+                    .map(|target| target == node)
+                    .unwrap_or(false)
+            }) {
+                let name = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+
+                return Some(format!("{} (pid {})", name.trim(), pid));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    pub fn interface_holder(_bus_number: u8, _address: u8) -> Option<String> {
+        None
+    }
+}
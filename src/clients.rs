@@ -0,0 +1,205 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use util::connection::ConnectionState;
+use util::thread::{DualChannel, MutexTrait};
+
+use crate::command::{ButtonEvent, ModeEvent};
+use crate::websocket::{DriverEvent, EventSender};
+
+// the biggest commands are imported profiles, a few hundred kilobytes at most
+const MAX_FRAME_LENGTH: u32 = 4 * 1024 * 1024;
+
+// the listeners of the clients other than the client app, disabled when unset
+#[derive(Clone, Copy)]
+pub struct ListenerSettings {
+    pub local_port: Option<u16>,
+    // the clients of this address always authenticate, as it's reachable from the network
+    pub remote_address: Option<SocketAddr>,
+}
+
+// the id of each client, and whether it's authenticated, as the others receive nothing
+type ClientVec = Vec<(u32, bool, DualChannel<ConnectionState>)>;
+
+// sessions of the connected clients, the client app and the local ones
+#[derive(Clone, Default)]
+pub struct ClientList {
+    next_id: Arc<AtomicU32>,
+    client_vec: Arc<Mutex<ClientVec>>,
+}
+
+impl ClientList {
+    pub fn add(
+        &self,
+        client_dualchannel: DualChannel<ConnectionState>,
+        authenticated: bool,
+    ) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        self.client_vec
+            .lock_poisoned()
+            .push((id, authenticated, client_dualchannel));
+
+        id
+    }
+
+    pub fn remove(&self, id: u32) {
+        self.client_vec
+            .lock_poisoned()
+            .retain(|(client_id, _, _)| *client_id != id);
+    }
+
+    pub fn set_authenticated(&self, id: u32, authenticated: bool) {
+        for (client_id, client_authenticated, _) in self.client_vec.lock_poisoned().iter_mut() {
+            if *client_id == id {
+                *client_authenticated = authenticated;
+            }
+        }
+    }
+
+    // cloned so the list isn't locked while sending, the client with the given id
+    // and the ones not authenticated being left out
+    pub fn others(&self, id: Option<u32>) -> Vec<DualChannel<ConnectionState>> {
+        self.client_vec
+            .lock_poisoned()
+            .iter()
+            .filter(|(client_id, authenticated, _)| *authenticated && Some(*client_id) != id)
+            .map(|(_, _, client_dualchannel)| client_dualchannel.clone())
+            .collect()
+    }
+
+    pub async fn broadcast(&self, data: Vec<u8>) {
+        for client_dualchannel in self.others(None) {
+            client_dualchannel
+                .send_async(ConnectionState::Data(data.clone()))
+                .await
+                .ok();
+        }
+    }
+}
+
+// each client talks like the client app, one session per connection
+pub async fn run_client_listener<F>(address: SocketAddr, on_client: F)
+where
+    F: Fn(DualChannel<ConnectionState>) + Send + 'static,
+{
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("Client listener not started : {}", err);
+            return;
+        }
+    };
+
+    println!("Client listener listening on {}", address);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (session_dualchannel, stream_dualchannel) = DualChannel::new();
+
+            on_client(session_dualchannel);
+            tokio::spawn(bridge_stream(stream, stream_dualchannel));
+        }
+    });
+}
+
+// each command is preceded by its length, as a big endian u32
+async fn bridge_stream(stream: TcpStream, stream_dualchannel: DualChannel<ConnectionState>) {
+    let (mut reader, mut writer) = stream.into_split();
+
+    stream_dualchannel
+        .send_async(ConnectionState::Start)
+        .await
+        .ok();
+
+    let reading = async {
+        loop {
+            let length = match reader.read_u32().await {
+                Ok(length) if length <= MAX_FRAME_LENGTH => length,
+                _ => return,
+            };
+            let mut data = vec![0; length as usize];
+
+            if reader.read_exact(&mut data).await.is_err()
+                || stream_dualchannel
+                    .send_async(ConnectionState::Data(data))
+                    .await
+                    .is_err()
+            {
+                return;
+            }
+        }
+    };
+    let writing = async {
+        while let Ok(connection_state) = stream_dualchannel.recv_async().await {
+            if let ConnectionState::Data(data) = connection_state {
+                if writer.write_u32(data.len() as u32).await.is_err()
+                    || writer.write_all(&data).await.is_err()
+                {
+                    return;
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = reading => {}
+        _ = writing => {}
+    }
+
+    // the session ends once the channel is dropped
+    stream_dualchannel
+        .send_async(ConnectionState::End)
+        .await
+        .ok();
+}
+
+// the button and mode events of a device, so a client can bind a button by pressing it,
+// until the task is aborted
+pub fn forward_events(
+    client_dualchannel: DualChannel<ConnectionState>,
+    event_tx: &EventSender,
+    serial_number: String,
+) -> JoinHandle<()> {
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let data = match event {
+                DriverEvent::Button {
+                    serial_number: event_serial_number,
+                    button,
+                    pressed,
+                } if event_serial_number == serial_number => {
+                    ButtonEvent::new(event_serial_number, button, pressed).into_bytes()
+                }
+                DriverEvent::Mode {
+                    serial_number: event_serial_number,
+                    mode,
+                    shift,
+                } if event_serial_number == serial_number => {
+                    ModeEvent::new(event_serial_number, mode, shift).into_bytes()
+                }
+                _ => continue,
+            };
+
+            if client_dualchannel
+                .send_async(ConnectionState::Data(data))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    })
+}
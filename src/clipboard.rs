@@ -0,0 +1,135 @@
+use std::io;
+use std::time::Duration;
+
+// time left to the clipboard tool to own the clipboard before pasting
+pub const PASTE_DELAY: Duration = Duration::from_millis(50);
+
+// replaces the clipboard with the text, to be pasted in the focused window
+pub fn copy(text: &str) -> io::Result<()> {
+    platform::copy(text)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::env;
+    use std::io::{self, Write};
+    use std::process::{Command, Stdio};
+
+    // clipboard tools of the session, tried in order, they keep serving the text once exited
+    fn tools() -> &'static [(&'static str, &'static [&'static str])] {
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            &[("wl-copy", &[])]
+        } else {
+            &[
+                ("xclip", &["-selection", "clipboard"]),
+                ("xsel", &["--clipboard", "--input"]),
+            ]
+        }
+    }
+
+    // through the first tool of the session found
+    pub fn copy(text: &str) -> io::Result<()> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found");
+
+        for (program, args) in tools() {
+            let mut child = match Command::new(program)
+                .args(*args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(child) => child,
+                Err(err) => {
+                    last_err = err;
+                    continue;
+                }
+            };
+
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+
+            let status = child.wait()?;
+
+            if status.success() {
+                return Ok(());
+            }
+
+            last_err = io::Error::other(format!("{} exited with {}", program, status));
+        }
+
+        Err(last_err)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::io;
+    use std::ptr::copy_nonoverlapping;
+
+    use windows_sys::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+    };
+    use windows_sys::Win32::System::Memory::{
+        GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE,
+    };
+
+    // from the ole formats, not worth the feature
+    const CF_UNICODETEXT: u32 = 13;
+
+    // the memory is owned by the clipboard once set
+    pub fn copy(text: &str) -> io::Result<()> {
+        let utf16: Vec<u16> = text.encode_utf16().chain([0]).collect();
+
+        unsafe {
+            if OpenClipboard(0) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let result = set_text(&utf16);
+
+            CloseClipboard();
+            result
+        }
+    }
+
+    unsafe fn set_text(utf16: &[u16]) -> io::Result<()> {
+        EmptyClipboard();
+
+        let memory = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(utf16));
+
+        if memory == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let pointer = GlobalLock(memory) as *mut u16;
+
+        if pointer.is_null() {
+            GlobalFree(memory);
+            return Err(io::Error::last_os_error());
+        }
+
+        copy_nonoverlapping(utf16.as_ptr(), pointer, utf16.len());
+        GlobalUnlock(memory);
+
+        if SetClipboardData(CF_UNICODETEXT, memory) == 0 {
+            GlobalFree(memory);
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::io;
+
+    pub fn copy(_text: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "clipboard unsupported on this platform",
+        ))
+    }
+}
@@ -0,0 +1,891 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::BackendKind;
+use crate::lighting::LedColor;
+use crate::settings::DeviceSettings;
+use crate::window::WindowRule;
+
+// first byte of the driver specific commands, out of the range used by the util commands
+pub const DRIVER_COMMAND_ID: u8 = 255;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum DriverCommands {
+    RequestWindowRules(RequestWindowRules),
+    WindowRules(WindowRules),
+    RequestLedColors(RequestLedColors),
+    LedColors(LedColors),
+    RequestOnboardMemory(RequestOnboardMemory),
+    OnboardMemory(OnboardMemory),
+    ExportProfile(ExportProfile),
+    ImportProfile(ImportProfile),
+    ProfileTransfer(ProfileTransfer),
+    RequestProfiles(RequestProfiles),
+    Profiles(Profiles),
+    CreateProfile(CreateProfile),
+    RenameProfile(RenameProfile),
+    DeleteProfile(DeleteProfile),
+    ActivateProfile(ActivateProfile),
+    ConfigErrors(ConfigErrors),
+    RequestReportLog(RequestReportLog),
+    ReportLog(ReportLog),
+    RequestDriverStatus(RequestDriverStatus),
+    DriverStatus(DriverStatus),
+    RequestDeviceInfo(RequestDeviceInfo),
+    DeviceInfo(DeviceInfo),
+    RequestPollingRate(RequestPollingRate),
+    PollingRate(PollingRate),
+    DeviceErrors(DeviceErrors),
+    DeviceNickname(DeviceNickname),
+    DeviceNicknames(DeviceNicknames),
+    IdentifyDevice(IdentifyDevice),
+    ShareProfile(ShareProfile),
+    RequestSettings(RequestSettings),
+    Settings(Settings),
+    RequestBackups(RequestBackups),
+    Backups(Backups),
+    RestoreBackup(RestoreBackup),
+    RequestSyncStatus(RequestSyncStatus),
+    SyncStatus(SyncStatus),
+    RunSync(RunSync),
+    ListPresets(ListPresets),
+    Presets(Presets),
+    ApplyPreset(ApplyPreset),
+    Capabilities(Capabilities),
+    UnsupportedCommand(UnsupportedCommand),
+    Authenticate(Authenticate),
+    Authentication(Authentication),
+    ForgetDevice(ForgetDevice),
+    DeviceForgotten(DeviceForgotten),
+    SubscribeEvents(SubscribeEvents),
+    ButtonEvent(ButtonEvent),
+    ModeEvent(ModeEvent),
+    TestMacro(TestMacro),
+    MacroTest(MacroTest),
+    GetStatus(GetStatus),
+    Status(Status),
+    SettingError(SettingError),
+}
+
+// increased when an existing command changes, the new commands being told by their count
+pub const PROTOCOL_VERSION: u32 = 2;
+// the commands are only appended, so the ones below this count are known by the driver,
+// to be increased with each new command
+pub const DRIVER_COMMAND_COUNT: u32 = 54;
+
+impl DriverCommands {
+    pub fn is_driver_command(data: &[u8]) -> bool {
+        data.first() == Some(&DRIVER_COMMAND_ID)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        bincode::deserialize(data.get(1..)?).ok()
+    }
+
+    // index of the command, readable even when the driver doesn't know it
+    pub fn index(data: &[u8]) -> Option<u32> {
+        bincode::deserialize(data.get(1..)?).ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![DRIVER_COMMAND_ID];
+
+        data.extend(bincode::serialize(self).unwrap_or_default());
+        data
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestWindowRules {
+    pub serial_number: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WindowRules {
+    pub serial_number: String,
+    pub window_rules: Vec<WindowRule>,
+}
+
+impl WindowRules {
+    pub fn new(serial_number: String, window_rules: Vec<WindowRule>) -> Self {
+        Self {
+            serial_number,
+            window_rules,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::WindowRules(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestLedColors {
+    pub serial_number: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LedColors {
+    pub serial_number: String,
+    // indexed by mode
+    pub led_colors: Vec<LedColor>,
+}
+
+impl LedColors {
+    pub fn new(serial_number: String, led_colors: Vec<LedColor>) -> Self {
+        Self {
+            serial_number,
+            led_colors,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::LedColors(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestOnboardMemory {
+    pub serial_number: String,
+}
+
+// sent by the driver with a dump of the memory
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OnboardMemory {
+    pub serial_number: String,
+    pub memory: Vec<u8>,
+}
+
+impl OnboardMemory {
+    pub fn new(serial_number: String, memory: Vec<u8>) -> Self {
+        Self {
+            serial_number,
+            memory,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::OnboardMemory(self).to_bytes()
+    }
+}
+
+// files of the mmo7_profile_files directory, next to the profiles file of the driver
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ExportProfile {
+    pub serial_number: String,
+    // the default profile when none
+    pub profile: Option<String>,
+    pub file_name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ImportProfile {
+    pub serial_number: String,
+    pub file_name: String,
+    pub profile: String,
+    // replace a profile with the same name instead of renaming the imported one
+    pub overwrite: bool,
+}
+
+// sent by the driver once an export or an import is done
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ProfileTransfer {
+    pub serial_number: String,
+    // name of the exported or imported profile
+    pub profile: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ProfileTransfer {
+    pub fn new(serial_number: String, profile: Option<String>, error: Option<String>) -> Self {
+        Self {
+            serial_number,
+            profile,
+            error,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::ProfileTransfer(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestProfiles {
+    pub serial_number: String,
+}
+
+// sent by the driver on request and after each profile change
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Profiles {
+    pub serial_number: String,
+    pub profiles: Vec<String>,
+    // kept the same on every device sharing them
+    pub shared_profiles: Vec<String>,
+    // activated by the user, edited by the device config commands, the default profile when none
+    pub selected_profile: Option<String>,
+    // currently used, can differ from the selected one because of the window rules
+    pub active_profile: Option<String>,
+    // why the last change was refused
+    pub error: Option<String>,
+}
+
+impl Profiles {
+    pub fn new(
+        serial_number: String,
+        profiles: Vec<String>,
+        shared_profiles: Vec<String>,
+        selected_profile: Option<String>,
+        active_profile: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            serial_number,
+            profiles,
+            shared_profiles,
+            selected_profile,
+            active_profile,
+            error,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Profiles(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateProfile {
+    pub serial_number: String,
+    pub profile: String,
+    // named profile to copy, an empty profile is created when none
+    pub copy_from: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RenameProfile {
+    pub serial_number: String,
+    pub profile: String,
+    pub new_name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DeleteProfile {
+    pub serial_number: String,
+    pub profile: String,
+}
+
+// the profiles shared by several devices are edited once for all of them
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ShareProfile {
+    pub serial_number: String,
+    pub profile: String,
+    pub shared: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ActivateProfile {
+    pub serial_number: String,
+    // the default profile when none
+    pub profile: Option<String>,
+}
+
+// sent by the driver when the client connects and when the errors change,
+// each error holds its location in the profiles file
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ConfigErrors {
+    pub errors: Vec<String>,
+}
+
+impl ConfigErrors {
+    pub fn new(errors: Vec<String>) -> Self {
+        Self { errors }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::ConfigErrors(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestReportLog {
+    pub serial_number: String,
+}
+
+// last reports read from the device, empty unless the driver runs with `--debug-reports`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReportLog {
+    pub serial_number: String,
+    // time in milliseconds and bytes in hex, followed by the decoded report after a `#`
+    pub reports: Vec<String>,
+}
+
+impl ReportLog {
+    pub fn new(serial_number: String, reports: Vec<String>) -> Self {
+        Self {
+            serial_number,
+            reports,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::ReportLog(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestDriverStatus {
+    pub serial_number: String,
+}
+
+// counters of the device since it connected, to check the driver isn't adding lag
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DriverStatus {
+    pub serial_number: String,
+    // reports read during the last second
+    pub report_rate: u32,
+    // bindings waiting to be played by the emulation worker
+    pub emulation_queue_depth: u64,
+    // average time between a binding being triggered and played
+    pub emulation_latency_micros: u64,
+    // reports whose movement was merged with the next ones, the cursor still being moved
+    pub merged_movements: u64,
+    // emulation and movement workers restarted after a panic
+    pub worker_restarts: u64,
+}
+
+impl DriverStatus {
+    pub fn new(
+        serial_number: String,
+        report_rate: u32,
+        emulation_queue_depth: u64,
+        emulation_latency_micros: u64,
+        merged_movements: u64,
+        worker_restarts: u64,
+    ) -> Self {
+        Self {
+            serial_number,
+            report_rate,
+            emulation_queue_depth,
+            emulation_latency_micros,
+            merged_movements,
+            worker_restarts,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::DriverStatus(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestDeviceInfo {
+    pub serial_number: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeviceInfo {
+    pub serial_number: String,
+    pub model: String,
+    // release of the device descriptor, like `1.05`
+    pub firmware_version: String,
+}
+
+impl DeviceInfo {
+    pub fn new(serial_number: String, model: String, firmware_version: String) -> Self {
+        Self {
+            serial_number,
+            model,
+            firmware_version,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::DeviceInfo(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestPollingRate {
+    pub serial_number: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PollingRate {
+    pub serial_number: String,
+    // in hz, none keeps the rate of the device
+    pub polling_rate: Option<u16>,
+}
+
+impl PollingRate {
+    pub fn new(serial_number: String, polling_rate: Option<u16>) -> Self {
+        Self {
+            serial_number,
+            polling_rate,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::PollingRate(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum DeviceErrorKind {
+    // the user can't open the device, see `--setup-permissions`
+    Permission,
+    // another program claimed the interface
+    Busy,
+    KernelDriver,
+    // no winusb driver installed on windows
+    NoDriver,
+    Other,
+}
+
+// plugged in mouse which couldn't be brought up
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DeviceError {
+    pub model: String,
+    pub bus_number: u8,
+    pub address: u8,
+    // none when the device couldn't be opened to read it
+    pub serial_number: Option<String>,
+    pub kind: DeviceErrorKind,
+    pub message: String,
+}
+
+impl DeviceError {
+    pub fn new(
+        model: String,
+        bus_number: u8,
+        address: u8,
+        serial_number: Option<String>,
+        kind: DeviceErrorKind,
+        message: String,
+    ) -> Self {
+        Self {
+            model,
+            bus_number,
+            address,
+            serial_number,
+            kind,
+            message,
+        }
+    }
+}
+
+// sent by the driver when the client connects and when the errors change
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DeviceErrors {
+    pub errors: Vec<DeviceError>,
+}
+
+impl DeviceErrors {
+    pub fn new(errors: Vec<DeviceError>) -> Self {
+        Self { errors }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::DeviceErrors(self).to_bytes()
+    }
+}
+
+// sent by the client to name a device, none removes its nickname
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DeviceNickname {
+    pub serial_number: String,
+    pub nickname: Option<String>,
+}
+
+// sent by the driver after each device list, with the connected devices having a nickname
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DeviceNicknames {
+    pub nicknames: BTreeMap<String, String>,
+}
+
+impl DeviceNicknames {
+    pub fn new(nicknames: BTreeMap<String, String>) -> Self {
+        Self { nicknames }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::DeviceNicknames(self).to_bytes()
+    }
+}
+
+// blinks the leds of a connected device for a few seconds
+#[derive(Deserialize, Serialize, Debug)]
+pub struct IdentifyDevice {
+    pub serial_number: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestSettings {
+    pub serial_number: String,
+}
+
+// dpi stages, lift-off distance, polling rate, led colors and sensitivity of a device
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Settings {
+    pub serial_number: String,
+    pub settings: DeviceSettings,
+}
+
+impl Settings {
+    pub fn new(serial_number: String, settings: DeviceSettings) -> Self {
+        Self {
+            serial_number,
+            settings,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Settings(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestBackups;
+
+// backups of the profiles file written before each device config change, newest first
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Backups {
+    pub backups: Vec<String>,
+    // why the last restore was refused
+    pub error: Option<String>,
+}
+
+impl Backups {
+    pub fn new(backups: Vec<String>, error: Option<String>) -> Self {
+        Self { backups, error }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Backups(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RestoreBackup {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RequestSyncStatus;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SyncState {
+    // no remote in the driver settings
+    Disabled,
+    Synced,
+    // the profiles changed on both sides since the last sync
+    Conflict,
+    Error,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    // what the last sync did, or why it failed
+    pub message: Option<String>,
+}
+
+impl SyncStatus {
+    pub fn new(state: SyncState, message: Option<String>) -> Self {
+        Self { state, message }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::SyncStatus(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum SyncAction {
+    Now,
+    // resolve a conflict by overwriting the other side
+    KeepLocal,
+    KeepRemote,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RunSync {
+    pub action: SyncAction,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListPresets;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PresetInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+impl PresetInfo {
+    pub fn new(id: String, name: String, description: String) -> Self {
+        Self {
+            id,
+            name,
+            description,
+        }
+    }
+}
+
+// bindings shipped with the driver
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Presets {
+    pub presets: Vec<PresetInfo>,
+}
+
+impl Presets {
+    pub fn new(presets: Vec<PresetInfo>) -> Self {
+        Self { presets }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Presets(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ApplyPreset {
+    pub serial_number: String,
+    pub preset: String,
+    // created from the preset, the default bindings are replaced when none
+    pub profile: Option<String>,
+}
+
+// sent by the driver once a client connects, and by the clients to tell their own version,
+// so both sides know which commands the other one handles
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Capabilities {
+    pub protocol_version: u32,
+    pub driver_version: String,
+    pub driver_command_count: u32,
+}
+
+impl Capabilities {
+    pub fn driver() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            driver_version: env!("CARGO_PKG_VERSION").to_string(),
+            driver_command_count: DRIVER_COMMAND_COUNT,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Capabilities(self).to_bytes()
+    }
+}
+
+// answer to a command the driver can't handle, rather than ignoring it silently,
+// with the index of the driver command or the first byte of the util command
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UnsupportedCommand {
+    pub driver_command: bool,
+    pub id: u32,
+}
+
+impl UnsupportedCommand {
+    pub fn new(driver_command: bool, id: u32) -> Self {
+        Self { driver_command, id }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::UnsupportedCommand(self).to_bytes()
+    }
+}
+
+// token read by the client app from the config directory
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Authenticate {
+    pub token: String,
+}
+
+// answer to the authentication, and to the commands refused before it
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Authentication {
+    pub authenticated: bool,
+}
+
+impl Authentication {
+    pub fn new(authenticated: bool) -> Self {
+        Self { authenticated }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Authentication(self).to_bytes()
+    }
+}
+
+// removes a retired device from the profiles file, refused while it's connected
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ForgetDevice {
+    pub serial_number: String,
+}
+
+// sent to every client once the device is forgotten, and to the asking one when it's refused
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DeviceForgotten {
+    pub serial_number: String,
+    // why the device was kept
+    pub error: Option<String>,
+}
+
+impl DeviceForgotten {
+    pub fn new(serial_number: String, error: Option<String>) -> Self {
+        Self {
+            serial_number,
+            error,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::DeviceForgotten(self).to_bytes()
+    }
+}
+
+// the button and mode events of a device are forwarded to the client until it subscribes
+// to another device, the events stopping when none
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SubscribeEvents {
+    pub serial_number: Option<String>,
+}
+
+// key of the button in the config file, or left, right and middle for the clicks
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ButtonEvent {
+    pub serial_number: String,
+    pub button: String,
+    pub pressed: bool,
+}
+
+impl ButtonEvent {
+    pub fn new(serial_number: String, button: String, pressed: bool) -> Self {
+        Self {
+            serial_number,
+            button,
+            pressed,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::ButtonEvent(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ModeEvent {
+    pub serial_number: String,
+    pub mode: u8,
+    pub shift: bool,
+}
+
+impl ModeEvent {
+    pub fn new(serial_number: String, mode: u8, shift: bool) -> Self {
+        Self {
+            serial_number,
+            mode,
+            shift,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::ModeEvent(self).to_bytes()
+    }
+}
+
+// checks a binding against the profiles of the device, and plays it once after the countdown
+// when set, so a macro can be tried before being saved to a button
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TestMacro {
+    pub serial_number: String,
+    pub tokens: String,
+    // in seconds, leaving time to focus the window the macro is for
+    pub countdown: Option<u64>,
+}
+
+// sent before the countdown starts
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MacroTest {
+    pub serial_number: String,
+    // problems with the tokens, or why they can't be played
+    pub errors: Vec<String>,
+    pub playing: bool,
+}
+
+impl MacroTest {
+    pub fn new(serial_number: String, errors: Vec<String>, playing: bool) -> Self {
+        Self {
+            serial_number,
+            errors,
+            playing,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::MacroTest(self).to_bytes()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GetStatus;
+
+// state of the whole driver, for the diagnostics of the client
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Status {
+    pub driver_version: String,
+    pub uptime_secs: u64,
+    pub connected_devices: u32,
+    // backend emulating the bindings of each connected device, auto being resolved
+    pub backends: BTreeMap<String, BackendKind>,
+    // the last errors printed by the driver, oldest first
+    pub last_errors: Vec<String>,
+}
+
+impl Status {
+    pub fn new(
+        uptime_secs: u64,
+        connected_devices: u32,
+        backends: BTreeMap<String, BackendKind>,
+        last_errors: Vec<String>,
+    ) -> Self {
+        Self {
+            driver_version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs,
+            connected_devices,
+            backends,
+            last_errors,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::Status(self).to_bytes()
+    }
+}
+
+// sent by the driver when a setting is refused, nothing being changed
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SettingError {
+    pub serial_number: String,
+    // name of the setting in the device config, like `polling_rate`
+    pub field: String,
+    pub message: String,
+}
+
+impl SettingError {
+    pub fn new(serial_number: String, field: &str, message: String) -> Self {
+        Self {
+            serial_number,
+            field: field.to_string(),
+            message,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        DriverCommands::SettingError(self).to_bytes()
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::Notify;
+
+// where the util config manager keeps the profiles
+pub fn profiles_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("mad-rust").join("mmo7_profiles.json"))
+}
+
+// notified on each change of the profiles file, none when it can't be watched
+pub fn watch_profiles(profiles_notify: Arc<Notify>) -> Option<RecommendedWatcher> {
+    let path = profiles_path()?;
+    let file_name = path.file_name()?.to_os_string();
+    let parent = path.parent()?.to_path_buf();
+    let watcher = recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(&file_name))
+            {
+                profiles_notify.notify_one();
+            }
+        }
+    })
+    .and_then(|mut watcher| {
+        // the directory is watched as editors often replace the file instead of writing to it
+        watcher
+            .watch(&parent, RecursiveMode::NonRecursive)
+            .map(|_| watcher)
+    });
+
+    match watcher {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            println!("Profiles file not watched : {}", err);
+            None
+        }
+    }
+}
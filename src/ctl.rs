@@ -0,0 +1,135 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::api::DeviceInfo;
+
+const USAGE: &str = "usage : mad-rust-mmo7-driver ctl <command>
+
+commands :
+    devices                           list the known devices
+    profile set <serial> <profile>    switch the active profile
+    macro test <tokens> [serial]      play a token string, on the first connected device by default
+    reload                            reload the profiles file";
+
+// talks to the running driver through its REST API, returns false on failure
+pub fn run_ctl(args: &[String], api_port: Option<u16>) -> bool {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let api_port = match api_port {
+        Some(api_port) => api_port,
+        None => {
+            println!("The REST API is disabled, set \"api_port\" in the mmo7_settings config file");
+            return false;
+        }
+    };
+    let result = match args.as_slice() {
+        ["devices"] => list_devices(api_port),
+        ["profile", "set", serial_number, profile] => request(
+            api_port,
+            "PUT",
+            &format!("/devices/{}/active_profile", serial_number),
+            profile,
+        )
+        .map(|active_profile| {
+            let active_profile = serde_json::from_str::<Option<String>>(&active_profile)
+                .ok()
+                .flatten();
+
+            println!(
+                "Active profile : {}",
+                active_profile.as_deref().unwrap_or("default")
+            );
+        }),
+        ["macro", "test", tokens] => first_connected_device(api_port)
+            .and_then(|serial_number| play_macro(api_port, &serial_number, tokens)),
+        ["macro", "test", tokens, serial_number] => play_macro(api_port, serial_number, tokens),
+        ["reload"] => request(api_port, "POST", "/reload", "").map(|_| ()),
+        _ => {
+            println!("{}", USAGE);
+            return false;
+        }
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            println!("{}", err);
+            false
+        }
+    }
+}
+
+fn list_devices(api_port: u16) -> io::Result<()> {
+    for device_info in devices(api_port)? {
+        println!(
+            "{}\t{}\t{}\t{}",
+            device_info.serial_number,
+            device_info.model.as_deref().unwrap_or("MMO7"),
+            if device_info.connected {
+                "connected"
+            } else {
+                "disconnected"
+            },
+            device_info.active_profile.as_deref().unwrap_or("default"),
+        );
+    }
+
+    Ok(())
+}
+
+fn devices(api_port: u16) -> io::Result<Vec<DeviceInfo>> {
+    let body = request(api_port, "GET", "/devices", "")?;
+
+    serde_json::from_str(&body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn first_connected_device(api_port: u16) -> io::Result<String> {
+    devices(api_port)?
+        .into_iter()
+        .find(|device_info| device_info.connected)
+        .map(|device_info| device_info.serial_number)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no connected device"))
+}
+
+fn play_macro(api_port: u16, serial_number: &str, tokens: &str) -> io::Result<()> {
+    request(
+        api_port,
+        "POST",
+        &format!("/devices/{}/macro", serial_number),
+        tokens,
+    )
+    .map(|_| ())
+}
+
+// plain http/1.1 request, the api closes the connection after each response
+fn request(api_port: u16, method: &str, path: &str, body: &str) -> io::Result<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", api_port))?;
+    let mut response = String::new();
+
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )?;
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status_code = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status_code| status_code.parse::<u16>().ok())
+        .unwrap_or_default();
+
+    if status_code == 200 {
+        Ok(body.to_string())
+    } else {
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|value| value["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("request failed with status {}", status_code));
+
+        Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set by the `--daemon` flag, systemd isn't notified otherwise
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+// tells systemd the state of the service, like `READY=1`, when started with `Type=notify`
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let path = match std::env::var("NOTIFY_SOCKET") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    // abstract sockets start with a `@`
+    let address = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name),
+        None => SocketAddr::from_pathname(&path),
+    };
+
+    if let (Ok(socket), Ok(address)) = (UnixDatagram::unbound(), address) {
+        if let Err(err) = socket.send_to_addr(state.as_bytes(), &address) {
+            println!("systemd not notified : {}", err);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
+
+// resolves on SIGTERM, or ctrl-c
+pub async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = sigterm.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+
+            return;
+        }
+    }
+
+    tokio::signal::ctrl_c().await.ok();
+}
+
+// systemd user unit starting the current executable with the graphical session
+pub fn unit_file() -> String {
+    let executable = std::env::current_exe()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "/usr/bin/mad-rust-mmo7-driver".to_string());
+
+    format!(
+        "[Unit]\n\
+        Description=Mad Rust - MMO7 Driver\n\
+        After=graphical-session.target\n\
+        PartOf=graphical-session.target\n\
+        \n\
+        [Service]\n\
+        Type=notify\n\
+        ExecStart=\"{}\" --daemon\n\
+        Restart=on-failure\n\
+        \n\
+        [Install]\n\
+        WantedBy=graphical-session.target\n",
+        executable
+    )
+}
@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::tokenizer::ProfileSwitch;
+use crate::websocket::{DriverEvent, EventSender};
+use crate::{ConnectedDevices, MousesConfig};
+
+use tokio::sync::broadcast::error::RecvError;
+use util::config::ConfigManager;
+use util::thread::MutexTrait;
+use zbus::fdo::{Error, Result};
+use zbus::{dbus_interface, Connection, ConnectionBuilder, SignalContext};
+
+const NAME: &str = "org.madrust.MMO7";
+const PATH: &str = "/org/madrust/MMO7";
+
+#[derive(Clone)]
+pub struct DbusService {
+    pub device_list_mutex: Arc<Mutex<ConnectedDevices>>,
+    pub mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    pub mouses_config_state_id: Arc<AtomicU32>,
+}
+
+// d-bus has no optional values, the default profile is an empty string
+#[dbus_interface(name = "org.madrust.MMO7")]
+impl DbusService {
+    // serial number, model, connection state and active profile of the known devices
+    async fn devices(&self) -> Vec<(String, String, bool, String)> {
+        // the device list is released before awaiting the config, as the device detection
+        // locks them the other way around
+        let connected_vec: Vec<String> = self
+            .device_list_mutex
+            .lock_poisoned()
+            .keys()
+            .cloned()
+            .collect();
+        let mouses_config = self.mouses_config_mutex.lock().await;
+
+        mouses_config
+            .config
+            .iter()
+            .map(|(serial_number, mouse_config)| {
+                (
+                    serial_number.clone(),
+                    mouse_config.device_model().name.to_string(),
+                    connected_vec.contains(serial_number),
+                    mouse_config.active_profile.clone().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    async fn profiles(&self, serial_number: &str) -> Result<Vec<String>> {
+        let mouses_config = self.mouses_config_mutex.lock().await;
+
+        match mouses_config.config.get(serial_number) {
+            Some(mouse_config) => Ok(mouse_config.profiles.keys().cloned().collect()),
+            None => Err(unknown_device()),
+        }
+    }
+
+    // takes the same values as the profile token, returns the new active profile
+    async fn set_profile(&self, serial_number: &str, profile: &str) -> Result<String> {
+        let mut mouses_config = self.mouses_config_mutex.lock().await;
+
+        match mouses_config.config.get_mut(serial_number) {
+            Some(mouse_config) => {
+                mouse_config.switch_profile(&ProfileSwitch::parse(profile));
+                self.mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+
+                Ok(mouse_config.active_profile.clone().unwrap_or_default())
+            }
+            None => Err(unknown_device()),
+        }
+    }
+
+    #[dbus_interface(signal)]
+    async fn device_connected(context: &SignalContext<'_>, serial_number: &str)
+        -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn device_disconnected(
+        context: &SignalContext<'_>,
+        serial_number: &str,
+    ) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn profile_changed(
+        context: &SignalContext<'_>,
+        serial_number: &str,
+        profile: &str,
+    ) -> zbus::Result<()>;
+}
+
+fn unknown_device() -> Error {
+    Error::InvalidArgs("unknown device".to_string())
+}
+
+// the system bus is used when running as a service, the session bus otherwise
+pub async fn run_dbus(dbus_service: DbusService, event_tx: EventSender) {
+    let connection = match connect(dbus_service).await {
+        Ok(connection) => connection,
+        Err(err) => {
+            println!("D-Bus service not started : {}", err);
+            return;
+        }
+    };
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        let context = match SignalContext::new(&connection, PATH) {
+            Ok(context) => context,
+            Err(_) => return,
+        };
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+
+            match event {
+                DriverEvent::Device {
+                    serial_number,
+                    connected: true,
+                } => DbusService::device_connected(&context, &serial_number).await,
+                DriverEvent::Device {
+                    serial_number,
+                    connected: false,
+                } => DbusService::device_disconnected(&context, &serial_number).await,
+                DriverEvent::Profile {
+                    serial_number,
+                    profile,
+                } => {
+                    DbusService::profile_changed(
+                        &context,
+                        &serial_number,
+                        &profile.unwrap_or_default(),
+                    )
+                    .await
+                }
+                _ => Ok(()),
+            }
+            .ok();
+        }
+    });
+}
+
+async fn connect(dbus_service: DbusService) -> zbus::Result<Connection> {
+    match ConnectionBuilder::system()?
+        .name(NAME)?
+        .serve_at(PATH, dbus_service.clone())?
+        .build()
+        .await
+    {
+        Ok(connection) => Ok(connection),
+        Err(_) => {
+            ConnectionBuilder::session()?
+                .name(NAME)?
+                .serve_at(PATH, dbus_service)?
+                .build()
+                .await
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use rusb::{request_type, DeviceHandle, Direction, Recipient, RequestType, UsbContext};
+use util::time::TIMEOUT_1S;
+
+use crate::command::DeviceInfo;
+use crate::model::DeviceModel;
+
+// standard request reading the device descriptor, which holds the firmware release
+const GET_DESCRIPTOR: u8 = 0x06;
+const DEVICE_DESCRIPTOR: u16 = 0x0100;
+const DEVICE_DESCRIPTOR_LENGTH: usize = 18;
+// bcdDevice field of the device descriptor
+const RELEASE_OFFSET: usize = 12;
+
+// firmware release in binary coded decimal, like `1.05`
+fn read_firmware_version<T: UsbContext>(device_handle: &DeviceHandle<T>) -> rusb::Result<String> {
+    let mut data = [0; DEVICE_DESCRIPTOR_LENGTH];
+    let length = device_handle.read_control(
+        request_type(Direction::In, RequestType::Standard, Recipient::Device),
+        GET_DESCRIPTOR,
+        DEVICE_DESCRIPTOR,
+        0,
+        &mut data,
+        TIMEOUT_1S,
+    )?;
+
+    if length < RELEASE_OFFSET + 2 {
+        return Err(rusb::Error::Other);
+    }
+
+    Ok(format!(
+        "{:x}.{:02x}",
+        data[RELEASE_OFFSET + 1],
+        data[RELEASE_OFFSET]
+    ))
+}
+
+pub fn read<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    serial_number: &str,
+) -> rusb::Result<DeviceInfo> {
+    Ok(DeviceInfo::new(
+        serial_number.to_string(),
+        device_model.name.to_string(),
+        read_firmware_version(device_handle)?,
+    ))
+}
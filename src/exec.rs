@@ -0,0 +1,110 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+use crate::window;
+
+static ALLOWLIST: OnceLock<Vec<String>> = OnceLock::new();
+
+// programs the bindings may run, none until called, as shared profiles could run anything
+pub fn configure(allowlist: Vec<String>) {
+    ALLOWLIST.set(allowlist).ok();
+}
+
+// a name only allows the program found in the path, a path only this exact program
+pub fn is_allowed(program: &str) -> bool {
+    ALLOWLIST
+        .get()
+        .map(|allowlist| allowlist.iter().any(|allowed| allowed == program))
+        .unwrap_or(false)
+}
+
+// `"program arg 'quoted arg'"`, the outer double quotes being optional
+pub fn split_command_line(command_line: &str) -> Vec<String> {
+    let command_line = command_line.trim();
+    let command_line = command_line
+        .strip_prefix('"')
+        .and_then(|command_line| command_line.strip_suffix('"'))
+        .unwrap_or(command_line);
+    let mut arg_vec = vec![];
+    let mut arg = String::new();
+    let mut quote = None;
+    let mut in_arg = false;
+
+    for char in command_line.chars() {
+        match (char, quote) {
+            ('\'' | '"', None) => {
+                quote = Some(char);
+                in_arg = true;
+            }
+            (char, Some(quote_char)) if char == quote_char => quote = None,
+            (char, None) if char.is_whitespace() => {
+                if in_arg {
+                    arg_vec.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            (char, _) => {
+                arg.push(char);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        arg_vec.push(arg);
+    }
+
+    arg_vec
+}
+
+// checked against the allowlist, as shared profiles could run anything
+pub fn run(command_line: &str) {
+    let arg_vec = split_command_line(command_line);
+    let (program, args) = match arg_vec.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    if !is_allowed(program) {
+        println!("Command not run : {} isn't in the exec allowlist", program);
+        return;
+    }
+
+    if let Err(err) = spawn(program, args, vec![]) {
+        println!("Command not run : {} {}", program, err);
+    }
+}
+
+// spawned without waiting for it, the process being reaped by its own thread once exited
+pub fn spawn(program: &str, args: &[String], envs: Vec<(&str, String)>) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(envs)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    thread::spawn(move || child.wait().ok());
+
+    Ok(())
+}
+
+// focuses the window of the program when it's already running, matched by the name of the program
+// against the window classes and titles, and runs the command otherwise
+pub fn launch(command_line: &str) {
+    let arg_vec = split_command_line(command_line);
+    let program_name = arg_vec
+        .first()
+        .and_then(|program| Path::new(program).file_stem())
+        .map(|file_stem| file_stem.to_string_lossy().to_string());
+
+    if let Some(program_name) = program_name {
+        if !window::focus_window(&program_name) {
+            run(command_line);
+        }
+    }
+}
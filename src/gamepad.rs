@@ -0,0 +1,235 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftShoulder,
+    RightShoulder,
+    Back,
+    Start,
+    Guide,
+    LeftThumb,
+    RightThumb,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadInput {
+    Button(GamepadButton),
+    // value between -1 and 1, y pointing up, triggers only going from 0 to 1
+    Axis(GamepadAxis, f32),
+}
+
+impl GamepadInput {
+    // `a`, `lb`, `up`... for the buttons, `lx:-100`, `rt:50`... for the axes in percent
+    pub fn parse(string: &str) -> Option<Self> {
+        let (name, value) = string.split_once(':').unwrap_or((string, "100"));
+        let button = match name.trim().to_lowercase().as_str() {
+            "a" => GamepadButton::A,
+            "b" => GamepadButton::B,
+            "x" => GamepadButton::X,
+            "y" => GamepadButton::Y,
+            "lb" => GamepadButton::LeftShoulder,
+            "rb" => GamepadButton::RightShoulder,
+            "back" => GamepadButton::Back,
+            "start" => GamepadButton::Start,
+            "guide" => GamepadButton::Guide,
+            "ls" => GamepadButton::LeftThumb,
+            "rs" => GamepadButton::RightThumb,
+            "up" => GamepadButton::DpadUp,
+            "down" => GamepadButton::DpadDown,
+            "left" => GamepadButton::DpadLeft,
+            "right" => GamepadButton::DpadRight,
+            name => {
+                let axis = match name {
+                    "lx" => GamepadAxis::LeftX,
+                    "ly" => GamepadAxis::LeftY,
+                    "rx" => GamepadAxis::RightX,
+                    "ry" => GamepadAxis::RightY,
+                    "lt" => GamepadAxis::LeftTrigger,
+                    "rt" => GamepadAxis::RightTrigger,
+                    _ => return None,
+                };
+                let minimum = match axis {
+                    GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => 0.0,
+                    _ => -1.0,
+                };
+                let value = value.trim().parse::<f32>().ok()? / 100.0;
+
+                return Some(Self::Axis(axis, value.clamp(minimum, 1.0)));
+            }
+        };
+
+        Some(Self::Button(button))
+    }
+}
+
+// the virtual controller is only plugged in on the first gamepad input
+#[derive(Default)]
+pub struct Gamepad {
+    device: Option<platform::Device>,
+    unavailable: bool,
+}
+
+impl Gamepad {
+    // a released axis goes back to its center
+    pub fn input(&mut self, input: GamepadInput, pressed: bool) {
+        if self.device.is_none() && !self.unavailable {
+            match platform::Device::new() {
+                Ok(device) => self.device = Some(device),
+                Err(err) => {
+                    println!("Virtual gamepad unavailable : {}", err);
+                    self.unavailable = true;
+                }
+            }
+        }
+
+        if let Some(device) = &mut self.device {
+            match input {
+                GamepadInput::Button(button) => device.button(button, pressed),
+                GamepadInput::Axis(axis, value) => {
+                    device.axis(axis, if pressed { value } else { 0.0 })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+
+    use super::{GamepadAxis, GamepadButton};
+    use crate::keycode::{gamepad_axis_event, gamepad_button_code, EV_ABS, EV_KEY};
+    use crate::uinput::UinputDevice;
+
+    pub struct Device(UinputDevice);
+
+    impl Device {
+        pub fn new() -> io::Result<Self> {
+            UinputDevice::new_gamepad().map(Self)
+        }
+
+        pub fn button(&mut self, button: GamepadButton, pressed: bool) {
+            self.0
+                .emit(&[(EV_KEY, gamepad_button_code(button), pressed as i32)]);
+        }
+
+        pub fn axis(&mut self, axis: GamepadAxis, value: f32) {
+            let (code, value) = gamepad_axis_event(axis, value);
+
+            self.0.emit(&[(EV_ABS, code, value)]);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{GamepadAxis, GamepadButton};
+
+    use vigem_client::{Client, TargetId, XGamepad, Xbox360Wired};
+
+    // xinput button masks
+    fn button_mask(button: GamepadButton) -> u16 {
+        match button {
+            GamepadButton::DpadUp => 0x0001,
+            GamepadButton::DpadDown => 0x0002,
+            GamepadButton::DpadLeft => 0x0004,
+            GamepadButton::DpadRight => 0x0008,
+            GamepadButton::Start => 0x0010,
+            GamepadButton::Back => 0x0020,
+            GamepadButton::LeftThumb => 0x0040,
+            GamepadButton::RightThumb => 0x0080,
+            GamepadButton::LeftShoulder => 0x0100,
+            GamepadButton::RightShoulder => 0x0200,
+            GamepadButton::Guide => 0x0400,
+            GamepadButton::A => 0x1000,
+            GamepadButton::B => 0x2000,
+            GamepadButton::X => 0x4000,
+            GamepadButton::Y => 0x8000,
+        }
+    }
+
+    // the whole controller state is sent on each change
+    pub struct Device {
+        target: Xbox360Wired<Client>,
+        state: XGamepad,
+    }
+
+    impl Device {
+        pub fn new() -> Result<Self, vigem_client::Error> {
+            let mut target = Xbox360Wired::new(Client::connect()?, TargetId::XBOX360_WIRED);
+
+            target.plugin()?;
+            target.wait_ready()?;
+
+            Ok(Self {
+                target,
+                state: XGamepad::default(),
+            })
+        }
+
+        pub fn button(&mut self, button: GamepadButton, pressed: bool) {
+            if pressed {
+                self.state.buttons.raw |= button_mask(button);
+            } else {
+                self.state.buttons.raw &= !button_mask(button);
+            }
+
+            self.update();
+        }
+
+        pub fn axis(&mut self, axis: GamepadAxis, value: f32) {
+            let thumb = (value * i16::MAX as f32) as i16;
+            let trigger = (value * u8::MAX as f32) as u8;
+
+            match axis {
+                GamepadAxis::LeftX => self.state.thumb_lx = thumb,
+                GamepadAxis::LeftY => self.state.thumb_ly = thumb,
+                GamepadAxis::RightX => self.state.thumb_rx = thumb,
+                GamepadAxis::RightY => self.state.thumb_ry = thumb,
+                GamepadAxis::LeftTrigger => self.state.left_trigger = trigger,
+                GamepadAxis::RightTrigger => self.state.right_trigger = trigger,
+            }
+
+            self.update();
+        }
+
+        fn update(&mut self) {
+            if let Err(err) = self.target.update(&self.state) {
+                println!("Virtual gamepad update error : {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::{GamepadAxis, GamepadButton};
+
+    pub struct Device;
+
+    impl Device {
+        pub fn new() -> Result<Self, &'static str> {
+            Err("not supported on this platform")
+        }
+
+        pub fn button(&mut self, _button: GamepadButton, _pressed: bool) {}
+
+        pub fn axis(&mut self, _axis: GamepadAxis, _value: f32) {}
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use util::thread::MutexTrait;
+
+// stock buttons the os sees too when a mouse is still driven by its hid driver on windows
+pub const MIDDLE: u8 = 1;
+pub const BACK: u8 = 1 << 1;
+pub const FORWARD: u8 = 1 << 2;
+pub const WHEEL_UP: u8 = 1 << 3;
+pub const WHEEL_DOWN: u8 = 1 << 4;
+pub const LEFT: u8 = 1 << 5;
+pub const RIGHT: u8 = 1 << 6;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// buttons with a binding, keyed by serial number
+static BUTTON_MASK_MAP: Mutex<BTreeMap<String, u8>> = Mutex::new(BTreeMap::new());
+static SUPPRESSED: AtomicU8 = AtomicU8::new(0);
+
+// disabled until called, the original events of every mouse are suppressed for the buttons
+// with a binding, as the hook can't tell the mice apart
+pub fn configure(exclusive_grab: bool) {
+    if exclusive_grab && !ENABLED.swap(true, Ordering::SeqCst) {
+        platform::install_hook();
+    }
+}
+
+// the buttons with a binding in the current mode of the device, none once it's gone
+pub fn suppress(serial_number: &str, button_mask: u8) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut button_mask_map = BUTTON_MASK_MAP.lock_poisoned();
+
+    if button_mask_map.get(serial_number) == Some(&button_mask) {
+        return;
+    }
+
+    if button_mask == 0 {
+        button_mask_map.remove(serial_number);
+    } else {
+        button_mask_map.insert(serial_number.to_string(), button_mask);
+    }
+
+    SUPPRESSED.store(
+        button_mask_map
+            .values()
+            .fold(0, |suppressed, button_mask| suppressed | button_mask),
+        Ordering::Relaxed,
+    );
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ptr::null;
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::thread::spawn;
+
+    use windows_sys::Win32::Foundation::{GetLastError, LPARAM, LRESULT, WPARAM};
+    use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CallNextHookEx, GetMessageW, SetWindowsHookExW, HC_ACTION, LLMHF_INJECTED, MSG,
+        MSLLHOOKSTRUCT, WH_MOUSE_LL, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
+        WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1,
+        XBUTTON2,
+    };
+
+    use super::{BACK, FORWARD, LEFT, MIDDLE, RIGHT, SUPPRESSED, WHEEL_DOWN, WHEEL_UP};
+
+    // buttons whose press was suppressed, so their release is too
+    static HELD: AtomicU8 = AtomicU8::new(0);
+
+    // pressed is none for the wheel, a release being suppressed only when its press was,
+    // so a binding added while the button is held doesn't leave it pressed
+    fn is_suppressed(button: u8, pressed: Option<bool>) -> bool {
+        match pressed {
+            Some(true) if SUPPRESSED.load(Ordering::Relaxed) & button != 0 => {
+                HELD.fetch_or(button, Ordering::Relaxed);
+                true
+            }
+            Some(true) => false,
+            Some(false) => HELD.fetch_and(!button, Ordering::Relaxed) & button != 0,
+            None => SUPPRESSED.load(Ordering::Relaxed) & button != 0,
+        }
+    }
+
+    // the hook is called from the message loop of the thread installing it
+    pub fn install_hook() {
+        spawn(|| unsafe {
+            let hook =
+                SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook), GetModuleHandleW(null()), 0);
+
+            if hook == 0 {
+                println!("Exclusive grab unavailable : error {}", GetLastError());
+                return;
+            }
+
+            let mut message: MSG = std::mem::zeroed();
+
+            while GetMessageW(&mut message, 0, 0, 0) > 0 {}
+        });
+    }
+
+    // the events sent by the driver are injected, so only the original ones are suppressed
+    unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 {
+            let hook_struct = &*(lparam as *const MSLLHOOKSTRUCT);
+            let high_word = (hook_struct.mouseData >> 16) as u16;
+            let button = match wparam as u32 {
+                WM_LBUTTONDOWN => Some((LEFT, Some(true))),
+                WM_LBUTTONUP => Some((LEFT, Some(false))),
+                WM_RBUTTONDOWN => Some((RIGHT, Some(true))),
+                WM_RBUTTONUP => Some((RIGHT, Some(false))),
+                WM_MBUTTONDOWN => Some((MIDDLE, Some(true))),
+                WM_MBUTTONUP => Some((MIDDLE, Some(false))),
+                WM_XBUTTONDOWN | WM_XBUTTONUP => match high_word {
+                    XBUTTON1 => Some((BACK, Some(wparam as u32 == WM_XBUTTONDOWN))),
+                    XBUTTON2 => Some((FORWARD, Some(wparam as u32 == WM_XBUTTONDOWN))),
+                    _ => None,
+                },
+                WM_MOUSEWHEEL if high_word as i16 > 0 => Some((WHEEL_UP, None)),
+                WM_MOUSEWHEEL => Some((WHEEL_DOWN, None)),
+                _ => None,
+            };
+
+            if hook_struct.flags & LLMHF_INJECTED == 0 {
+                if let Some((button, pressed)) = button {
+                    if is_suppressed(button, pressed) {
+                        return 1;
+                    }
+                }
+            }
+        }
+
+        CallNextHookEx(0, code, wparam, lparam)
+    }
+}
+
+// the kernel driver of the mouse is detached on linux, the os never seeing its events
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    pub fn install_hook() {
+        println!("Exclusive grab only needed on windows");
+    }
+}
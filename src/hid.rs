@@ -0,0 +1,45 @@
+use rusb::{request_type, DeviceHandle, Direction, Recipient, RequestType, UsbContext};
+use util::time::TIMEOUT_1S;
+
+// hid class requests
+const GET_REPORT: u8 = 0x01;
+const SET_REPORT: u8 = 0x09;
+const FEATURE_REPORT: u16 = 0x0300;
+
+// the first byte of data is the report id
+pub fn set_feature_report<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    iface: u8,
+    data: &[u8],
+) -> rusb::Result<usize> {
+    device_handle.write_control(
+        request_type(Direction::Out, RequestType::Class, Recipient::Interface),
+        SET_REPORT,
+        FEATURE_REPORT | data[0] as u16,
+        iface as u16,
+        data,
+        TIMEOUT_1S,
+    )
+}
+
+// the report id is kept as the first byte of the returned data
+pub fn get_feature_report<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    iface: u8,
+    report_id: u8,
+    length: usize,
+) -> rusb::Result<Vec<u8>> {
+    let mut data = vec![0; length];
+    let length = device_handle.read_control(
+        request_type(Direction::In, RequestType::Class, Recipient::Interface),
+        GET_REPORT,
+        FEATURE_REPORT | report_id as u16,
+        iface as u16,
+        &mut data,
+        TIMEOUT_1S,
+    )?;
+
+    data.truncate(length);
+
+    Ok(data)
+}
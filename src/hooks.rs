@@ -0,0 +1,95 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::exec::{spawn, split_command_line};
+use crate::websocket::{DriverEvent, EventSender};
+
+// command lines run on the events of the mice, nothing being run when unset
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct HookSettings {
+    #[serde(default)]
+    pub on_device_connect: Option<String>,
+    #[serde(default)]
+    pub on_profile_switch: Option<String>,
+    #[serde(default)]
+    pub on_mode_change: Option<String>,
+}
+
+// the event is described to the command by environment variables, so any script can react to it
+pub fn run_hooks(hook_settings: HookSettings, event_tx: EventSender) {
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        // devices whose first profile event, sent once connected, isn't a switch yet
+        let mut connecting_set = BTreeSet::new();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let (command_line, env_vec) = match event {
+                DriverEvent::Device {
+                    serial_number,
+                    connected: true,
+                } => {
+                    connecting_set.insert(serial_number.clone());
+
+                    (
+                        &hook_settings.on_device_connect,
+                        vec![("MMO7_SERIAL", serial_number)],
+                    )
+                }
+                DriverEvent::Profile { serial_number, .. }
+                    if connecting_set.remove(&serial_number) =>
+                {
+                    continue
+                }
+                DriverEvent::Profile {
+                    serial_number,
+                    profile,
+                } => (
+                    &hook_settings.on_profile_switch,
+                    vec![
+                        ("MMO7_SERIAL", serial_number),
+                        // empty for the default profile
+                        ("MMO7_PROFILE", profile.unwrap_or_default()),
+                    ],
+                ),
+                DriverEvent::Mode {
+                    serial_number,
+                    mode,
+                    shift,
+                } => (
+                    &hook_settings.on_mode_change,
+                    vec![
+                        ("MMO7_SERIAL", serial_number),
+                        ("MMO7_MODE", (mode + 1).to_string()),
+                        ("MMO7_SHIFT", (shift as u8).to_string()),
+                    ],
+                ),
+                _ => continue,
+            };
+
+            if let Some(command_line) = command_line {
+                run(command_line, env_vec);
+            }
+        }
+    });
+}
+
+// not limited by the exec allowlist, the settings being written by the user, unlike shared profiles
+fn run(command_line: &str, env_vec: Vec<(&str, String)>) {
+    let arg_vec = split_command_line(command_line);
+    let (program, args) = match arg_vec.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    if let Err(err) = spawn(program, args, env_vec) {
+        println!("Hook not run : {} {}", program, err);
+    }
+}
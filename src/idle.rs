@@ -0,0 +1,176 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// read timeout while suspended, a report still wakes the thread up right away
+const SUSPENDED_READ_TIMEOUT: Duration = Duration::from_secs(1);
+// the os input is only checked this often once the mouse is idle
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+static IDLE_DELAY: OnceLock<Duration> = OnceLock::new();
+
+// disabled until called
+pub fn configure(idle_suspend_minutes: Option<u64>) {
+    if let Some(minutes) = idle_suspend_minutes.filter(|minutes| *minutes > 0) {
+        IDLE_DELAY.set(Duration::from_secs(minutes * 60)).ok();
+    }
+}
+
+// lengthens the read timeout of a device once neither the mouse nor the other inputs of the
+// system were used for the idle delay, to cut the wakeups on laptops
+pub struct IdleTracker {
+    last_report: Instant,
+    last_check: Option<Instant>,
+    user_idle: UserIdle,
+    suspended: bool,
+}
+
+impl Default for IdleTracker {
+    fn default() -> Self {
+        Self {
+            last_report: Instant::now(),
+            last_check: None,
+            user_idle: UserIdle::default(),
+            suspended: false,
+        }
+    }
+}
+
+impl IdleTracker {
+    // true when the report resumed a suspended device
+    pub fn report(&mut self) -> bool {
+        self.last_report = Instant::now();
+        self.last_check = None;
+
+        std::mem::take(&mut self.suspended)
+    }
+
+    // the mapper has to be idle too, so held and latched bindings keep being played,
+    // true when the device was just suspended
+    pub fn update(&mut self, mapper_is_idle: bool) -> bool {
+        let idle_delay = match IDLE_DELAY.get() {
+            Some(idle_delay) => *idle_delay,
+            None => return false,
+        };
+
+        if !mapper_is_idle {
+            self.suspended = false;
+            return false;
+        }
+
+        if self.suspended
+            || self.last_report.elapsed() < idle_delay
+            || self
+                .last_check
+                .map(|last_check| last_check.elapsed() < CHECK_INTERVAL)
+                .unwrap_or(false)
+        {
+            return false;
+        }
+
+        self.last_check = Some(Instant::now());
+        // sessions where it can't be read only rely on the reports
+        self.suspended = self
+            .user_idle
+            .idle_time()
+            .map(|idle_time| idle_time >= idle_delay)
+            .unwrap_or(true);
+        self.suspended
+    }
+
+    pub fn read_timeout(&self, read_timeout: Duration) -> Duration {
+        if self.suspended {
+            SUSPENDED_READ_TIMEOUT
+        } else {
+            read_timeout
+        }
+    }
+}
+
+use platform::UserIdle;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::time::Duration;
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::screensaver::ConnectionExt;
+    use x11rb::protocol::xproto::Window;
+    use x11rb::rust_connection::RustConnection;
+
+    // time since the last input of the X11 session, through the screensaver extension,
+    // wayland compositors don't expose it
+    #[derive(Default)]
+    pub struct UserIdle {
+        x11: Option<(RustConnection, Window)>,
+    }
+
+    impl UserIdle {
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            if self.x11.is_none() {
+                let (connection, screen_num) = x11rb::connect(None).ok()?;
+                let root = connection.setup().roots.get(screen_num)?.root;
+
+                self.x11 = Some((connection, root));
+            }
+
+            let (connection, root) = self.x11.as_ref()?;
+            let idle_time = connection
+                .screensaver_query_info(*root)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .map(|reply| Duration::from_millis(reply.ms_since_user_input as u64));
+
+            // the connection might be broken, retry on next call
+            if idle_time.is_none() {
+                self.x11 = None;
+            }
+
+            idle_time
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::mem::size_of;
+    use std::time::Duration;
+
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    #[derive(Default)]
+    pub struct UserIdle;
+
+    impl UserIdle {
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            unsafe {
+                let mut last_input_info = LASTINPUTINFO {
+                    cbSize: size_of::<LASTINPUTINFO>() as u32,
+                    dwTime: 0,
+                };
+
+                if GetLastInputInfo(&mut last_input_info) == 0 {
+                    return None;
+                }
+
+                Some(Duration::from_millis(
+                    GetTickCount().wrapping_sub(last_input_info.dwTime) as u64,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::time::Duration;
+
+    #[derive(Default)]
+    pub struct UserIdle;
+
+    impl UserIdle {
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            None
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// evdev codes from linux/input-event-codes.h, shared by the linux backends
+use enigo::Key;
+
+use crate::gamepad::{GamepadAxis, GamepadButton};
+use crate::scancode::char_to_scancode;
+use crate::tokenizer::Button;
+
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_ABS: u16 = 0x03;
+pub const SYN_REPORT: u16 = 0x00;
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const REL_HWHEEL: u16 = 0x06;
+pub const REL_WHEEL: u16 = 0x08;
+pub const REL_WHEEL_HI_RES: u16 = 0x0b;
+pub const REL_HWHEEL_HI_RES: u16 = 0x0c;
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+pub const BTN_SIDE: u16 = 0x113;
+pub const BTN_EXTRA: u16 = 0x114;
+pub const KEY_LEFTCTRL: u16 = 29;
+pub const KEY_LEFTSHIFT: u16 = 42;
+pub const KEY_LEFTALT: u16 = 56;
+pub const KEY_LEFTMETA: u16 = 125;
+pub const KEY_V: u16 = 47;
+// the keyboard keys, starting at KEY_ESC
+pub const KEY_RANGE: std::ops::Range<u16> = 1..128;
+pub const GAMEPAD_BUTTONS: [u16; 15] = [
+    0x130, 0x131, 0x133, 0x134, 0x136, 0x137, 0x13a, 0x13b, 0x13c, 0x13d, 0x13e, 0x220, 0x221,
+    0x222, 0x223,
+];
+// code, minimum and maximum of the sticks then the triggers
+pub const GAMEPAD_AXES: [(u16, i32, i32); 6] = [
+    (0x00, -32768, 32767),
+    (0x01, -32768, 32767),
+    (0x03, -32768, 32767),
+    (0x04, -32768, 32767),
+    (0x02, 0, 255),
+    (0x05, 0, 255),
+];
+
+pub fn button_code(button: Button) -> Option<u16> {
+    match button {
+        Button::Left => Some(BTN_LEFT),
+        Button::Right => Some(BTN_RIGHT),
+        Button::Middle => Some(BTN_MIDDLE),
+        Button::Back => Some(BTN_SIDE),
+        Button::Forward => Some(BTN_EXTRA),
+        _ => None,
+    }
+}
+
+pub fn key_code(key: Key) -> Option<u16> {
+    match key {
+        Key::Shift => Some(KEY_LEFTSHIFT),
+        Key::Control => Some(KEY_LEFTCTRL),
+        Key::Alt => Some(KEY_LEFTALT),
+        Key::Meta => Some(KEY_LEFTMETA),
+        Key::Escape => Some(1),
+        Key::Backspace => Some(14),
+        Key::Tab => Some(15),
+        Key::Return => Some(28),
+        Key::Space => Some(57),
+        Key::CapsLock => Some(58),
+        Key::F1 => Some(59),
+        Key::F2 => Some(60),
+        Key::F3 => Some(61),
+        Key::F4 => Some(62),
+        Key::F5 => Some(63),
+        Key::F6 => Some(64),
+        Key::F7 => Some(65),
+        Key::F8 => Some(66),
+        Key::F9 => Some(67),
+        Key::F10 => Some(68),
+        Key::F11 => Some(87),
+        Key::F12 => Some(88),
+        Key::Home => Some(102),
+        Key::UpArrow => Some(103),
+        Key::PageUp => Some(104),
+        Key::LeftArrow => Some(105),
+        Key::RightArrow => Some(106),
+        Key::End => Some(107),
+        Key::DownArrow => Some(108),
+        Key::PageDown => Some(109),
+        Key::Delete => Some(111),
+        Key::Layout(char) => char_to_scancode(char).map(|(code, _)| code),
+        _ => None,
+    }
+}
+
+// in the order of GAMEPAD_BUTTONS, named like the xpad driver does
+pub fn gamepad_button_code(button: GamepadButton) -> u16 {
+    GAMEPAD_BUTTONS[match button {
+        GamepadButton::A => 0,
+        GamepadButton::B => 1,
+        GamepadButton::X => 2,
+        GamepadButton::Y => 3,
+        GamepadButton::LeftShoulder => 4,
+        GamepadButton::RightShoulder => 5,
+        GamepadButton::Back => 6,
+        GamepadButton::Start => 7,
+        GamepadButton::Guide => 8,
+        GamepadButton::LeftThumb => 9,
+        GamepadButton::RightThumb => 10,
+        GamepadButton::DpadUp => 11,
+        GamepadButton::DpadDown => 12,
+        GamepadButton::DpadLeft => 13,
+        GamepadButton::DpadRight => 14,
+    }]
+}
+
+// code and value of the axis, the y axes pointing down
+pub fn gamepad_axis_event(axis: GamepadAxis, value: f32) -> (u16, i32) {
+    let (index, value) = match axis {
+        GamepadAxis::LeftX => (0, value),
+        GamepadAxis::LeftY => (1, -value),
+        GamepadAxis::RightX => (2, value),
+        GamepadAxis::RightY => (3, -value),
+        GamepadAxis::LeftTrigger => (4, value),
+        GamepadAxis::RightTrigger => (5, value),
+    };
+    let (code, minimum, maximum) = GAMEPAD_AXES[index];
+
+    (
+        code,
+        if value < 0.0 {
+            (-value * minimum as f32) as i32
+        } else {
+            (value * maximum as f32) as i32
+        },
+    )
+}
@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rusb::{DeviceHandle, UsbContext};
+
+use crate::hid::set_feature_report;
+use crate::model::DeviceModel;
+
+// red, green, blue
+pub type LedColor = [u8; 3];
+
+// the identify blink alternates white and off
+const BLINK_COLORS: [LedColor; 2] = [[255, 255, 255], [0, 0, 0]];
+const BLINK_DURATION: Duration = Duration::from_secs(3);
+const BLINK_INTERVAL: Duration = Duration::from_millis(250);
+
+// the led report below is a guess, no documentation or capture of the mmo7 protocol describing it,
+// so nothing is sent to the leds unless the user opts in
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(unverified_led_report: bool) {
+    ENABLED.store(unverified_led_report, Ordering::SeqCst);
+}
+
+// one feature report per mode led : report id, mode, red, green, blue
+pub fn set_led_colors<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    iface: u8,
+    led_colors: &[LedColor],
+) -> rusb::Result<()> {
+    let report_id = match device_model.led_report_id {
+        Some(report_id) if ENABLED.load(Ordering::SeqCst) => report_id,
+        _ => return Ok(()),
+    };
+
+    for (mode, [red, green, blue]) in led_colors
+        .iter()
+        .take(device_model.mode_count as usize)
+        .enumerate()
+    {
+        set_feature_report(
+            device_handle,
+            iface,
+            &[report_id, mode as u8, *red, *green, *blue],
+        )?;
+    }
+
+    Ok(())
+}
+
+// blinks all the mode leds so the user can tell which device is which,
+// ticked by the device thread between reads
+pub struct Blink {
+    start: Instant,
+    color_index: Option<usize>,
+}
+
+impl Blink {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            color_index: None,
+        }
+    }
+
+    // the leds are only set when they change color, false once the blink is over
+    pub fn tick<T: UsbContext>(
+        &mut self,
+        device_handle: &DeviceHandle<T>,
+        device_model: &DeviceModel,
+        iface: u8,
+    ) -> rusb::Result<bool> {
+        let elapsed = self.start.elapsed();
+
+        if elapsed >= BLINK_DURATION {
+            return Ok(false);
+        }
+
+        let color_index =
+            (elapsed.as_millis() / BLINK_INTERVAL.as_millis()) as usize % BLINK_COLORS.len();
+
+        if self.color_index != Some(color_index) {
+            self.color_index = Some(color_index);
+            set_led_colors(
+                device_handle,
+                device_model,
+                iface,
+                &vec![BLINK_COLORS[color_index]; device_model.mode_count as usize],
+            )?;
+        }
+
+        Ok(true)
+    }
+}
@@ -1,28 +1,140 @@
 // hide the console on release builds for windows
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod api;
+mod auth;
+mod autostart;
+mod backend;
+mod backup;
+mod bring_up;
+mod clients;
+mod clipboard;
+mod command;
+mod config_watcher;
+mod ctl;
+mod daemon;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod device_info;
+mod exec;
+mod gamepad;
+mod grab;
+mod hid;
+mod hooks;
+mod idle;
+#[cfg(target_os = "linux")]
+mod keycode;
+mod lighting;
 mod mapper;
+mod memory;
+mod metrics;
+mod midi;
+mod model;
+mod movement;
+mod mqtt;
+mod notification;
+mod osd;
+#[cfg(target_os = "linux")]
+mod permissions;
+mod plugin;
+mod polling;
+mod power;
+mod presets;
+mod process;
+mod profile_file;
+mod report_log;
+mod scancode;
+mod script;
+#[cfg(feature = "rhai")]
+mod scripting;
+#[cfg(target_os = "windows")]
+mod sendinput;
+mod sensor;
+mod settings;
+mod shared_profiles;
+mod simulate;
+mod snapshot;
+mod sound;
+mod status;
+mod sync;
+mod tokenizer;
+mod transfer;
+#[cfg(target_os = "linux")]
+mod tray;
+mod turbo;
+#[cfg(target_os = "linux")]
+mod uinput;
+mod validation;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(target_os = "linux")]
+mod wayland;
+mod websocket;
+mod window;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::spawn;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use hashbrown::HashSet;
-use mapper::Mapper;
-use rusb::{Context, DeviceHandle, UsbContext};
+use api::{run_api, ApiState};
+use autostart::run_autostart;
+use backend::BackendKind;
+use bring_up::{BringUpError, BringUpFailure};
+use clients::{forward_events, run_client_listener, ClientList, ListenerSettings};
+use command::{
+    Authentication, Backups, Capabilities, ConfigErrors, DeviceError, DeviceErrors,
+    DeviceForgotten, DeviceInfo, DeviceNicknames, DriverCommands, DriverStatus, LedColors,
+    MacroTest, OnboardMemory, PollingRate, Presets, ProfileTransfer, Profiles, ReportLog,
+    SettingError, Settings, Status, SyncState, SyncStatus, UnsupportedCommand, WindowRules,
+    PROTOCOL_VERSION,
+};
+use config_watcher::watch_profiles;
+use ctl::run_ctl;
+#[cfg(target_os = "linux")]
+use dbus::{run_dbus, DbusService};
+use hashbrown::HashMap;
+use hooks::{run_hooks, HookSettings};
+use idle::IdleTracker;
+use lighting::{Blink, LedColor};
+use mapper::{check_binding, Mapper};
+use model::DeviceModel;
+use movement::ResponseCurve;
+use mqtt::{run_mqtt, MqttSettings};
+use notification::NotificationKind;
+use osd::run_osd;
+use polling::Ticker;
+use presets::Preset;
+use process::{watch_processes, ProcessRule};
+use rusb::{Context, Device, DeviceDescriptor, DeviceHandle, UsbContext};
+use scancode::KeyMode;
 use serde::{Deserialize, Serialize};
+use settings::DeviceSettings;
+use shared_profiles::SharedProfiles;
+use simulate::run_simulation;
+use snapshot::{ConfigSnapshot, DeviceSnapshot, ProfileSwitchSender};
+use sound::{run_sound, SoundSettings};
+use sync::{run_sync, SyncSettings};
 use thread_priority::{set_current_thread_priority, ThreadPriority};
-use tokio::time::{interval, MissedTickBehavior};
+use tokenizer::ProfileSwitch;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use transfer::InterruptTransfer;
+#[cfg(target_os = "linux")]
+use tray::{run_tray, TrayService};
 use util::config::ConfigManager;
 use util::connection::{command::*, Client, ConnectionState};
 use util::linux_x11::wait_for_x11;
 use util::thread::{kill_double, DualChannel, MutexTrait};
 use util::time::TIMEOUT_1S;
-
-const VID: u16 = 0x0738;
-const PID: u16 = 0x1713;
+use websocket::{event_channel, run_websocket, DriverEvent, EventSender};
+use window::{ActiveWindowWatcher, WindowRule};
 
 type ButtonConfig = [Vec<String>; 2];
 
@@ -43,51 +155,503 @@ pub struct ButtonConfigs {
     precision_aim: ButtonConfig,
     button_2: ButtonConfig,
     button_3: ButtonConfig,
+    // the clicks are played as usual in the modes without a binding
+    #[serde(default)]
+    left_click: ButtonConfig,
+    #[serde(default)]
+    right_click: ButtonConfig,
+    #[serde(default)]
+    middle_click: ButtonConfig,
+    // the wheel scrolls in the modes without a binding
+    #[serde(default)]
+    wheel_up: ButtonConfig,
+    #[serde(default)]
+    wheel_down: ButtonConfig,
+    // in milliseconds, defaults to 250ms when unset
+    #[serde(default)]
+    double_tap_window: Option<u64>,
+    // in milliseconds, defaults to 200ms when unset
+    #[serde(default)]
+    hold_threshold: Option<u64>,
+    // the movement is divided by this factor while the precision aim button is held
+    #[serde(default)]
+    precision_aim_factor: Option<f32>,
+    // applied to the speed of the movement, the movement is left as is when unset
+    #[serde(default)]
+    response_curve: Option<ResponseCurve>,
+    // applied after the response curve, the axes are swapped before being inverted
+    #[serde(default)]
+    invert_x: bool,
+    #[serde(default)]
+    invert_y: bool,
+    #[serde(default)]
+    swap_axes: bool,
+    // the shift button latches the shift mode until it's pressed again
+    #[serde(default)]
+    shift_toggle: bool,
+    // the clicks are swapped and the left and right bindings mirrored, for a left hand grip
+    #[serde(default)]
+    left_handed: bool,
+    // `scancode` types the characters of the bindings by their position on a us keyboard
+    #[serde(default)]
+    key_mode: KeyMode,
+    // the wheel and the scroll tokens send high resolution deltas scrolling this many lines
+    // per notch, instead of notch clicks, when set
+    #[serde(default)]
+    smooth_scroll: Option<f32>,
+    // in milliseconds keyed by button name, defaults to 50ms for missing buttons
+    #[serde(default)]
+    repeat_interval: BTreeMap<String, u64>,
+    // in milliseconds keyed by button name, transitions closer than this to the previous one
+    // are ignored, no debounce for missing buttons
+    #[serde(default)]
+    debounce: BTreeMap<String, u64>,
+    // bindings played while several buttons are held together, instead of their own bindings
+    #[serde(default)]
+    chords: Vec<Chord>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct Chord {
+    // names of the buttons
+    buttons: Vec<String>,
+    binding: String,
 }
 
 impl ButtonConfigs {
-    fn to_config(&self) -> Vec<ButtonConfig> {
-        vec![
-            self.scroll_button.clone(),
-            self.left_actionlock.clone(),
-            self.right_actionlock.clone(),
-            self.forwards_button.clone(),
-            self.back_button.clone(),
-            self.thumb_anticlockwise.clone(),
-            self.thumb_clockwise.clone(),
-            self.hat_top.clone(),
-            self.hat_left.clone(),
-            self.hat_right.clone(),
-            self.hat_bottom.clone(),
-            self.button_1.clone(),
-            self.precision_aim.clone(),
-            self.button_2.clone(),
-            self.button_3.clone(),
-        ]
-    }
-
-    fn from_config(data: &Vec<ButtonConfig>) -> Self {
-        Self {
-            scroll_button: data[0].clone(),
-            left_actionlock: data[1].clone(),
-            right_actionlock: data[2].clone(),
-            forwards_button: data[3].clone(),
-            back_button: data[4].clone(),
-            thumb_anticlockwise: data[5].clone(),
-            thumb_clockwise: data[6].clone(),
-            hat_top: data[7].clone(),
-            hat_left: data[8].clone(),
-            hat_right: data[9].clone(),
-            hat_bottom: data[10].clone(),
-            button_1: data[11].clone(),
-            precision_aim: data[12].clone(),
-            button_2: data[13].clone(),
-            button_3: data[14].clone(),
-        }
-    }
-}
-
-type MousesConfig = BTreeMap<String, ButtonConfigs>;
+    fn get(&self, key: &str) -> Option<&ButtonConfig> {
+        match key {
+            "scroll_button" => Some(&self.scroll_button),
+            "left_actionlock" => Some(&self.left_actionlock),
+            "right_actionlock" => Some(&self.right_actionlock),
+            "forwards_button" => Some(&self.forwards_button),
+            "back_button" => Some(&self.back_button),
+            "thumb_anticlockwise" => Some(&self.thumb_anticlockwise),
+            "thumb_clockwise" => Some(&self.thumb_clockwise),
+            "hat_top" => Some(&self.hat_top),
+            "hat_left" => Some(&self.hat_left),
+            "hat_right" => Some(&self.hat_right),
+            "hat_bottom" => Some(&self.hat_bottom),
+            "button_1" => Some(&self.button_1),
+            "precision_aim" => Some(&self.precision_aim),
+            "button_2" => Some(&self.button_2),
+            "button_3" => Some(&self.button_3),
+            "left_click" => Some(&self.left_click),
+            "right_click" => Some(&self.right_click),
+            "middle_click" => Some(&self.middle_click),
+            "wheel_up" => Some(&self.wheel_up),
+            "wheel_down" => Some(&self.wheel_down),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut ButtonConfig> {
+        match key {
+            "scroll_button" => Some(&mut self.scroll_button),
+            "left_actionlock" => Some(&mut self.left_actionlock),
+            "right_actionlock" => Some(&mut self.right_actionlock),
+            "forwards_button" => Some(&mut self.forwards_button),
+            "back_button" => Some(&mut self.back_button),
+            "thumb_anticlockwise" => Some(&mut self.thumb_anticlockwise),
+            "thumb_clockwise" => Some(&mut self.thumb_clockwise),
+            "hat_top" => Some(&mut self.hat_top),
+            "hat_left" => Some(&mut self.hat_left),
+            "hat_right" => Some(&mut self.hat_right),
+            "hat_bottom" => Some(&mut self.hat_bottom),
+            "button_1" => Some(&mut self.button_1),
+            "precision_aim" => Some(&mut self.precision_aim),
+            "button_2" => Some(&mut self.button_2),
+            "button_3" => Some(&mut self.button_3),
+            "left_click" => Some(&mut self.left_click),
+            "right_click" => Some(&mut self.right_click),
+            "middle_click" => Some(&mut self.middle_click),
+            "wheel_up" => Some(&mut self.wheel_up),
+            "wheel_down" => Some(&mut self.wheel_down),
+            _ => None,
+        }
+    }
+
+    // bindings in the button order of the device model
+    fn to_config(&self, device_model: &DeviceModel) -> Vec<ButtonConfig> {
+        device_model
+            .buttons
+            .iter()
+            .map(|button| self.get(button.key).cloned().unwrap_or_default())
+            .collect()
+    }
+
+    // only replace the bindings, keeping the other settings of the profile
+    fn update_from_config(&mut self, device_model: &DeviceModel, data: &[ButtonConfig]) {
+        for (button, button_config) in device_model.buttons.iter().zip(data) {
+            if let Some(config) = self.get_mut(button.key) {
+                *config = button_config.clone();
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct MouseConfig {
+    // profile used when no window rule matches, flattened to keep older configs loading
+    #[serde(flatten)]
+    button_configs: ButtonConfigs,
+    #[serde(default)]
+    profiles: BTreeMap<String, ButtonConfigs>,
+    // profiles kept the same on every device sharing them
+    #[serde(default)]
+    shared_profiles: BTreeSet<String>,
+    #[serde(default)]
+    window_rules: Vec<WindowRule>,
+    // profiles activated while a process runs, in priority order
+    #[serde(default)]
+    process_rules: Vec<ProcessRule>,
+    // activated once no process rule matches anymore, the selected profile being used when unset
+    #[serde(default)]
+    process_fallback_profile: Option<String>,
+    // name of the device model, missing in configs written before other models were supported
+    #[serde(default)]
+    model: Option<String>,
+    // given by the user to tell the devices apart, like `Travel mouse`
+    #[serde(default)]
+    nickname: Option<String>,
+    // dpi, polling rate and led colors, flattened like the default profile
+    #[serde(flatten)]
+    settings: DeviceSettings,
+    // colors of the named profiles, replacing the led colors while they're active
+    #[serde(default)]
+    profile_led_colors: BTreeMap<String, Vec<LedColor>>,
+    // output used to emulate the bindings, applied when the mouse connects
+    #[serde(default)]
+    backend: BackendKind,
+    // profile activated by the user, used when no window rule matches
+    #[serde(default)]
+    selected_profile: Option<String>,
+    // selected at runtime by the window rules or the profile tokens
+    #[serde(skip)]
+    active_profile: Option<String>,
+    // last profile selected by the window rules
+    #[serde(skip)]
+    window_profile: Option<String>,
+}
+
+impl MouseConfig {
+    fn device_model(&self) -> &'static DeviceModel {
+        model::find_by_name(self.model.as_deref())
+    }
+
+    // nickname, or model and serial number
+    fn display_name(&self, serial_number: &str) -> String {
+        match &self.nickname {
+            Some(nickname) => nickname.clone(),
+            None => format!("{} {}", self.device_model().name, serial_number),
+        }
+    }
+
+    // sent to the clients after each profile change
+    fn profiles(&self, serial_number: String, error: Option<String>) -> Profiles {
+        Profiles::new(
+            serial_number,
+            self.profiles.keys().cloned().collect(),
+            self.shared_profiles.iter().cloned().collect(),
+            self.selected_profile.clone(),
+            self.active_profile.clone(),
+            error,
+        )
+    }
+
+    fn active_button_configs(&self) -> &ButtonConfigs {
+        self.active_profile
+            .as_ref()
+            .and_then(|profile| self.profiles.get(profile))
+            .unwrap_or(&self.button_configs)
+    }
+
+    // so the leds show which profile is active
+    fn active_led_colors(&self) -> &Vec<LedColor> {
+        self.active_profile
+            .as_ref()
+            .and_then(|profile| self.profile_led_colors.get(profile))
+            .unwrap_or(&self.settings.led_colors)
+    }
+
+    // profile edited by the client
+    fn selected_button_configs(&self) -> &ButtonConfigs {
+        self.selected_profile
+            .as_ref()
+            .and_then(|profile| self.profiles.get(profile))
+            .unwrap_or(&self.button_configs)
+    }
+
+    fn selected_button_configs_mut(&mut self) -> &mut ButtonConfigs {
+        match self
+            .selected_profile
+            .as_ref()
+            .and_then(|profile| self.profiles.get_mut(profile))
+        {
+            Some(button_configs) => button_configs,
+            None => &mut self.button_configs,
+        }
+    }
+
+    // an empty profile, or a copy of another named one
+    fn create_profile(&mut self, profile: &str, copy_from: Option<&str>) -> Result<(), String> {
+        check_profile_name(profile)?;
+
+        if self.profiles.contains_key(profile) {
+            return Err(format!("profile {} already exists", profile));
+        }
+
+        let button_configs = match copy_from {
+            Some(copy_from) => self
+                .profiles
+                .get(copy_from)
+                .cloned()
+                .ok_or_else(|| format!("unknown profile {}", copy_from))?,
+            None => ButtonConfigs::default(),
+        };
+
+        self.profiles.insert(profile.to_string(), button_configs);
+
+        Ok(())
+    }
+
+    // a named profile is created from the preset, the default bindings being replaced otherwise
+    fn apply_preset(&mut self, preset: &str, profile: Option<&str>) -> Result<(), String> {
+        let button_configs = presets::find(preset)
+            .ok_or_else(|| format!("unknown preset {}", preset))?
+            .button_configs()?;
+
+        match profile {
+            Some(profile) => {
+                check_profile_name(profile)?;
+
+                if self.profiles.contains_key(profile) {
+                    return Err(format!("profile {} already exists", profile));
+                }
+
+                self.profiles.insert(profile.to_string(), button_configs);
+            }
+            None => self.button_configs = button_configs,
+        }
+
+        Ok(())
+    }
+
+    // the window rules and led colors follow the renamed profile, which is no longer shared
+    fn rename_profile(&mut self, profile: &str, new_name: &str) -> Result<(), String> {
+        check_profile_name(new_name)?;
+
+        if self.profiles.contains_key(new_name) {
+            return Err(format!("profile {} already exists", new_name));
+        }
+
+        let button_configs = self
+            .profiles
+            .remove(profile)
+            .ok_or_else(|| format!("unknown profile {}", profile))?;
+
+        self.profiles.insert(new_name.to_string(), button_configs);
+        self.shared_profiles.remove(profile);
+
+        if let Some(led_colors) = self.profile_led_colors.remove(profile) {
+            self.profile_led_colors
+                .insert(new_name.to_string(), led_colors);
+        }
+
+        for window_rule in self.window_rules.iter_mut() {
+            if window_rule.profile == profile {
+                window_rule.profile = new_name.to_string();
+            }
+        }
+        for process_rule in self.process_rules.iter_mut() {
+            if process_rule.profile == profile {
+                process_rule.profile = new_name.to_string();
+            }
+        }
+        for profile_pointer in [
+            &mut self.process_fallback_profile,
+            &mut self.selected_profile,
+            &mut self.active_profile,
+            &mut self.window_profile,
+        ] {
+            if profile_pointer.as_deref() == Some(profile) {
+                *profile_pointer = Some(new_name.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    // the default profile takes over where the deleted one was active
+    fn delete_profile(&mut self, profile: &str) -> Result<(), String> {
+        self.profiles
+            .remove(profile)
+            .ok_or_else(|| format!("unknown profile {}", profile))?;
+        self.profile_led_colors.remove(profile);
+        self.shared_profiles.remove(profile);
+        // rules left pointing to the profile would only be reported as config errors
+        self.window_rules
+            .retain(|window_rule| window_rule.profile != profile);
+        self.process_rules
+            .retain(|process_rule| process_rule.profile != profile);
+
+        for profile_pointer in [
+            &mut self.selected_profile,
+            &mut self.active_profile,
+            &mut self.window_profile,
+            &mut self.process_fallback_profile,
+        ] {
+            if profile_pointer.as_deref() == Some(profile) {
+                *profile_pointer = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the other devices sharing a profile of the same name replace this copy with theirs
+    fn share_profile(&mut self, profile: &str, shared: bool) -> Result<(), String> {
+        if !self.profiles.contains_key(profile) {
+            return Err(format!("unknown profile {}", profile));
+        }
+
+        if shared {
+            self.shared_profiles.insert(profile.to_string());
+        } else {
+            self.shared_profiles.remove(profile);
+        }
+
+        Ok(())
+    }
+
+    // the default profile when none is given
+    fn activate_profile(&mut self, profile: Option<&str>) -> Result<(), String> {
+        if let Some(profile) = profile {
+            if !self.profiles.contains_key(profile) {
+                return Err(format!("unknown profile {}", profile));
+            }
+        }
+
+        self.selected_profile = profile.map(str::to_string);
+        self.active_profile = self.selected_profile.clone();
+
+        Ok(())
+    }
+
+    // cycles through the default profile then the named profiles
+    fn switch_profile(&mut self, profile_switch: &ProfileSwitch) {
+        let profile_names: Vec<&String> = self.profiles.keys().collect();
+        let index = self.active_profile.as_ref().and_then(|active_profile| {
+            profile_names
+                .iter()
+                .position(|profile_name| *profile_name == active_profile)
+        });
+
+        self.active_profile = match profile_switch {
+            ProfileSwitch::Next => match index {
+                Some(index) => profile_names.get(index + 1).map(|name| name.to_string()),
+                None => profile_names.first().map(|name| name.to_string()),
+            },
+            ProfileSwitch::Previous => match index {
+                Some(0) => None,
+                Some(index) => profile_names.get(index - 1).map(|name| name.to_string()),
+                None => profile_names.last().map(|name| name.to_string()),
+            },
+            ProfileSwitch::Default => None,
+            ProfileSwitch::Named(profile) if self.profiles.contains_key(profile) => {
+                Some(profile.clone())
+            }
+            ProfileSwitch::Named(_) => self.active_profile.clone(),
+        };
+    }
+}
+
+type MousesConfig = BTreeMap<String, MouseConfig>;
+
+// the names understood by the profile token can't be used for a profile
+fn check_profile_name(profile: &str) -> Result<(), String> {
+    if profile.trim().is_empty() || ["next", "previous", "default"].contains(&profile) {
+        Err(format!("invalid profile name {}", profile))
+    } else {
+        Ok(())
+    }
+}
+
+// a malformed profiles file is left aside, keeping the last good config loaded,
+// and the runtime profile state is lost when the file is read
+fn reload_mouses_config(mouses_config: &mut ConfigManager<MousesConfig>) -> bool {
+    if validation::check_file().is_none() && mouses_config.update() {
+        restore_selected_profiles(&mut mouses_config.config);
+
+        true
+    } else {
+        false
+    }
+}
+
+fn restore_selected_profiles(mouses_config: &mut MousesConfig) {
+    for mouse_config in mouses_config.values_mut() {
+        mouse_config.active_profile = mouse_config.selected_profile.clone();
+    }
+}
+
+// driver wide settings, separate from the profiles edited by the client
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+struct DriverSettings {
+    // port of the localhost REST API, disabled when unset
+    #[serde(default)]
+    api_port: Option<u16>,
+    // port of the localhost websocket event stream, disabled when unset
+    #[serde(default)]
+    websocket_port: Option<u16>,
+    // desktop notifications not shown
+    #[serde(default)]
+    silenced_notifications: Vec<NotificationKind>,
+    // remote the profiles file is synced with, disabled when unset
+    #[serde(default)]
+    sync: Option<SyncSettings>,
+    // minutes without input before the mice are read less often, disabled when unset
+    #[serde(default)]
+    idle_suspend_minutes: Option<u64>,
+    // the original events of the stock buttons with a binding are suppressed on windows,
+    // for the mice still driven by their hid driver
+    #[serde(default)]
+    exclusive_grab: bool,
+    // programs the `{exec:...}` token may run, by name or by path
+    #[serde(default)]
+    exec_allowlist: Vec<String>,
+    // sends the led colors with a report format that hasn't been checked against the mmo7
+    #[serde(default)]
+    unverified_led_report: bool,
+    // reads the onboard memory with a page layout that hasn't been checked against the mmo7
+    #[serde(default)]
+    unverified_memory_dump: bool,
+    // flashes the mode and profile of a mouse when they change
+    #[serde(default)]
+    mode_osd: bool,
+    // sounds played when the mode or the profile of a mouse changes, silent when unset
+    #[serde(default)]
+    sounds: Option<SoundSettings>,
+    // name of the virtual midi port on linux, part of the name of the midi output on windows
+    #[serde(default)]
+    midi_port: Option<String>,
+    // commands run when a mouse connects, or its profile or mode changes
+    #[serde(default)]
+    hooks: HookSettings,
+    // broker the button events and mode changes are published to, disabled when unset
+    #[serde(default)]
+    mqtt: Option<MqttSettings>,
+    // the client has to send the token of the config directory before any other command
+    #[serde(default)]
+    client_auth: bool,
+    // port of the localhost listener of the clients other than the client app, disabled when unset
+    #[serde(default)]
+    client_port: Option<u16>,
+    // address and port listened on for the clients of other machines, like `0.0.0.0:7878`,
+    // disabled when unset
+    #[serde(default)]
+    remote_client_address: Option<SocketAddr>,
+}
 
 #[derive(Debug)]
 struct Endpoint {
@@ -100,378 +664,2205 @@ struct Endpoint {
 #[derive(Debug, Clone)]
 enum Message {
     DeviceListUpdate,
+    OnboardMemory(OnboardMemory),
+    ConfigErrors(Vec<String>),
+    ReportLog(ReportLog),
+    DriverStatus(DriverStatus),
+    DeviceInfo(DeviceInfo),
+    DeviceErrors(Vec<DeviceError>),
 }
 
+// requests handled by the thread owning the device
+#[derive(Debug)]
+enum DeviceRequest {
+    DumpMemory,
+    // token string played like a binding
+    PlayMacro(String),
+    SendReportLog,
+    SendDriverStatus,
+    SendDeviceInfo,
+    Identify,
+    // releases the device before the driver exits
+    Stop,
+    // sent by the interrupt transfer of the device
+    Report(Vec<u8>),
+    ReadError(rusb::Error),
+}
+
+// connected devices by serial number
+type ConnectedDevices = HashMap<String, Sender<DeviceRequest>>;
+
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // control commands are sent to the running driver
+    if args.first().map(String::as_str) == Some("ctl") {
+        let driver_settings = ConfigManager::<DriverSettings>::new("mmo7_settings").config;
+
+        if !run_ctl(&args[1..], driver_settings.api_port) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // captured reports are replayed without the device, alongside a running driver
+    if args.first().map(String::as_str) == Some("--simulate") {
+        let simulated = match args.get(1) {
+            Some(path) => run_simulation(path, args.get(2).map(String::as_str)),
+            None => {
+                println!("usage : mad-rust-mmo7-driver --simulate <file> [serial]");
+                false
+            }
+        };
+
+        if !simulated {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // registers the driver to be started at login, then exits
+    if let Some(install) = match args.first().map(String::as_str) {
+        Some("--install-autostart") => Some(true),
+        Some("--uninstall-autostart") => Some(false),
+        _ => None,
+    } {
+        if !run_autostart(install) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // lets the mice be opened without running the driver as root
+    if args.first().map(String::as_str) == Some("--setup-permissions") {
+        #[cfg(target_os = "linux")]
+        let permissions_set_up = permissions::setup_permissions();
+        #[cfg(not(target_os = "linux"))]
+        let permissions_set_up = {
+            println!("Permissions only have to be set up on Linux");
+            true
+        };
+
+        if !permissions_set_up {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // user unit running the driver as a systemd service
+    if args.first().map(String::as_str) == Some("--print-unit") {
+        print!("{}", daemon::unit_file());
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--daemon") {
+        daemon::enable();
+    }
+
+    if args.iter().any(|arg| arg == "--debug-reports") {
+        report_log::enable();
+    }
+
     if kill_double() {
         return;
     }
 
+    status::start();
+
     wait_for_x11();
     set_current_thread_priority(ThreadPriority::Min).ok();
 
     let client = Client::new().await;
     let client_dualchannel = client.dual_channel;
-    let device_list_mutex = Arc::new(Mutex::new(HashSet::<String>::new()));
+    let device_list_mutex = Arc::new(Mutex::new(ConnectedDevices::new()));
+    let device_errors_mutex = Arc::new(Mutex::new(vec![]));
     let (host, child) = DualChannel::<Message>::new();
-    let icon_data = include_bytes!("../icon.png").to_vec();
-    let mouses_config_mutex = Arc::new(tokio::sync::Mutex::new(
-        ConfigManager::<MousesConfig>::new("mmo7_profiles"),
-    ));
+    let mut mouses_config = ConfigManager::<MousesConfig>::new("mmo7_profiles");
+
+    restore_selected_profiles(&mut mouses_config.config);
+
+    let config_snapshot = ConfigSnapshot::default();
+    let (profile_switch_tx, profile_switch_rx) = unbounded_channel();
+
+    config_snapshot.publish(&mouses_config.config);
+
+    let mouses_config_mutex = Arc::new(tokio::sync::Mutex::new(mouses_config));
     let mouses_config_state_id = Arc::new(AtomicU32::new(0));
+    let driver_settings = ConfigManager::<DriverSettings>::new("mmo7_settings").config;
+    let event_tx = event_channel();
+
+    notification::silence(driver_settings.silenced_notifications.clone());
+    idle::configure(driver_settings.idle_suspend_minutes);
+    grab::configure(driver_settings.exclusive_grab);
+    lighting::configure(driver_settings.unverified_led_report);
+    memory::configure(driver_settings.unverified_memory_dump);
+    exec::configure(driver_settings.exec_allowlist.clone());
+    plugin::load();
+    midi::configure(driver_settings.midi_port.clone());
+    auth::configure(driver_settings.client_auth);
+
+    if let Some(sync_settings) = driver_settings.sync.clone() {
+        run_sync(sync_settings);
+    }
+
+    if let Some(api_port) = driver_settings.api_port {
+        run_api(
+            api_port,
+            ApiState {
+                device_list_mutex: device_list_mutex.clone(),
+                mouses_config_mutex: mouses_config_mutex.clone(),
+                mouses_config_state_id: mouses_config_state_id.clone(),
+            },
+        );
+    }
+
+    if let Some(websocket_port) = driver_settings.websocket_port {
+        run_websocket(websocket_port, event_tx.clone()).await;
+    }
+
+    if driver_settings.mode_osd {
+        run_osd(event_tx.clone()).await;
+    }
+
+    if let Some(sound_settings) = driver_settings.sounds.clone() {
+        run_sound(sound_settings, event_tx.clone()).await;
+    }
+
+    run_hooks(driver_settings.hooks.clone(), event_tx.clone());
+
+    if let Some(mqtt_settings) = driver_settings.mqtt.clone() {
+        run_mqtt(mqtt_settings, event_tx.clone()).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    run_dbus(
+        DbusService {
+            device_list_mutex: device_list_mutex.clone(),
+            mouses_config_mutex: mouses_config_mutex.clone(),
+            mouses_config_state_id: mouses_config_state_id.clone(),
+        },
+        event_tx.clone(),
+    )
+    .await;
+
+    #[cfg(target_os = "linux")]
+    run_tray(
+        TrayService {
+            device_list_mutex: device_list_mutex.clone(),
+            mouses_config_mutex: mouses_config_mutex.clone(),
+            mouses_config_state_id: mouses_config_state_id.clone(),
+        },
+        event_tx.clone(),
+    )
+    .await;
 
-    watch_config_update(mouses_config_mutex.clone(), mouses_config_state_id.clone()).await;
+    watch_config_update(
+        host.clone(),
+        mouses_config_mutex.clone(),
+        mouses_config_state_id.clone(),
+    )
+    .await;
+    watch_active_window(mouses_config_mutex.clone(), mouses_config_state_id.clone()).await;
+    watch_processes(mouses_config_mutex.clone(), profile_switch_tx.clone()).await;
+    publish_config_snapshot(
+        mouses_config_mutex.clone(),
+        mouses_config_state_id.clone(),
+        config_snapshot.clone(),
+        profile_switch_rx,
+    )
+    .await;
     run_connection(
         client_dualchannel,
         child,
-        device_list_mutex.clone(),
-        icon_data,
-        mouses_config_mutex.clone(),
-        mouses_config_state_id.clone(),
+        ListenerSettings {
+            local_port: driver_settings.client_port,
+            remote_address: driver_settings.remote_client_address,
+        },
+        SessionState {
+            client_list: ClientList::default(),
+            device_list_mutex: device_list_mutex.clone(),
+            device_errors_mutex: device_errors_mutex.clone(),
+            mouses_config_mutex: mouses_config_mutex.clone(),
+            mouses_config_state_id: mouses_config_state_id.clone(),
+            event_tx: event_tx.clone(),
+        },
     )
     .await;
+    daemon::notify("READY=1");
     listening_new_device(
         host,
         device_list_mutex,
+        device_errors_mutex,
         mouses_config_mutex,
-        mouses_config_state_id,
+        config_snapshot,
+        profile_switch_tx,
+        event_tx,
     )
     .await;
 }
 
 async fn watch_config_update(
+    host: DualChannel<Message>,
     mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
     mouses_config_state_id: Arc<AtomicU32>,
 ) {
     let mouses_config_mutex = mouses_config_mutex.clone();
 
     tokio::spawn(async move {
+        let profiles_notify = Arc::new(Notify::new());
+        let watcher = watch_profiles(profiles_notify.clone());
+        // polling is only used when the profiles file can't be watched
         let mut interval_ = interval(TIMEOUT_1S * 10);
+        let mut last_config_error_vec = vec![];
 
         loop {
-            if mouses_config_mutex.lock().await.update() {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+
+            if reload_mouses_config(&mut mouses_config) {
                 mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
             }
 
-            interval_.tick().await;
-        }
-    });
-}
+            // errors are only reported when they change
+            let config_error_vec: Vec<String> = validation::config_errors(&mouses_config.config)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
 
-// device handling
-async fn listening_new_device(
-    host: DualChannel<Message>,
-    device_list_mutex: Arc<Mutex<HashSet<String>>>,
-    mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
-    mouses_config_state_id: Arc<AtomicU32>,
-) {
-    let mut interval_ = interval(TIMEOUT_1S);
+            drop(mouses_config);
 
-    interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            if config_error_vec != last_config_error_vec {
+                for config_error in &config_error_vec {
+                    status::error(format!("config error : {}", config_error));
+                }
 
-    loop {
-        if let Ok(context) = Context::new() {
-            if let Ok(devices) = context.devices() {
-                for device in devices.iter() {
-                    if let Ok(device_descriptor) = device.device_descriptor() {
-                        if device_descriptor.vendor_id() == VID
-                            && device_descriptor.product_id() == PID
-                        {
-                            if let Ok(device_handle) = device.open() {
-                                if let Ok(languages) =
-                                    device_handle.read_languages(Duration::from_millis(100))
-                                {
-                                    if let Ok(serial_number) = device_handle
-                                        .read_serial_number_string(
-                                            languages[0],
-                                            &device_descriptor,
-                                            Duration::from_millis(100),
-                                        )
-                                    {
-                                        let mut device_list = device_list_mutex.lock_poisoned();
-
-                                        if let None = device_list.get(&serial_number) {
-                                            {
-                                                // create a default config if needed
-                                                let mut mouses_config =
-                                                    mouses_config_mutex.lock().await;
-
-                                                if !mouses_config
-                                                    .config
-                                                    .contains_key(&serial_number)
-                                                {
-                                                    mouses_config.config.insert(
-                                                        serial_number.clone(),
-                                                        ButtonConfigs::default(),
-                                                    );
-                                                    mouses_config.save();
-                                                }
-                                            }
-
-                                            device_list.insert(serial_number.clone());
-
-                                            let host = host.clone();
-                                            let device_list_mutex = device_list_mutex.clone();
-                                            let mouses_config_mutex = mouses_config_mutex.clone();
-                                            let mouses_config_state_id =
-                                                mouses_config_state_id.clone();
-
-                                            spawn(move || {
-                                                set_current_thread_priority(ThreadPriority::Max)
-                                                    .ok();
-
-                                                run_device(
-                                                    serial_number.clone(),
-                                                    host.clone(),
-                                                    mouses_config_mutex,
-                                                    mouses_config_state_id,
-                                                );
-
-                                                device_list_mutex
-                                                    .lock_poisoned()
-                                                    .remove(&serial_number);
-                                                host.send(Message::DeviceListUpdate).ok();
-                                            });
-                                        }
-                                    }
-                                }
+                if let Some(config_error) = config_error_vec.first() {
+                    notification::notify(
+                        NotificationKind::Error,
+                        match config_error_vec.len() {
+                            1 => format!("Config error : {}", config_error),
+                            length => {
+                                format!("Config error : {} and {} more", config_error, length - 1)
                             }
-                        }
-                    }
+                        },
+                    );
                 }
-            }
-        }
 
-        interval_.tick().await;
-    }
-}
+                host.send(Message::ConfigErrors(config_error_vec.clone()))
+                    .ok();
+                last_config_error_vec = config_error_vec;
+            }
 
-fn find_device(serial_number: String) -> Option<DeviceHandle<Context>> {
-    if let Ok(context) = Context::new() {
-        if let Ok(devices) = context.devices() {
-            for device in devices.iter() {
-                if let Ok(device_descriptor) = device.device_descriptor() {
-                    if device_descriptor.vendor_id() == VID && device_descriptor.product_id() == PID
-                    {
-                        if let Ok(device_handle) = device.open() {
-                            if let Ok(languages) = device_handle.read_languages(TIMEOUT_1S) {
-                                if let Ok(serial_number_found) = device_handle
-                                    .read_serial_number_string(
-                                        languages[0],
-                                        &device_descriptor,
-                                        TIMEOUT_1S,
-                                    )
-                                {
-                                    if serial_number == serial_number_found {
-                                        return Some(device_handle);
-                                    }
-                                }
-                            }
-                        }
-                    }
+            tokio::select! {
+                _ = profiles_notify.notified() => {
+                    // editors can write the file in several steps
+                    sleep(Duration::from_millis(50)).await;
                 }
+                _ = interval_.tick(), if watcher.is_none() => {}
             }
         }
-    }
-
-    None
+    });
 }
 
-fn run_device(
-    serial_number: String,
-    dual_channel: DualChannel<Message>,
+async fn watch_active_window(
     mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
     mouses_config_state_id: Arc<AtomicU32>,
 ) {
-    if let Some(mut device_handle) = find_device(serial_number.clone()) {
-        let device = device_handle.device();
-        if let Ok(config_descriptor) = device.config_descriptor(0) {
-            if let Some(interface) = config_descriptor.interfaces().next() {
-                if let Some(interface_descriptor) = interface.descriptors().next() {
-                    if let Some(endpoint_descriptor) =
-                        interface_descriptor.endpoint_descriptors().next()
-                    {
-                        let endpoint = Endpoint {
-                            config: config_descriptor.number(),
-                            iface: interface_descriptor.interface_number(),
-                            setting: interface_descriptor.setting_number(),
-                            address: endpoint_descriptor.address(),
-                        };
-
-                        let has_kernel_driver =
-                            match device_handle.kernel_driver_active(endpoint.iface) {
-                                Ok(true) => {
-                                    device_handle.detach_kernel_driver(endpoint.iface).ok();
-                                    true
-                                }
-                                _ => false,
-                            };
+    tokio::spawn(async move {
+        let mut interval_ = interval(Duration::from_millis(500));
+        let mut active_window_watcher = ActiveWindowWatcher::default();
 
-                        if let (Ok(_), Ok(_), Ok(_)) = (
-                            device_handle.set_active_configuration(endpoint.config),
-                            device_handle.claim_interface(endpoint.iface),
-                            device_handle.set_alternate_setting(endpoint.iface, endpoint.setting),
-                        ) {
-                            println!("{} connected", serial_number);
-
-                            dual_channel.send(Message::DeviceListUpdate).ok();
-
-                            let mut buffer = [0; 8];
-                            let mut mapper = Mapper::new(
-                                mouses_config_mutex,
-                                mouses_config_state_id,
-                                serial_number.clone(),
-                            );
-
-                            loop {
-                                match device_handle.read_interrupt(
-                                    endpoint.address,
-                                    &mut buffer,
-                                    Duration::from_millis(25),
-                                ) {
-                                    Ok(_) => mapper.emulate(&buffer),
-                                    Err(rusb::Error::Timeout) => {
-                                        mapper.emulate_only_mapped(&buffer)
-                                    }
-                                    Err(err) => {
-                                        println!("{} disconnected : {}", serial_number, err);
-                                        break;
-                                    }
-                                }
-                            }
+        interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-                            if has_kernel_driver {
-                                device_handle.attach_kernel_driver(endpoint.iface).ok();
-                            }
-                        }
-                    }
+        loop {
+            let active_window = active_window_watcher.active_window();
+            let mut mouses_config = mouses_config_mutex.lock().await;
+            let mut has_changed = false;
+
+            for mouse_config in mouses_config.config.values_mut() {
+                let active_profile = active_window.as_ref().and_then(|active_window| {
+                    mouse_config
+                        .window_rules
+                        .iter()
+                        .find(|window_rule| {
+                            window_rule.matches(active_window)
+                                && mouse_config.profiles.contains_key(&window_rule.profile)
+                        })
+                        .map(|window_rule| window_rule.profile.clone())
+                });
+
+                // only override the active profile when the rules result changes,
+                // so profiles switched from a button stay active
+                if mouse_config.window_profile != active_profile {
+                    mouse_config.window_profile = active_profile.clone();
+                    mouse_config.active_profile =
+                        active_profile.or_else(|| mouse_config.selected_profile.clone());
+                    has_changed = true;
                 }
             }
+
+            if has_changed {
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+            }
+
+            drop(mouses_config);
+            interval_.tick().await;
         }
-    }
+    });
 }
 
-// connection processing
-async fn run_connection(
-    client_dualchannel: DualChannel<ConnectionState>,
-    child: DualChannel<Message>,
-    device_list_mutex: Arc<Mutex<HashSet<String>>>,
-    icon_data: Vec<u8>,
+// applies the profile switches of the devices, and publishes the config snapshot read by the
+// device threads after each change
+async fn publish_config_snapshot(
     mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
     mouses_config_state_id: Arc<AtomicU32>,
+    config_snapshot: ConfigSnapshot,
+    mut profile_switch_rx: UnboundedReceiver<(String, ProfileSwitch)>,
 ) {
-    {
-        let client_dualchannel = client_dualchannel.clone();
-        let device_list_mutex = device_list_mutex.clone();
-
-        tokio::spawn(async move {
-            let mut driver_configuration_descriptor = DriverConfigurationDescriptor::new(
-                VID,
-                PID,
-                "MMO7".to_string(),
-                icon_data,
-                3,
-                3,
-                vec![
-                    "Scroll Button".to_string(),
-                    "Left ActionLock".to_string(),
-                    "Right ActionLock".to_string(),
-                    "Forwards Button".to_string(),
-                    "Back Button".to_string(),
-                    "Thumb Anticlockwise".to_string(),
-                    "Thumb Clockwise".to_string(),
-                    "Hat Top".to_string(),
-                    "Hat Left".to_string(),
-                    "Hat Right".to_string(),
-                    "Hat Bottom".to_string(),
-                    "Button 1".to_string(),
-                    "Precision Aim".to_string(),
-                    "Button 2".to_string(),
-                    "Button 3".to_string(),
-                ],
-            );
+    tokio::spawn(async move {
+        let mut interval_ = interval(Duration::from_millis(25));
+        let mut last_mouses_config_state_id = mouses_config_state_id.load(Ordering::SeqCst);
+        let mut shared_profiles = SharedProfiles::default();
 
-            loop {
-                if let Ok(connection_state) = client_dualchannel.recv_async().await {
-                    match connection_state {
-                        ConnectionState::Start => {
-                            client_dualchannel
-                                .send_async(ConnectionState::Data(
-                                    driver_configuration_descriptor.to_bytes(),
-                                ))
-                                .await
-                                .ok();
+        sync_shared_profiles(&mouses_config_mutex, &mut shared_profiles, &config_snapshot).await;
 
-                            update_device_list(&client_dualchannel, device_list_mutex.clone())
-                                .await;
-                        }
-                        ConnectionState::Data(data) => match Commands::from(data) {
-                            Commands::RequestDeviceConfig(request_device_config) => {
-                                let mouses_config = mouses_config_mutex.lock().await;
+        interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-                                if let Some(mouse_config) = mouses_config
-                                    .config
-                                    .get(&request_device_config.serial_number)
+        loop {
+            tokio::select! {
+                Some((serial_number, profile_switch)) = profile_switch_rx.recv() => {
+                    let mut mouses_config = mouses_config_mutex.lock().await;
+
+                    if let Some(mouse_config) = mouses_config.config.get_mut(&serial_number) {
+                        mouse_config.switch_profile(&profile_switch);
+                        mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+                _ = interval_.tick() => {}
+            }
+
+            let state_id = mouses_config_state_id.load(Ordering::SeqCst);
+
+            if last_mouses_config_state_id != state_id {
+                last_mouses_config_state_id = state_id;
+                sync_shared_profiles(&mouses_config_mutex, &mut shared_profiles, &config_snapshot)
+                    .await;
+            }
+        }
+    });
+}
+
+// edits of a shared profile are copied to the other devices sharing it before publishing
+async fn sync_shared_profiles(
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
+    shared_profiles: &mut SharedProfiles,
+    config_snapshot: &ConfigSnapshot,
+) {
+    let mut mouses_config = mouses_config_mutex.lock().await;
+
+    if shared_profiles.sync(&mut mouses_config.config) {
+        mouses_config.save();
+    }
+
+    config_snapshot.publish(&mouses_config.config);
+}
+
+// device handling
+async fn listening_new_device(
+    host: DualChannel<Message>,
+    device_list_mutex: Arc<Mutex<ConnectedDevices>>,
+    device_errors_mutex: Arc<Mutex<Vec<DeviceError>>>,
+    mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    config_snapshot: ConfigSnapshot,
+    profile_switch_tx: ProfileSwitchSender,
+    event_tx: EventSender,
+) {
+    let mut interval_ = interval(TIMEOUT_1S);
+    let terminated = daemon::terminated();
+    // last bring up failure of the device threads, by serial number
+    let bring_up_failure_map = Arc::new(Mutex::new(HashMap::<String, BringUpFailure>::new()));
+    let mut resume_rx = power::watch_resume();
+
+    interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    tokio::pin!(terminated);
+
+    loop {
+        let mut device_error_vec = vec![];
+        let mut serial_number_vec = vec![];
+
+        if let Ok(context) = Context::new() {
+            if let Ok(devices) = context.devices() {
+                for device in devices.iter() {
+                    if let Ok(device_descriptor) = device.device_descriptor() {
+                        if let Some(device_model) = model::find(
+                            device_descriptor.vendor_id(),
+                            device_descriptor.product_id(),
+                        ) {
+                            let serial_number =
+                                match read_serial_number(&device, &device_descriptor) {
+                                    Ok(serial_number) => serial_number,
+                                    Err(bring_up_error) => {
+                                        device_error_vec.push(bring_up_error.into_device_error(
+                                            device_model,
+                                            &device,
+                                            None,
+                                        ));
+                                        continue;
+                                    }
+                                };
+                            let mut device_list = device_list_mutex.lock_poisoned();
+
+                            serial_number_vec.push(serial_number.clone());
+
+                            if let None = device_list.get(&serial_number) {
+                                // reported until the device thread brings it up
+                                if let Some(bring_up_failure) =
+                                    bring_up_failure_map.lock_poisoned().get(&serial_number)
                                 {
-                                    client_dualchannel
-                                        .send_async(ConnectionState::Data(
-                                            DeviceConfig::new(
-                                                request_device_config.serial_number,
-                                                mouse_config.to_config(),
-                                            )
-                                            .to_bytes(),
-                                        ))
-                                        .await
-                                        .ok();
+                                    device_error_vec.push(bring_up_failure.to_device_error(
+                                        device_model,
+                                        &device,
+                                        Some(serial_number.clone()),
+                                    ));
+
+                                    if !bring_up_failure.is_due() {
+                                        continue;
+                                    }
+                                }
+
+                                {
+                                    // create a default config if needed
+                                    let mut mouses_config = mouses_config_mutex.lock().await;
+                                    let mouse_config = mouses_config
+                                        .config
+                                        .entry(serial_number.clone())
+                                        .or_default();
+
+                                    if mouse_config.model.as_deref() != Some(device_model.name) {
+                                        mouse_config.model = Some(device_model.name.to_string());
+                                        mouses_config.save();
+                                    }
+
+                                    config_snapshot.publish(&mouses_config.config);
                                 }
+
+                                let (device_request_tx, device_request_rx) = channel();
+
+                                device_list
+                                    .insert(serial_number.clone(), device_request_tx.clone());
+
+                                let host = host.clone();
+                                let device_list_mutex = device_list_mutex.clone();
+                                let bring_up_failure_map = bring_up_failure_map.clone();
+                                let (bus_number, address) = (device.bus_number(), device.address());
+                                let config_snapshot = config_snapshot.clone();
+                                let profile_switch_tx = profile_switch_tx.clone();
+                                let event_tx = event_tx.clone();
+
+                                spawn(move || {
+                                    set_current_thread_priority(ThreadPriority::Max).ok();
+
+                                    // the mapper releases its outputs while unwinding,
+                                    // and the device is found again once removed from the list
+                                    match catch_unwind(AssertUnwindSafe(|| {
+                                        run_device(
+                                            serial_number.clone(),
+                                            device_model,
+                                            host.clone(),
+                                            (device_request_tx, device_request_rx),
+                                            config_snapshot,
+                                            profile_switch_tx,
+                                            event_tx.clone(),
+                                        )
+                                    })) {
+                                        Ok(Ok(())) => {
+                                            bring_up_failure_map
+                                                .lock_poisoned()
+                                                .remove(&serial_number);
+                                        }
+                                        Ok(Err(bring_up_error)) => {
+                                            let mut bring_up_failure_map =
+                                                bring_up_failure_map.lock_poisoned();
+                                            let bring_up_failure = BringUpFailure::new(
+                                                bring_up_error,
+                                                bring_up_failure_map.get(&serial_number),
+                                                bus_number,
+                                                address,
+                                            );
+
+                                            bring_up_failure_map
+                                                .insert(serial_number.clone(), bring_up_failure);
+                                        }
+                                        Err(_) => {
+                                            println!(
+                                                "{} device thread panicked, restarting it",
+                                                serial_number
+                                            );
+                                            event_tx
+                                                .send(DriverEvent::Device {
+                                                    serial_number: serial_number.clone(),
+                                                    connected: false,
+                                                })
+                                                .ok();
+                                        }
+                                    }
+
+                                    device_list_mutex.lock_poisoned().remove(&serial_number);
+                                    host.send(Message::DeviceListUpdate).ok();
+                                });
                             }
-                            Commands::DeviceConfig(device_config) => {
-                                let mut mouses_config = mouses_config_mutex.lock().await;
-
-                                mouses_config.config.insert(
-                                    device_config.serial_number,
-                                    ButtonConfigs::from_config(&device_config.config),
-                                );
-                                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
-                                mouses_config.save();
+                        }
+                    }
+                }
+            }
+        }
+
+        // the errors of unplugged devices are forgotten
+        bring_up_failure_map
+            .lock_poisoned()
+            .retain(|serial_number, _| serial_number_vec.contains(serial_number));
+        update_device_errors(&host, &device_errors_mutex, device_error_vec);
+
+        tokio::select! {
+            _ = interval_.tick() => {}
+            // the handles opened before the sleep are stale, even when the mice are still
+            // listed, so they're released and found again
+            Some(()) = resume_rx.recv() => {
+                println!("System resumed, reconnecting the devices");
+                stop_devices(&device_list_mutex).await;
+                // the conflicts given up before the sleep are retried too
+                bring_up_failure_map.lock_poisoned().clear();
+            }
+            _ = &mut terminated => break,
+        }
+    }
+
+    daemon::notify("STOPPING=1");
+    stop_devices(&device_list_mutex).await;
+    println!("Driver stopped");
+}
+
+// the device is only opened to read its serial number, the device thread claims it
+fn read_serial_number(
+    device: &Device<Context>,
+    device_descriptor: &DeviceDescriptor,
+) -> Result<String, BringUpError> {
+    let device_handle = device.open().map_err(BringUpError::Open)?;
+    let languages = device_handle
+        .read_languages(Duration::from_millis(100))
+        .map_err(BringUpError::SerialNumber)?;
+
+    device_handle
+        .read_serial_number_string(languages[0], device_descriptor, Duration::from_millis(100))
+        .map_err(BringUpError::SerialNumber)
+}
+
+// errors are only reported when they change, and notified once per device
+fn update_device_errors(
+    host: &DualChannel<Message>,
+    device_errors_mutex: &Mutex<Vec<DeviceError>>,
+    device_error_vec: Vec<DeviceError>,
+) {
+    let mut last_device_error_vec = device_errors_mutex.lock_poisoned();
+
+    if *last_device_error_vec == device_error_vec {
+        return;
+    }
+
+    for device_error in &device_error_vec {
+        if !last_device_error_vec.contains(device_error) {
+            let device_name = match &device_error.serial_number {
+                Some(serial_number) => format!("{} {}", device_error.model, serial_number),
+                None => device_error.model.clone(),
+            };
+
+            status::error(format!("{} : {}", device_name, device_error.message));
+            notification::notify(
+                NotificationKind::Error,
+                format!("{} : {}", device_name, device_error.message),
+            );
+        }
+    }
+
+    *last_device_error_vec = device_error_vec.clone();
+    host.send(Message::DeviceErrors(device_error_vec)).ok();
+}
+
+// the device threads release the mice and give them back to their kernel driver,
+// they're stopped within a read timeout
+async fn stop_devices(device_list_mutex: &Mutex<ConnectedDevices>) {
+    for device_request_tx in device_list_mutex.lock_poisoned().values() {
+        device_request_tx.send(DeviceRequest::Stop).ok();
+    }
+
+    let start = Instant::now();
+
+    while !device_list_mutex.lock_poisoned().is_empty() && start.elapsed() < TIMEOUT_1S * 2 {
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+// the error of the last supported mouse which couldn't be opened is returned when none matches
+fn find_device(serial_number: &str) -> Result<DeviceHandle<Context>, BringUpError> {
+    let context = transfer::context().map_err(BringUpError::Open)?;
+    let devices = context.devices().map_err(BringUpError::Open)?;
+    let mut bring_up_error = BringUpError::Open(rusb::Error::NoDevice);
+
+    for device in devices.iter() {
+        if let Ok(device_descriptor) = device.device_descriptor() {
+            if model::find(
+                device_descriptor.vendor_id(),
+                device_descriptor.product_id(),
+            )
+            .is_some()
+            {
+                match device.open() {
+                    Ok(device_handle) => {
+                        if let Ok(languages) = device_handle.read_languages(TIMEOUT_1S) {
+                            if let Ok(serial_number_found) = device_handle
+                                .read_serial_number_string(
+                                    languages[0],
+                                    &device_descriptor,
+                                    TIMEOUT_1S,
+                                )
+                            {
+                                if serial_number == serial_number_found {
+                                    return Ok(device_handle);
+                                }
                             }
-                            _ => {}
-                        },
-                        ConnectionState::End => {}
+                        }
                     }
+                    Err(err) => bring_up_error = BringUpError::Open(err),
                 }
             }
-        })
+        }
+    }
+
+    Err(bring_up_error)
+}
+
+fn run_device(
+    serial_number: String,
+    device_model: &'static DeviceModel,
+    dual_channel: DualChannel<Message>,
+    (device_request_tx, device_request_rx): (Sender<DeviceRequest>, Receiver<DeviceRequest>),
+    config_snapshot: ConfigSnapshot,
+    profile_switch_tx: ProfileSwitchSender,
+    event_tx: EventSender,
+) -> Result<(), BringUpError> {
+    let mut device_handle = find_device(&serial_number)?;
+    let device = device_handle.device();
+    let config_descriptor = device
+        .config_descriptor(0)
+        .map_err(BringUpError::Descriptor)?;
+    let interface_descriptor = config_descriptor
+        .interfaces()
+        .next()
+        .and_then(|interface| interface.descriptors().next())
+        .ok_or(BringUpError::Descriptor(rusb::Error::NotFound))?;
+    let endpoint_descriptor = interface_descriptor
+        .endpoint_descriptors()
+        .next()
+        .ok_or(BringUpError::Descriptor(rusb::Error::NotFound))?;
+    let endpoint = Endpoint {
+        config: config_descriptor.number(),
+        iface: interface_descriptor.interface_number(),
+        setting: interface_descriptor.setting_number(),
+        address: endpoint_descriptor.address(),
+    };
+
+    let has_kernel_driver = match device_handle.kernel_driver_active(endpoint.iface) {
+        Ok(true) => {
+            device_handle
+                .detach_kernel_driver(endpoint.iface)
+                .map_err(BringUpError::KernelDriver)?;
+            true
+        }
+        _ => false,
+    };
+
+    // the mouse is given back to its kernel driver when it can't be claimed
+    let interrupt_transfer = match claim_device(&mut device_handle, &endpoint).and_then(|_| {
+        InterruptTransfer::submit(
+            &device_handle,
+            endpoint.address,
+            device_model.report.length,
+            device_request_tx,
+        )
+        .map_err(BringUpError::Claim)
+    }) {
+        Ok(interrupt_transfer) => interrupt_transfer,
+        Err(err) => {
+            device_handle.release_interface(endpoint.iface).ok();
+
+            if has_kernel_driver {
+                device_handle.attach_kernel_driver(endpoint.iface).ok();
+            }
+
+            return Err(err);
+        }
     };
+    // shared with the thread dumping the memory
+    let device_handle = Arc::new(device_handle);
+    let mut memory_worker: Option<std::thread::JoinHandle<()>> = None;
+
+    let active_profile = config_snapshot
+        .get(&serial_number)
+        .and_then(|device_snapshot| device_snapshot.active_profile.clone());
+
+    println!("{} connected", serial_number);
+    notification::notify(
+        NotificationKind::Connected,
+        format!(
+            "{} {} connected, {}",
+            device_model.name,
+            serial_number,
+            match &active_profile {
+                Some(profile) => format!("profile '{}' active", profile),
+                None => "default profile active".to_string(),
+            }
+        ),
+    );
+
+    dual_channel.send(Message::DeviceListUpdate).ok();
+    event_tx
+        .send(DriverEvent::Device {
+            serial_number: serial_number.clone(),
+            connected: true,
+        })
+        .ok();
+    // the profile events only follow the changes
+    event_tx
+        .send(DriverEvent::Profile {
+            serial_number: serial_number.clone(),
+            profile: active_profile,
+        })
+        .ok();
+
+    let mut buffer = vec![0; device_model.report.length];
+    let mut led_colors = None;
+    let mut polling_rate = None;
+    let mut dpi_stages = vec![];
+    let mut lift_off_distance = None;
+    let mut read_timeout = polling::read_timeout(None);
+    let mut tick_interval = polling::tick_interval(None, None);
+    let mut ticker = Ticker::default();
+    let mut blink: Option<Blink> = None;
+    let mut idle_tracker = IdleTracker::default();
+    let mut mapper = Mapper::new(
+        device_model,
+        config_snapshot.clone(),
+        profile_switch_tx.clone(),
+        serial_number.clone(),
+        event_tx.clone(),
+    );
+
+    loop {
+        // apply the device settings on connection and on config changes
+        let device_snapshot = config_snapshot.get(&serial_number);
+
+        if let Some(device_snapshot) = &device_snapshot {
+            read_timeout = device_snapshot.read_timeout;
+            tick_interval = device_snapshot.tick_interval;
+
+            // the colors are restored once the identify blink is over
+            if blink.is_none() && led_colors.as_ref() != Some(&device_snapshot.led_colors) {
+                led_colors = Some(device_snapshot.led_colors.clone());
+                update_led_colors(
+                    &device_handle,
+                    device_model,
+                    endpoint.iface,
+                    &serial_number,
+                    &device_snapshot.led_colors,
+                );
+            }
+
+            if device_snapshot.polling_rate != polling_rate {
+                polling_rate = device_snapshot.polling_rate;
+
+                if let Some(polling_rate) = polling_rate {
+                    update_polling_rate(
+                        &device_handle,
+                        device_model,
+                        endpoint.iface,
+                        &serial_number,
+                        polling_rate,
+                    );
+                }
+            }
+
+            if device_snapshot.dpi_stages != dpi_stages
+                || device_snapshot.lift_off_distance != lift_off_distance
+            {
+                dpi_stages = device_snapshot.dpi_stages.clone();
+                lift_off_distance = device_snapshot.lift_off_distance;
+                update_sensor(
+                    &device_handle,
+                    device_model,
+                    endpoint.iface,
+                    &serial_number,
+                    device_snapshot,
+                );
+            }
+        }
+
+        if let Some(blink_) = &mut blink {
+            let result = blink_.tick(&device_handle, device_model, endpoint.iface);
+
+            if !matches!(result, Ok(true)) {
+                if let Err(err) = result {
+                    status::error(format!("{} not identified : {}", serial_number, err));
+                }
+
+                blink = None;
+
+                if let Some(device_snapshot) = &device_snapshot {
+                    led_colors = Some(device_snapshot.led_colors.clone());
+                    update_led_colors(
+                        &device_handle,
+                        device_model,
+                        endpoint.iface,
+                        &serial_number,
+                        &device_snapshot.led_colors,
+                    );
+                }
+            }
+        }
+
+        let mapper_is_idle = mapper.is_idle();
+
+        if idle_tracker.update(mapper_is_idle) {
+            println!("{} suspended while idle", serial_number);
+        }
+
+        // the held and repeated bindings are played on their own cadence, whether reports
+        // keep coming or not
+        if ticker.update(tick_interval, !mapper_is_idle) {
+            mapper.emulate_only_mapped(&buffer);
+        }
+
+        match device_request_rx
+            .recv_timeout(idle_tracker.read_timeout(ticker.timeout(read_timeout)))
+        {
+            Ok(DeviceRequest::Report(report)) => {
+                let length = report.len().min(buffer.len());
+
+                buffer[..length].copy_from_slice(&report[..length]);
+                mapper.emulate(&buffer);
+
+                if idle_tracker.report() {
+                    println!("{} resumed", serial_number);
+                }
+            }
+            Ok(DeviceRequest::ReadError(err)) => {
+                status::error(format!("{} disconnected : {}", serial_number, err));
+                notification::notify(
+                    NotificationKind::Disconnected,
+                    format!("{} {} disconnected", device_model.name, serial_number),
+                );
+                break;
+            }
+            Ok(DeviceRequest::Stop) => {
+                println!("{} released", serial_number);
+                break;
+            }
+            Ok(DeviceRequest::Identify) => blink = Some(Blink::new()),
+            // a request per page, so the reports keep being read meanwhile
+            Ok(DeviceRequest::DumpMemory) => {
+                if memory_worker
+                    .as_ref()
+                    .map(|memory_worker| memory_worker.is_finished())
+                    .unwrap_or(true)
+                {
+                    memory_worker = Some(dump_memory(
+                        device_handle.clone(),
+                        device_model,
+                        endpoint.iface,
+                        serial_number.clone(),
+                        dual_channel.clone(),
+                    ));
+                }
+            }
+            Ok(device_request) => handle_device_request(
+                &device_handle,
+                device_model,
+                &serial_number,
+                &dual_channel,
+                &mut mapper,
+                device_request,
+            ),
+            // applies the config changes while nothing is played
+            Err(RecvTimeoutError::Timeout) if mapper_is_idle => mapper.emulate_only_mapped(&buffer),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    // the outputs are released before the device, and the transfer before the interface
+    drop(mapper);
+    drop(interrupt_transfer);
+    // the dump still running uses the interface
+    if let Some(memory_worker) = memory_worker {
+        memory_worker.join().ok();
+    }
+
+    event_tx
+        .send(DriverEvent::Device {
+            serial_number: serial_number.clone(),
+            connected: false,
+        })
+        .ok();
+    device_handle.release_interface(endpoint.iface).ok();
+
+    if has_kernel_driver {
+        device_handle.attach_kernel_driver(endpoint.iface).ok();
+    }
+
+    Ok(())
+}
+
+fn dump_memory(
+    device_handle: Arc<DeviceHandle<Context>>,
+    device_model: &'static DeviceModel,
+    iface: u8,
+    serial_number: String,
+    dual_channel: DualChannel<Message>,
+) -> std::thread::JoinHandle<()> {
+    spawn(
+        move || match memory::dump(&device_handle, device_model, iface) {
+            Ok(memory) => {
+                dual_channel
+                    .send(Message::OnboardMemory(OnboardMemory::new(
+                        serial_number,
+                        memory,
+                    )))
+                    .ok();
+            }
+            Err(err) => status::error(format!("{} memory not dumped : {}", serial_number, err)),
+        },
+    )
+}
+
+fn claim_device(
+    device_handle: &mut DeviceHandle<Context>,
+    endpoint: &Endpoint,
+) -> Result<(), BringUpError> {
+    device_handle
+        .set_active_configuration(endpoint.config)
+        .map_err(BringUpError::Configuration)?;
+    device_handle
+        .claim_interface(endpoint.iface)
+        .map_err(BringUpError::Claim)?;
+    device_handle
+        .set_alternate_setting(endpoint.iface, endpoint.setting)
+        .map_err(BringUpError::Claim)
+}
+
+fn update_led_colors(
+    device_handle: &DeviceHandle<Context>,
+    device_model: &DeviceModel,
+    iface: u8,
+    serial_number: &str,
+    led_colors: &[LedColor],
+) {
+    if let Err(err) = lighting::set_led_colors(device_handle, device_model, iface, led_colors) {
+        status::error(format!("{} led colors not set : {}", serial_number, err));
+    }
+}
+
+fn update_polling_rate(
+    device_handle: &DeviceHandle<Context>,
+    device_model: &DeviceModel,
+    iface: u8,
+    serial_number: &str,
+    polling_rate: u16,
+) {
+    if let Err(err) = polling::set_polling_rate(device_handle, device_model, iface, polling_rate) {
+        status::error(format!("{} polling rate not set : {}", serial_number, err));
+    }
+}
+
+// the device keeps its own dpi stages and lift-off distance when they're unset
+fn update_sensor(
+    device_handle: &DeviceHandle<Context>,
+    device_model: &DeviceModel,
+    iface: u8,
+    serial_number: &str,
+    device_snapshot: &DeviceSnapshot,
+) {
+    if !device_snapshot.dpi_stages.is_empty() {
+        if let Err(err) = sensor::set_dpi_stages(
+            device_handle,
+            device_model,
+            iface,
+            &device_snapshot.dpi_stages,
+        ) {
+            status::error(format!("{} dpi stages not set : {}", serial_number, err));
+        }
+    }
+
+    if let Some(lift_off_distance) = device_snapshot.lift_off_distance {
+        if let Err(err) =
+            sensor::set_lift_off_distance(device_handle, device_model, iface, lift_off_distance)
+        {
+            status::error(format!(
+                "{} lift-off distance not set : {}",
+                serial_number, err
+            ));
+        }
+    }
+}
+
+fn handle_device_request(
+    device_handle: &DeviceHandle<Context>,
+    device_model: &DeviceModel,
+    serial_number: &str,
+    dual_channel: &DualChannel<Message>,
+    mapper: &mut Mapper,
+    device_request: DeviceRequest,
+) {
+    match device_request {
+        DeviceRequest::PlayMacro(token_string) => mapper.play_macro(token_string),
+        DeviceRequest::SendReportLog => {
+            dual_channel
+                .send(Message::ReportLog(ReportLog::new(
+                    serial_number.to_string(),
+                    mapper.report_lines(),
+                )))
+                .ok();
+        }
+        DeviceRequest::SendDriverStatus => {
+            dual_channel
+                .send(Message::DriverStatus(mapper.driver_status()))
+                .ok();
+        }
+        DeviceRequest::SendDeviceInfo => {
+            match device_info::read(device_handle, device_model, serial_number) {
+                Ok(device_info) => {
+                    dual_channel.send(Message::DeviceInfo(device_info)).ok();
+                }
+                Err(err) => {
+                    status::error(format!("{} device info not read : {}", serial_number, err))
+                }
+            }
+        }
+        // handled by the device loop
+        DeviceRequest::Stop
+        | DeviceRequest::Identify
+        | DeviceRequest::DumpMemory
+        | DeviceRequest::Report(_)
+        | DeviceRequest::ReadError(_) => {}
+    }
+}
+
+// shared by the sessions of the clients
+#[derive(Clone)]
+struct SessionState {
+    client_list: ClientList,
+    device_list_mutex: Arc<Mutex<ConnectedDevices>>,
+    device_errors_mutex: Arc<Mutex<Vec<DeviceError>>>,
+    mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    mouses_config_state_id: Arc<AtomicU32>,
+    event_tx: EventSender,
+}
+
+// connection processing
+// the client app and the clients of the listeners each get their own session,
+// the device updates being sent to all of them
+async fn run_connection(
+    client_dualchannel: DualChannel<ConnectionState>,
+    child: DualChannel<Message>,
+    listener_settings: ListenerSettings,
+    session_state: SessionState,
+) {
+    run_session(session_state.clone(), client_dualchannel, false);
+
+    let listener_vec = [
+        listener_settings
+            .local_port
+            .map(|port| (SocketAddr::from((Ipv4Addr::LOCALHOST, port)), false)),
+        listener_settings
+            .remote_address
+            .map(|address| (address, true)),
+    ];
+
+    for (address, remote) in listener_vec.into_iter().flatten() {
+        let session_state = session_state.clone();
+
+        run_client_listener(address, move |client_dualchannel| {
+            run_session(session_state.clone(), client_dualchannel, remote)
+        })
+        .await;
+    }
+
+    let SessionState {
+        client_list,
+        device_list_mutex,
+        mouses_config_mutex,
+        ..
+    } = session_state;
 
     tokio::spawn(async move {
         loop {
             if let Ok(message) = child.recv_async().await {
                 match message {
                     Message::DeviceListUpdate => {
-                        update_device_list(&client_dualchannel, device_list_mutex.clone()).await;
+                        for client_dualchannel in client_list.others(None) {
+                            update_device_list(
+                                &client_dualchannel,
+                                &device_list_mutex,
+                                &mouses_config_mutex,
+                            )
+                            .await;
+                        }
+                    }
+                    Message::OnboardMemory(onboard_memory) => {
+                        client_list.broadcast(onboard_memory.into_bytes()).await;
+                    }
+                    Message::DriverStatus(driver_status) => {
+                        client_list.broadcast(driver_status.into_bytes()).await;
+                    }
+                    Message::ReportLog(report_log) => {
+                        client_list.broadcast(report_log.into_bytes()).await;
+                    }
+                    Message::DeviceInfo(device_info) => {
+                        client_list.broadcast(device_info.into_bytes()).await;
+                    }
+                    Message::ConfigErrors(config_error_vec) => {
+                        client_list
+                            .broadcast(ConfigErrors::new(config_error_vec).into_bytes())
+                            .await;
+                    }
+                    Message::DeviceErrors(device_error_vec) => {
+                        client_list
+                            .broadcast(DeviceErrors::new(device_error_vec).into_bytes())
+                            .await;
+                    }
+                }
+            }
+        }
+    });
+}
+
+// ends once the channel of the client is closed
+fn run_session(
+    session_state: SessionState,
+    client_dualchannel: DualChannel<ConnectionState>,
+    remote: bool,
+) {
+    let SessionState {
+        client_list,
+        device_list_mutex,
+        device_errors_mutex,
+        mouses_config_mutex,
+        mouses_config_state_id,
+        event_tx,
+    } = session_state;
+    // each connection authenticates again
+    let mut authenticated = !auth::is_required(remote);
+    let client_id = client_list.add(client_dualchannel.clone(), authenticated);
+
+    tokio::spawn(async move {
+        // only set by the token, even when the client doesn't have to send it
+        let mut token_accepted = false;
+        // task forwarding the events of the device the client subscribed to
+        let mut event_forwarding: Option<JoinHandle<()>> = None;
+
+        while let Ok(connection_state) = client_dualchannel.recv_async().await {
+            // the changes made by a client are sent to the others
+            let state_id = mouses_config_state_id.load(Ordering::SeqCst);
+
+            match connection_state {
+                ConnectionState::Start => {
+                    authenticated = !auth::is_required(remote);
+                    token_accepted = false;
+
+                    if let Some(event_forwarding) = event_forwarding.take() {
+                        event_forwarding.abort();
+                    }
+
+                    client_list.set_authenticated(client_id, authenticated);
+                    client_dualchannel
+                        .send_async(ConnectionState::Data(Capabilities::driver().into_bytes()))
+                        .await
+                        .ok();
+
+                    // otherwise sent once the token is accepted
+                    if authenticated {
+                        send_driver_state(
+                            &client_dualchannel,
+                            &device_list_mutex,
+                            &device_errors_mutex,
+                            &mouses_config_mutex,
+                        )
+                        .await;
+                    }
+                }
+                ConnectionState::Data(data) if DriverCommands::is_driver_command(&data) => {
+                    match DriverCommands::from_bytes(&data) {
+                        Some(DriverCommands::Authenticate(authenticate)) => {
+                            let was_authenticated = authenticated;
+
+                            authenticated = auth::verify(&authenticate.token);
+                            token_accepted = authenticated;
+                            client_list.set_authenticated(client_id, authenticated);
+                            client_dualchannel
+                                .send_async(ConnectionState::Data(
+                                    Authentication::new(authenticated).into_bytes(),
+                                ))
+                                .await
+                                .ok();
+
+                            if authenticated && !was_authenticated {
+                                send_driver_state(
+                                    &client_dualchannel,
+                                    &device_list_mutex,
+                                    &device_errors_mutex,
+                                    &mouses_config_mutex,
+                                )
+                                .await;
+                            }
+                            continue;
+                        }
+                        // the versions are needed to authenticate
+                        Some(DriverCommands::Capabilities(_)) => {}
+                        _ if !authenticated => {
+                            client_dualchannel
+                                .send_async(ConnectionState::Data(
+                                    Authentication::new(false).into_bytes(),
+                                ))
+                                .await
+                                .ok();
+                            continue;
+                        }
+                        // the files are on the machine running the driver, so only the local
+                        // clients which sent the token can read or write them
+                        Some(DriverCommands::ExportProfile(export_profile))
+                            if remote || !token_accepted =>
+                        {
+                            client_dualchannel
+                                .send_async(ConnectionState::Data(
+                                    ProfileTransfer::new(
+                                        export_profile.serial_number,
+                                        export_profile.profile,
+                                        Some(profile_transfer_refusal(remote)),
+                                    )
+                                    .into_bytes(),
+                                ))
+                                .await
+                                .ok();
+                            continue;
+                        }
+                        Some(DriverCommands::ImportProfile(import_profile))
+                            if remote || !token_accepted =>
+                        {
+                            client_dualchannel
+                                .send_async(ConnectionState::Data(
+                                    ProfileTransfer::new(
+                                        import_profile.serial_number,
+                                        None,
+                                        Some(profile_transfer_refusal(remote)),
+                                    )
+                                    .into_bytes(),
+                                ))
+                                .await
+                                .ok();
+                            continue;
+                        }
+                        Some(DriverCommands::SubscribeEvents(subscribe_events)) => {
+                            if let Some(event_forwarding) = event_forwarding.take() {
+                                event_forwarding.abort();
+                            }
+
+                            event_forwarding =
+                                subscribe_events.serial_number.map(|serial_number| {
+                                    forward_events(
+                                        client_dualchannel.clone(),
+                                        &event_tx,
+                                        serial_number,
+                                    )
+                                });
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    handle_driver_command(
+                        &client_list,
+                        &client_dualchannel,
+                        &data,
+                        &device_list_mutex,
+                        &mouses_config_mutex,
+                        &mouses_config_state_id,
+                    )
+                    .await;
+                }
+                ConnectionState::Data(_) if !authenticated => {
+                    client_dualchannel
+                        .send_async(ConnectionState::Data(
+                            Authentication::new(false).into_bytes(),
+                        ))
+                        .await
+                        .ok();
+                }
+                ConnectionState::Data(data) => {
+                    let command_id = data.first().copied().unwrap_or_default();
+
+                    match Commands::from(data) {
+                        Commands::RequestDeviceConfig(request_device_config) => {
+                            let mouses_config = mouses_config_mutex.lock().await;
+
+                            if let Some(mouse_config) = mouses_config
+                                .config
+                                .get(&request_device_config.serial_number)
+                            {
+                                client_dualchannel
+                                    .send_async(ConnectionState::Data(
+                                        DeviceConfig::new(
+                                            request_device_config.serial_number,
+                                            mouse_config
+                                                .selected_button_configs()
+                                                .to_config(mouse_config.device_model()),
+                                        )
+                                        .to_bytes(),
+                                    ))
+                                    .await
+                                    .ok();
+                            }
+                        }
+                        Commands::DeviceConfig(device_config) => {
+                            let mut mouses_config = mouses_config_mutex.lock().await;
+
+                            let mouse_config = mouses_config
+                                .config
+                                .entry(device_config.serial_number)
+                                .or_default();
+                            let device_model = mouse_config.device_model();
+
+                            // the client edits the profile activated by the user
+                            mouse_config
+                                .selected_button_configs_mut()
+                                .update_from_config(device_model, &device_config.config);
+                            mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                            // a bad push from the client can be undone
+                            backup::backup();
+                            mouses_config.save();
+                        }
+                        _ => {
+                            client_dualchannel
+                                .send_async(ConnectionState::Data(
+                                    UnsupportedCommand::new(false, command_id as u32).into_bytes(),
+                                ))
+                                .await
+                                .ok();
+                        }
                     }
                 }
+                ConnectionState::End => {
+                    authenticated = !auth::is_required(remote);
+                    token_accepted = false;
+                    client_list.set_authenticated(client_id, authenticated);
+
+                    if let Some(event_forwarding) = event_forwarding.take() {
+                        event_forwarding.abort();
+                    }
+                }
+            }
+
+            if mouses_config_state_id.load(Ordering::SeqCst) != state_id {
+                send_config_change(
+                    &client_list,
+                    client_id,
+                    &device_list_mutex,
+                    &mouses_config_mutex,
+                )
+                .await;
             }
         }
+
+        if let Some(event_forwarding) = event_forwarding {
+            event_forwarding.abort();
+        }
+
+        client_list.remove(client_id);
     });
 }
 
+fn profile_transfer_refusal(remote: bool) -> String {
+    if remote {
+        "not allowed for remote clients".to_string()
+    } else {
+        "the client has to authenticate first".to_string()
+    }
+}
+
+// the device list and the errors, once the client is allowed to see them
+async fn send_driver_state(
+    client_dualchannel: &DualChannel<ConnectionState>,
+    device_list_mutex: &Mutex<ConnectedDevices>,
+    device_errors_mutex: &Mutex<Vec<DeviceError>>,
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
+) {
+    update_device_list(client_dualchannel, device_list_mutex, mouses_config_mutex).await;
+
+    let config_error_vec = validation::config_errors(&mouses_config_mutex.lock().await.config)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    client_dualchannel
+        .send_async(ConnectionState::Data(
+            ConfigErrors::new(config_error_vec).into_bytes(),
+        ))
+        .await
+        .ok();
+
+    let device_error_vec = device_errors_mutex.lock_poisoned().clone();
+
+    client_dualchannel
+        .send_async(ConnectionState::Data(
+            DeviceErrors::new(device_error_vec).into_bytes(),
+        ))
+        .await
+        .ok();
+}
+
+async fn handle_driver_command(
+    client_list: &ClientList,
+    client_dualchannel: &DualChannel<ConnectionState>,
+    data: &[u8],
+    device_list_mutex: &Mutex<ConnectedDevices>,
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
+    mouses_config_state_id: &AtomicU32,
+) {
+    match DriverCommands::from_bytes(data) {
+        Some(DriverCommands::RequestWindowRules(request_window_rules)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config
+                .config
+                .get(&request_window_rules.serial_number)
+            {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        WindowRules::new(
+                            request_window_rules.serial_number,
+                            mouse_config.window_rules.clone(),
+                        )
+                        .into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        Some(DriverCommands::WindowRules(window_rules)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get_mut(&window_rules.serial_number) {
+                mouse_config.window_rules = window_rules.window_rules;
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                mouses_config.save();
+            }
+        }
+        Some(DriverCommands::RequestLedColors(request_led_colors)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get(&request_led_colors.serial_number)
+            {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        LedColors::new(
+                            request_led_colors.serial_number,
+                            mouse_config.settings.led_colors.clone(),
+                        )
+                        .into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        Some(DriverCommands::LedColors(led_colors)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get_mut(&led_colors.serial_number) {
+                mouse_config.settings.led_colors = led_colors.led_colors;
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                mouses_config.save();
+            }
+        }
+        Some(DriverCommands::RequestPollingRate(request_polling_rate)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config
+                .config
+                .get(&request_polling_rate.serial_number)
+            {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        PollingRate::new(
+                            request_polling_rate.serial_number,
+                            mouse_config.settings.polling_rate,
+                        )
+                        .into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        Some(DriverCommands::DeviceNickname(device_nickname)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get_mut(&device_nickname.serial_number)
+            {
+                // blank nicknames are removed
+                mouse_config.nickname = device_nickname
+                    .nickname
+                    .map(|nickname| nickname.trim().to_string())
+                    .filter(|nickname| !nickname.is_empty());
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                mouses_config.save();
+                drop(mouses_config);
+
+                update_device_list(client_dualchannel, device_list_mutex, mouses_config_mutex)
+                    .await;
+            }
+        }
+        Some(DriverCommands::RequestSyncStatus(_)) => {
+            client_dualchannel
+                .send_async(ConnectionState::Data(sync::status().into_bytes()))
+                .await
+                .ok();
+        }
+        // the status is requested again to know how it went
+        Some(DriverCommands::RunSync(run_sync)) => {
+            if !sync::request(run_sync.action) {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        SyncStatus::new(SyncState::Disabled, None).into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        Some(DriverCommands::RequestBackups(_)) => {
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    Backups::new(backup::list(), None).into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        // answered with the backups, including the one of the replaced file
+        Some(DriverCommands::RestoreBackup(restore_backup)) => {
+            let error = match backup::restore(&restore_backup.name) {
+                Ok(()) => {
+                    if reload_mouses_config(&mut *mouses_config_mutex.lock().await) {
+                        mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                    }
+
+                    println!("Backup {} restored", restore_backup.name);
+                    None
+                }
+                Err(err) => {
+                    status::error(format!("Backup not restored : {}", err));
+                    Some(err)
+                }
+            };
+
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    Backups::new(backup::list(), error).into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        Some(DriverCommands::ListPresets(_)) => {
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    Presets::new(presets::PRESETS.iter().map(Preset::info).collect()).into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        Some(DriverCommands::RequestSettings(request_settings)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get(&request_settings.serial_number) {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        Settings::new(
+                            request_settings.serial_number,
+                            mouse_config.settings.clone(),
+                        )
+                        .into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        // the whole block is refused when a setting is invalid or can't be applied by the model,
+        // each refused setting being answered
+        Some(DriverCommands::Settings(settings)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+            let mouse_config = match mouses_config.config.get_mut(&settings.serial_number) {
+                Some(mouse_config) => mouse_config,
+                None => return,
+            };
+            let error_vec: Vec<(&str, String)> = settings
+                .settings
+                .check()
+                .into_iter()
+                .chain(settings.settings.unsupported(mouse_config.device_model()))
+                .collect();
+
+            if error_vec.is_empty() {
+                mouse_config.settings = settings.settings;
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                mouses_config.save();
+                return;
+            }
+
+            drop(mouses_config);
+
+            for (field, message) in error_vec {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        SettingError::new(settings.serial_number.clone(), field, message)
+                            .into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+        // refused when the rate isn't supported, or when the model can't change it
+        Some(DriverCommands::PollingRate(polling_rate)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+            let mouse_config = match mouses_config.config.get_mut(&polling_rate.serial_number) {
+                Some(mouse_config) => mouse_config,
+                None => return,
+            };
+            let mut settings = mouse_config.settings.clone();
+
+            settings.polling_rate = polling_rate.polling_rate;
+
+            let error = settings
+                .check()
+                .into_iter()
+                .chain(settings.unsupported(mouse_config.device_model()))
+                .find(|(field, _)| *field == "polling_rate");
+
+            match error {
+                Some((field, message)) => {
+                    drop(mouses_config);
+                    client_dualchannel
+                        .send_async(ConnectionState::Data(
+                            SettingError::new(polling_rate.serial_number, field, message)
+                                .into_bytes(),
+                        ))
+                        .await
+                        .ok();
+                }
+                None => {
+                    mouse_config.settings = settings;
+                    mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                    mouses_config.save();
+                }
+            }
+        }
+        // the device thread answers with the dump
+        Some(DriverCommands::RequestOnboardMemory(request_onboard_memory)) => {
+            if let Some(device_request_tx) = device_list_mutex
+                .lock_poisoned()
+                .get(&request_onboard_memory.serial_number)
+            {
+                device_request_tx.send(DeviceRequest::DumpMemory).ok();
+            }
+        }
+        // the device thread answers with its log
+        Some(DriverCommands::RequestReportLog(request_report_log)) => {
+            if let Some(device_request_tx) = device_list_mutex
+                .lock_poisoned()
+                .get(&request_report_log.serial_number)
+            {
+                device_request_tx.send(DeviceRequest::SendReportLog).ok();
+            }
+        }
+        // the device thread answers with its counters
+        Some(DriverCommands::RequestDriverStatus(request_driver_status)) => {
+            if let Some(device_request_tx) = device_list_mutex
+                .lock_poisoned()
+                .get(&request_driver_status.serial_number)
+            {
+                device_request_tx.send(DeviceRequest::SendDriverStatus).ok();
+            }
+        }
+        Some(DriverCommands::IdentifyDevice(identify_device)) => {
+            if let Some(device_request_tx) = device_list_mutex
+                .lock_poisoned()
+                .get(&identify_device.serial_number)
+            {
+                device_request_tx.send(DeviceRequest::Identify).ok();
+            }
+        }
+        // the device thread answers with what it reads from the device
+        Some(DriverCommands::RequestDeviceInfo(request_device_info)) => {
+            if let Some(device_request_tx) = device_list_mutex
+                .lock_poisoned()
+                .get(&request_device_info.serial_number)
+            {
+                device_request_tx.send(DeviceRequest::SendDeviceInfo).ok();
+            }
+        }
+        Some(DriverCommands::ExportProfile(export_profile)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+            let result = match mouses_config.config.get(&export_profile.serial_number) {
+                Some(mouse_config) => profile_file::export(
+                    mouse_config,
+                    export_profile.profile.as_deref(),
+                    &export_profile.file_name,
+                )
+                .map_err(|err| err.to_string()),
+                None => Err("unknown device".to_string()),
+            };
+
+            drop(mouses_config);
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    ProfileTransfer::new(
+                        export_profile.serial_number,
+                        export_profile.profile,
+                        result.err(),
+                    )
+                    .into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        Some(DriverCommands::ImportProfile(import_profile)) => {
+            let mut mouses_config = mouses_config_mutex.lock().await;
+            let result = match mouses_config.config.get_mut(&import_profile.serial_number) {
+                Some(mouse_config) => profile_file::import(
+                    mouse_config,
+                    &import_profile.file_name,
+                    &import_profile.profile,
+                    import_profile.overwrite,
+                )
+                .map_err(|err| err.to_string()),
+                None => Err("unknown device".to_string()),
+            };
+
+            if result.is_ok() {
+                mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                mouses_config.save();
+            }
+
+            drop(mouses_config);
+
+            let (profile, error) = match result {
+                Ok(profile) => (Some(profile), None),
+                Err(err) => (None, Some(err)),
+            };
+
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    ProfileTransfer::new(import_profile.serial_number, profile, error).into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        Some(DriverCommands::RequestProfiles(request_profiles)) => {
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            if let Some(mouse_config) = mouses_config.config.get(&request_profiles.serial_number) {
+                send_profiles(
+                    client_dualchannel,
+                    request_profiles.serial_number,
+                    mouse_config,
+                    None,
+                )
+                .await;
+            }
+        }
+        // profile changes are answered with the updated profile list
+        Some(DriverCommands::CreateProfile(create_profile)) => {
+            update_profiles(
+                client_dualchannel,
+                create_profile.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| {
+                    mouse_config.create_profile(
+                        &create_profile.profile,
+                        create_profile.copy_from.as_deref(),
+                    )
+                },
+            )
+            .await;
+        }
+        Some(DriverCommands::RenameProfile(rename_profile)) => {
+            update_profiles(
+                client_dualchannel,
+                rename_profile.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| {
+                    mouse_config.rename_profile(&rename_profile.profile, &rename_profile.new_name)
+                },
+            )
+            .await;
+        }
+        Some(DriverCommands::DeleteProfile(delete_profile)) => {
+            update_profiles(
+                client_dualchannel,
+                delete_profile.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| mouse_config.delete_profile(&delete_profile.profile),
+            )
+            .await;
+        }
+        Some(DriverCommands::ShareProfile(share_profile)) => {
+            update_profiles(
+                client_dualchannel,
+                share_profile.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| {
+                    mouse_config.share_profile(&share_profile.profile, share_profile.shared)
+                },
+            )
+            .await;
+        }
+        Some(DriverCommands::ApplyPreset(apply_preset)) => {
+            update_profiles(
+                client_dualchannel,
+                apply_preset.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| {
+                    mouse_config.apply_preset(&apply_preset.preset, apply_preset.profile.as_deref())
+                },
+            )
+            .await;
+        }
+        Some(DriverCommands::Capabilities(capabilities)) => {
+            if capabilities.protocol_version != PROTOCOL_VERSION {
+                println!(
+                    "Client protocol version {}, the driver using version {}",
+                    capabilities.protocol_version, PROTOCOL_VERSION
+                );
+            }
+
+            client_dualchannel
+                .send_async(ConnectionState::Data(Capabilities::driver().into_bytes()))
+                .await
+                .ok();
+        }
+        Some(DriverCommands::GetStatus(_)) => {
+            let serial_number_vec: Vec<String> =
+                device_list_mutex.lock_poisoned().keys().cloned().collect();
+            let backend_map = serial_number_vec
+                .iter()
+                .filter_map(|serial_number| {
+                    status::backend(serial_number)
+                        .map(|backend_kind| (serial_number.clone(), backend_kind))
+                })
+                .collect();
+
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    Status::new(
+                        status::uptime().as_secs(),
+                        serial_number_vec.len() as u32,
+                        backend_map,
+                        status::last_errors(),
+                    )
+                    .into_bytes(),
+                ))
+                .await
+                .ok();
+        }
+        // the macro is played from the thread of the device, like the api does
+        Some(DriverCommands::TestMacro(test_macro)) => {
+            let mut error_vec = {
+                let mouses_config = mouses_config_mutex.lock().await;
+                let mouse_config = mouses_config.config.get(&test_macro.serial_number);
+
+                check_binding(&test_macro.tokens, |profile| {
+                    mouse_config
+                        .map(|mouse_config| mouse_config.profiles.contains_key(profile))
+                        .unwrap_or(false)
+                })
+            };
+            let device_request_tx = device_list_mutex
+                .lock_poisoned()
+                .get(&test_macro.serial_number)
+                .cloned();
+            let countdown = match (test_macro.countdown, device_request_tx) {
+                (Some(_), None) => {
+                    error_vec.push(format!(
+                        "device {} isn't connected",
+                        test_macro.serial_number
+                    ));
+                    None
+                }
+                (Some(countdown), Some(device_request_tx)) if error_vec.is_empty() => {
+                    Some((countdown, device_request_tx))
+                }
+                _ => None,
+            };
+
+            client_dualchannel
+                .send_async(ConnectionState::Data(
+                    MacroTest::new(test_macro.serial_number, error_vec, countdown.is_some())
+                        .into_bytes(),
+                ))
+                .await
+                .ok();
+
+            if let Some((countdown, device_request_tx)) = countdown {
+                tokio::spawn(async move {
+                    sleep(Duration::from_secs(countdown)).await;
+                    device_request_tx
+                        .send(DeviceRequest::PlayMacro(test_macro.tokens))
+                        .ok();
+                });
+            }
+        }
+        // every client is told, as the device is gone from their config too
+        Some(DriverCommands::ForgetDevice(forget_device)) => {
+            let serial_number = forget_device.serial_number;
+            let error = if device_list_mutex
+                .lock_poisoned()
+                .contains_key(&serial_number)
+            {
+                Some(format!("device {} is connected", serial_number))
+            } else {
+                let mut mouses_config = mouses_config_mutex.lock().await;
+
+                if mouses_config.config.contains_key(&serial_number) {
+                    // a device forgotten by mistake can be restored
+                    backup::backup();
+                    mouses_config.config.remove(&serial_number);
+                    mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                    mouses_config.save();
+                    println!("Device {} forgotten", serial_number);
+                    None
+                } else {
+                    Some(format!("unknown device {}", serial_number))
+                }
+            };
+
+            match error {
+                Some(error) => {
+                    client_dualchannel
+                        .send_async(ConnectionState::Data(
+                            DeviceForgotten::new(serial_number, Some(error)).into_bytes(),
+                        ))
+                        .await
+                        .ok();
+                }
+                None => {
+                    client_list
+                        .broadcast(DeviceForgotten::new(serial_number, None).into_bytes())
+                        .await;
+                }
+            }
+        }
+        Some(DriverCommands::ActivateProfile(activate_profile)) => {
+            update_profiles(
+                client_dualchannel,
+                activate_profile.serial_number,
+                mouses_config_mutex,
+                mouses_config_state_id,
+                |mouse_config| mouse_config.activate_profile(activate_profile.profile.as_deref()),
+            )
+            .await;
+        }
+        // only sent by the driver
+        Some(DriverCommands::ProfileTransfer(_))
+        | Some(DriverCommands::SettingError(_))
+        | Some(DriverCommands::OnboardMemory(_))
+        | Some(DriverCommands::Profiles(_))
+        | Some(DriverCommands::ConfigErrors(_))
+        | Some(DriverCommands::ReportLog(_))
+        | Some(DriverCommands::DriverStatus(_))
+        | Some(DriverCommands::DeviceInfo(_))
+        | Some(DriverCommands::DeviceErrors(_))
+        | Some(DriverCommands::DeviceNicknames(_))
+        | Some(DriverCommands::Backups(_))
+        | Some(DriverCommands::SyncStatus(_))
+        | Some(DriverCommands::Presets(_))
+        | Some(DriverCommands::UnsupportedCommand(_))
+        | Some(DriverCommands::Authentication(_))
+        | Some(DriverCommands::DeviceForgotten(_))
+        | Some(DriverCommands::ButtonEvent(_))
+        | Some(DriverCommands::ModeEvent(_))
+        | Some(DriverCommands::MacroTest(_))
+        | Some(DriverCommands::Status(_)) => {}
+        // handled with the connection
+        Some(DriverCommands::Authenticate(_)) | Some(DriverCommands::SubscribeEvents(_)) => {}
+        // sent by a newer client
+        None => {
+            if let Some(index) = DriverCommands::index(data) {
+                client_dualchannel
+                    .send_async(ConnectionState::Data(
+                        UnsupportedCommand::new(true, index).into_bytes(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+    }
+}
+
+async fn update_profiles(
+    client_dualchannel: &DualChannel<ConnectionState>,
+    serial_number: String,
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
+    mouses_config_state_id: &AtomicU32,
+    update: impl FnOnce(&mut MouseConfig) -> Result<(), String>,
+) {
+    let mut mouses_config = mouses_config_mutex.lock().await;
+    let mouse_config = match mouses_config.config.get_mut(&serial_number) {
+        Some(mouse_config) => mouse_config,
+        None => return,
+    };
+    let error = update(mouse_config).err();
+
+    if error.is_none() {
+        mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+        mouses_config.save();
+    }
+
+    send_profiles(
+        client_dualchannel,
+        serial_number.clone(),
+        &mouses_config.config[&serial_number],
+        error,
+    )
+    .await;
+}
+
+async fn send_profiles(
+    client_dualchannel: &DualChannel<ConnectionState>,
+    serial_number: String,
+    mouse_config: &MouseConfig,
+    error: Option<String>,
+) {
+    client_dualchannel
+        .send_async(ConnectionState::Data(
+            mouse_config.profiles(serial_number, error).into_bytes(),
+        ))
+        .await
+        .ok();
+}
+
+// the device list, and the profiles and the bindings of the profile edited by the clients,
+// as they might be outdated
+async fn send_config_change(
+    client_list: &ClientList,
+    client_id: u32,
+    device_list_mutex: &Mutex<ConnectedDevices>,
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
+) {
+    let device_config_vec: Vec<Vec<u8>> = mouses_config_mutex
+        .lock()
+        .await
+        .config
+        .iter()
+        .flat_map(|(serial_number, mouse_config)| {
+            [
+                mouse_config
+                    .profiles(serial_number.clone(), None)
+                    .into_bytes(),
+                DeviceConfig::new(
+                    serial_number.clone(),
+                    mouse_config
+                        .selected_button_configs()
+                        .to_config(mouse_config.device_model()),
+                )
+                .to_bytes(),
+            ]
+        })
+        .collect();
+
+    for client_dualchannel in client_list.others(Some(client_id)) {
+        update_device_list(&client_dualchannel, device_list_mutex, mouses_config_mutex).await;
+
+        for device_config in &device_config_vec {
+            client_dualchannel
+                .send_async(ConnectionState::Data(device_config.clone()))
+                .await
+                .ok();
+        }
+    }
+}
+
+// the descriptors come before the list, so the client knows the buttons of each model,
+// and the nicknames follow it, as it only holds the serial numbers
 async fn update_device_list(
     client_dualchannel: &DualChannel<ConnectionState>,
-    device_list_mutex: Arc<Mutex<HashSet<String>>>,
+    device_list_mutex: &Mutex<ConnectedDevices>,
+    mouses_config_mutex: &tokio::sync::Mutex<ConfigManager<MousesConfig>>,
 ) {
     let mut serial_number_vec = vec![];
 
-    for serial_number in device_list_mutex.lock_poisoned().iter() {
+    for serial_number in device_list_mutex.lock_poisoned().keys() {
         serial_number_vec.push(serial_number.clone());
     }
 
+    let (device_model_vec, nickname_map) = {
+        let mouses_config = mouses_config_mutex.lock().await;
+        let mouse_config_vec: Vec<(&String, &MouseConfig)> = serial_number_vec
+            .iter()
+            .filter_map(|serial_number| {
+                mouses_config
+                    .config
+                    .get(serial_number)
+                    .map(|mouse_config| (serial_number, mouse_config))
+            })
+            .collect();
+
+        (
+            model::distinct_models(
+                mouse_config_vec
+                    .iter()
+                    .map(|(_, mouse_config)| mouse_config.device_model()),
+            ),
+            mouse_config_vec
+                .iter()
+                .filter_map(|(serial_number, mouse_config)| {
+                    mouse_config
+                        .nickname
+                        .clone()
+                        .map(|nickname| (serial_number.to_string(), nickname))
+                })
+                .collect(),
+        )
+    };
+
+    for device_model in device_model_vec {
+        client_dualchannel
+            .send_async(ConnectionState::Data(device_model.descriptor().to_bytes()))
+            .await
+            .ok();
+    }
+
     client_dualchannel
         .send_async(ConnectionState::Data(
             DeviceList::new(serial_number_vec).to_bytes(),
         ))
         .await
         .ok();
+    client_dualchannel
+        .send_async(ConnectionState::Data(
+            DeviceNicknames::new(nickname_map).into_bytes(),
+        ))
+        .await
+        .ok();
 }
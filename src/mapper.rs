@@ -1,60 +1,386 @@
 use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::rc::Rc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
-use std::thread::spawn;
-use std::time::Duration;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
 
-use crate::{ButtonConfig, ButtonConfigs, MousesConfig};
+use crate::backend::{Backend, BackendFactory, NOTCH_DELTA};
+use crate::clipboard;
+use crate::command::DriverStatus;
+use crate::exec;
+use crate::gamepad::{Gamepad, GamepadInput};
+use crate::grab;
+use crate::metrics::{self, Metrics, ReportRate};
+use crate::midi;
+use crate::model::{mirrored_key, DeviceModel, WHEEL_KEYS};
+use crate::movement::{MovementFilter, MovementSettings};
+use crate::plugin;
+use crate::polling;
+use crate::report_log::ReportLog;
+use crate::scancode::KeyMode;
+use crate::script::{interpret, Script, ScriptContext, Variables, SCRIPT_PREFIX};
+#[cfg(feature = "rhai")]
+use crate::scripting::{self, ScriptEngine, RHAI_PREFIX};
+use crate::settings::DeviceSettings;
+use crate::snapshot::{ConfigSnapshot, DeviceSnapshot, ProfileSwitchSender};
+use crate::status;
+use crate::tokenizer::{self, tokenize, Button, ProfileSwitch, StateToken, Token};
+use crate::turbo::{self, TurboCommand};
+#[cfg(feature = "wasm")]
+use crate::wasm::{self, WasmCall, WASM_PREFIX};
+use crate::websocket::{DriverEvent, EventSender};
+use crate::window;
+use crate::{ButtonConfig, ButtonConfigs, Chord};
 
-use enigo::{Enigo, KeyboardControllable, MouseButton, MouseControllable};
 use thread_priority::{set_current_thread_priority, ThreadPriority};
-use util::config::ConfigManager;
 use util::thread::CondMutex;
 use util::time::Timer;
-use util::tokenizer::{tokenize, Button, Key, StateToken, Token};
 
-type ButtonConfigToken = [[StateToken; 3]; 2];
+// separates the momentary action from the action latched by a double tap
+const DOUBLE_TAP_SEPARATOR: &str = "{doubletap}";
+const DEFAULT_DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(250);
+// separates the action played on a tap from the action played while the button is held
+const HOLD_SEPARATOR: &str = "{hold}";
+const DEFAULT_HOLD_THRESHOLD: Duration = Duration::from_millis(200);
+const DEFAULT_REPEAT_INTERVAL: Duration = Duration::from_millis(50);
+// scrolled by a notch with the default settings of the systems
+const SYSTEM_LINES_PER_NOTCH: f32 = 3.0;
+
+// set from the tray, the mouse keeps moving and clicking but the bindings aren't played
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone)]
+enum ActionToken {
+    Static(Arc<StateToken>),
+    Script(Script),
+    #[cfg(feature = "rhai")]
+    Rhai(Arc<rhai::AST>),
+    #[cfg(feature = "wasm")]
+    Wasm(Arc<wasmtime::Module>),
+}
+
+impl Default for ActionToken {
+    fn default() -> Self {
+        Self::Static(Arc::default())
+    }
+}
+
+impl ActionToken {
+    fn from_config(config: &str) -> Self {
+        #[cfg(feature = "rhai")]
+        if let Some(source) = config.strip_prefix(RHAI_PREFIX) {
+            return match scripting::compile(source) {
+                Some(ast) => Self::Rhai(Arc::new(ast)),
+                None => Self::default(),
+            };
+        }
+
+        #[cfg(feature = "wasm")]
+        if let Some(path) = config.strip_prefix(WASM_PREFIX) {
+            return match wasm::load(path) {
+                Some(module) => Self::Wasm(module),
+                None => Self::default(),
+            };
+        }
+
+        match config.strip_prefix(SCRIPT_PREFIX) {
+            Some(source) => Self::Script(Script::new(source.to_string())),
+            None => Self::Static(Arc::new(tokenize(config.to_string()))),
+        }
+    }
+
+    // rhai scripts and wasm modules produce their tokens when run, they have no state token,
+    // nor the scripts until they're evaluated into a static action on press
+    fn state_token(&self) -> Option<Arc<StateToken>> {
+        match self {
+            Self::Static(state_token) => Some(state_token.clone()),
+            Self::Script(_) => None,
+            #[cfg(feature = "rhai")]
+            Self::Rhai(_) => None,
+            #[cfg(feature = "wasm")]
+            Self::Wasm(_) => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Static(state_token) => {
+                state_token.down.is_empty()
+                    && state_token.repeat.is_empty()
+                    && state_token.up.is_empty()
+            }
+            Self::Script(_) => false,
+            #[cfg(feature = "rhai")]
+            Self::Rhai(_) => false,
+            #[cfg(feature = "wasm")]
+            Self::Wasm(_) => false,
+        }
+    }
+
+    fn is_toggle(&self) -> bool {
+        match self {
+            Self::Static(state_token) => state_token.toggle,
+            Self::Script(script) => script.is_toggle(),
+            #[cfg(feature = "rhai")]
+            Self::Rhai(_) => false,
+            #[cfg(feature = "wasm")]
+            Self::Wasm(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BindingToken {
+    action: ActionToken,
+    double_tap: Option<Arc<StateToken>>,
+    hold: Option<Arc<StateToken>>,
+    // used when the state token doesn't set its own interval
+    repeat_interval: Duration,
+}
+
+impl Default for BindingToken {
+    fn default() -> Self {
+        Self {
+            action: ActionToken::default(),
+            double_tap: None,
+            hold: None,
+            repeat_interval: DEFAULT_REPEAT_INTERVAL,
+        }
+    }
+}
+
+impl BindingToken {
+    // a binding has either a double tap or a hold action
+    fn from_config(config: &str, repeat_interval: Duration) -> Self {
+        if let Some((action, double_tap)) = config.split_once(DOUBLE_TAP_SEPARATOR) {
+            return Self {
+                action: ActionToken::from_config(action),
+                double_tap: Some(Arc::new(tokenize(double_tap.to_string()))),
+                hold: None,
+                repeat_interval,
+            };
+        }
+
+        match config.split_once(HOLD_SEPARATOR) {
+            Some((action, hold)) => Self {
+                action: ActionToken::from_config(action),
+                double_tap: None,
+                hold: Some(Arc::new(tokenize(hold.to_string()))),
+                repeat_interval,
+            },
+            None => Self {
+                action: ActionToken::from_config(config),
+                double_tap: None,
+                hold: None,
+                repeat_interval,
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.action.is_empty() && self.double_tap.is_none() && self.hold.is_none()
+    }
+}
+
+// same parsing as the binding token, reporting what would be silently ignored
+pub fn check_binding(config: &str, profile_exists: impl Fn(&str) -> bool) -> Vec<String> {
+    let (action, other_action) = config
+        .split_once(DOUBLE_TAP_SEPARATOR)
+        .or_else(|| config.split_once(HOLD_SEPARATOR))
+        .unwrap_or((config, ""));
+    let mut error_vec = tokenizer::check(other_action, &profile_exists);
+
+    #[cfg(feature = "rhai")]
+    if let Some(source) = action.strip_prefix(RHAI_PREFIX) {
+        error_vec.extend(scripting::check(source));
+
+        return error_vec;
+    }
+
+    #[cfg(feature = "wasm")]
+    if let Some(path) = action.strip_prefix(WASM_PREFIX) {
+        error_vec.extend(wasm::check(path));
+
+        return error_vec;
+    }
+
+    let action = action.strip_prefix(SCRIPT_PREFIX).unwrap_or(action);
+
+    error_vec.extend(tokenizer::check(action, &profile_exists));
+    error_vec
+}
+
+type ButtonConfigToken = [[BindingToken; 3]; 2];
+
+#[derive(Debug)]
+struct ChordToken {
+    // indexes of the buttons in the button order of the device model
+    buttons: Vec<usize>,
+    action: ActionToken,
+}
+
+fn mirror(key: &str, left_handed: bool) -> &str {
+    if left_handed {
+        mirrored_key(key)
+    } else {
+        key
+    }
+}
+
+impl ChordToken {
+    // none when a button is unknown or when there are less than two buttons
+    fn from_config(chord: &Chord, device_model: &DeviceModel, left_handed: bool) -> Option<Self> {
+        let buttons = chord
+            .buttons
+            .iter()
+            .map(|button| device_model.button_index(mirror(button, left_handed)))
+            .collect::<Option<Vec<usize>>>()?;
+
+        if buttons.len() < 2 {
+            return None;
+        }
+
+        Some(Self {
+            buttons,
+            action: ActionToken::from_config(&chord.binding),
+        })
+    }
+}
 
 #[derive(Debug)]
 pub struct ButtonConfigsToken {
-    scroll_button: ButtonConfigToken,
-    left_actionlock: ButtonConfigToken,
-    right_actionlock: ButtonConfigToken,
-    forwards_button: ButtonConfigToken,
-    back_button: ButtonConfigToken,
-    thumb_anticlockwise: ButtonConfigToken,
-    thumb_clockwise: ButtonConfigToken,
-    hat_top: ButtonConfigToken,
-    hat_left: ButtonConfigToken,
-    hat_right: ButtonConfigToken,
-    hat_bottom: ButtonConfigToken,
-    button_1: ButtonConfigToken,
-    precision_aim: ButtonConfigToken,
-    button_2: ButtonConfigToken,
-    button_3: ButtonConfigToken,
+    // in the button order of the device model
+    buttons: Vec<ButtonConfigToken>,
+    // in the order of the wheel keys
+    wheel: [ButtonConfigToken; 2],
+    debounce: Vec<Duration>,
+    chords: Vec<ChordToken>,
+    double_tap_window: Duration,
+    hold_threshold: Duration,
+    movement: MovementSettings,
+    shift_toggle: bool,
+    left_handed: bool,
+    key_mode: KeyMode,
+    // delta of a notch when scrolling smoothly
+    smooth_scroll: Option<i32>,
 }
 
 impl ButtonConfigsToken {
-    fn from_config(button_configs: ButtonConfigs) -> Self {
+    pub fn from_config(
+        button_configs: &ButtonConfigs,
+        device_model: &DeviceModel,
+        settings: &DeviceSettings,
+    ) -> Self {
+        let tokenize_wheel = |key| {
+            button_configs
+                .get(key)
+                .map(|button_config| button_config.tokenize(DEFAULT_REPEAT_INTERVAL))
+                .unwrap_or_default()
+        };
+
         Self {
-            scroll_button: button_configs.scroll_button.tokenize(),
-            left_actionlock: button_configs.left_actionlock.tokenize(),
-            right_actionlock: button_configs.right_actionlock.tokenize(),
-            forwards_button: button_configs.forwards_button.tokenize(),
-            back_button: button_configs.back_button.tokenize(),
-            thumb_anticlockwise: button_configs.thumb_anticlockwise.tokenize(),
-            thumb_clockwise: button_configs.thumb_clockwise.tokenize(),
-            hat_top: button_configs.hat_top.tokenize(),
-            hat_left: button_configs.hat_left.tokenize(),
-            hat_right: button_configs.hat_right.tokenize(),
-            hat_bottom: button_configs.hat_bottom.tokenize(),
-            button_1: button_configs.button_1.tokenize(),
-            precision_aim: button_configs.precision_aim.tokenize(),
-            button_2: button_configs.button_2.tokenize(),
-            button_3: button_configs.button_3.tokenize(),
+            buttons: device_model
+                .buttons
+                .iter()
+                .map(|button| {
+                    // the physical button plays the binding of its mirror for the left handed
+                    let key = mirror(button.key, button_configs.left_handed);
+                    let repeat_interval = button_configs
+                        .repeat_interval
+                        .get(key)
+                        .map(|millis| Duration::from_millis(*millis))
+                        .unwrap_or(DEFAULT_REPEAT_INTERVAL);
+
+                    button_configs
+                        .get(key)
+                        .map(|button_config| button_config.tokenize(repeat_interval))
+                        .unwrap_or_else(|| ButtonConfig::default().tokenize(repeat_interval))
+                })
+                .collect(),
+            wheel: WHEEL_KEYS.map(tokenize_wheel),
+            debounce: device_model
+                .buttons
+                .iter()
+                .map(|button| {
+                    button_configs
+                        .debounce
+                        .get(button.key)
+                        .map(|millis| Duration::from_millis(*millis))
+                        .unwrap_or_default()
+                })
+                .collect(),
+            chords: button_configs
+                .chords
+                .iter()
+                .filter_map(|chord| {
+                    ChordToken::from_config(chord, device_model, button_configs.left_handed)
+                })
+                .collect(),
+            double_tap_window: button_configs
+                .double_tap_window
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_DOUBLE_TAP_WINDOW),
+            hold_threshold: button_configs
+                .hold_threshold
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_HOLD_THRESHOLD),
+            movement: MovementSettings {
+                precision_aim_factor: button_configs
+                    .precision_aim_factor
+                    .filter(|factor| *factor > 0.0),
+                response_curve: button_configs
+                    .response_curve
+                    .clone()
+                    .filter(|response_curve| response_curve.check().is_none()),
+                sensitivity: settings
+                    .sensitivity
+                    .filter(|sensitivity| sensitivity.iter().all(|multiplier| *multiplier > 0.0)),
+                invert_x: button_configs.invert_x,
+                invert_y: button_configs.invert_y,
+                swap_axes: button_configs.swap_axes,
+            },
+            shift_toggle: button_configs.shift_toggle,
+            left_handed: button_configs.left_handed,
+            key_mode: button_configs.key_mode,
+            smooth_scroll: button_configs
+                .smooth_scroll
+                .map(|lines| (lines * NOTCH_DELTA as f32 / SYSTEM_LINES_PER_NOTCH).round() as i32)
+                .filter(|notch_delta| *notch_delta > 0),
+        }
+    }
+}
+
+// ignores the transitions following the last one within the debounce window
+#[derive(Default)]
+struct Debounce {
+    last_transition: Option<Instant>,
+}
+
+impl Debounce {
+    fn filter(&mut self, previous_state: bool, current_state: bool, window: Duration) -> bool {
+        if previous_state == current_state {
+            return current_state;
+        }
+
+        if let Some(last_transition) = self.last_transition {
+            if last_transition.elapsed() < window {
+                return previous_state;
+            }
         }
+
+        self.last_transition = Some(Instant::now());
+        current_state
     }
 }
 
@@ -64,371 +390,674 @@ struct ClickState {
     middle: bool,
 }
 
-struct ButtonState {
-    scroll_button: bool,
-    left_actionlock: bool,
-    right_actionlock: bool,
-    forwards_button: bool,
-    back_button: bool,
-    thumb_anticlockwise: bool,
-    thumb_clockwise: bool,
-    hat_top: bool,
-    hat_left: bool,
-    hat_right: bool,
-    hat_bottom: bool,
-    button_1: bool,
-    precision_aim: bool,
-    button_2: bool,
-    button_3: bool,
-}
-
-struct ButtonTimer {
-    scroll_button: Rc<RefCell<Timer>>,
-    left_actionlock: Rc<RefCell<Timer>>,
-    right_actionlock: Rc<RefCell<Timer>>,
-    forwards_button: Rc<RefCell<Timer>>,
-    back_button: Rc<RefCell<Timer>>,
-    thumb_anticlockwise: Rc<RefCell<Timer>>,
-    thumb_clockwise: Rc<RefCell<Timer>>,
-    hat_top: Rc<RefCell<Timer>>,
-    hat_left: Rc<RefCell<Timer>>,
-    hat_right: Rc<RefCell<Timer>>,
-    hat_bottom: Rc<RefCell<Timer>>,
-    button_1: Rc<RefCell<Timer>>,
-    precision_aim: Rc<RefCell<Timer>>,
-    button_2: Rc<RefCell<Timer>>,
-    button_3: Rc<RefCell<Timer>>,
+// output pressed by a binding and not released yet
+#[derive(Clone, Copy, PartialEq)]
+enum HeldOutput {
+    Key(enigo::Key),
+    Mouse(Button),
+    Gamepad(GamepadInput),
+}
+
+impl HeldOutput {
+    // the held output with the token releasing it, or the output released by the token
+    fn from_token(token: &Token) -> Option<(Self, Option<Token>)> {
+        match token {
+            Token::KeyDown(key) => Some((Self::Key(*key), Some(Token::KeyUp(*key)))),
+            Token::KeyUp(key) => Some((Self::Key(*key), None)),
+            Token::MouseDown(button) => Some((Self::Mouse(*button), Some(Token::MouseUp(*button)))),
+            Token::MouseUp(button) => Some((Self::Mouse(*button), None)),
+            Token::Gamepad(input, true) => {
+                Some((Self::Gamepad(*input), Some(Token::Gamepad(*input, false))))
+            }
+            Token::Gamepad(input, false) => Some((Self::Gamepad(*input), None)),
+            _ => None,
+        }
+    }
+}
+
+struct RepeatTimer {
+    timer: Timer,
+    interval: Duration,
+}
+
+impl RepeatTimer {
+    fn new(interval: Duration) -> Self {
+        Self {
+            timer: Timer::new(interval),
+            interval,
+        }
+    }
+
+    fn check(&mut self, interval: Duration) -> bool {
+        if self.interval != interval {
+            *self = Self::new(interval);
+        }
+
+        self.timer.check()
+    }
 }
 
+#[derive(Default)]
+struct DoubleTap {
+    first_press: Option<Instant>,
+    second_press: bool,
+    latched: Option<Arc<StateToken>>,
+}
+
+// what a report of a button having a double tap action plays
+#[derive(Debug, Default, PartialEq)]
+struct TapOutcome {
+    // the window ended without a second tap, the momentary action being played late
+    single_tap: bool,
+    // second tap, flipping the latched double tap action
+    double_tap: bool,
+    // not part of a tap, the report being handled like for any other binding
+    pass: bool,
+}
+
+impl DoubleTap {
+    fn update(
+        &mut self,
+        now: Instant,
+        window: Duration,
+        previous_button_state: bool,
+        current_button_state: bool,
+    ) -> TapOutcome {
+        let mut outcome = TapOutcome::default();
+
+        if let Some(first_press) = self.first_press {
+            if now.duration_since(first_press) >= window {
+                self.first_press = None;
+                outcome.single_tap = true;
+            }
+        }
+
+        if current_button_state && !previous_button_state {
+            if self.first_press.take().is_some() {
+                self.second_press = true;
+                outcome.double_tap = true;
+            } else {
+                self.first_press = Some(now);
+            }
+        } else if self.second_press {
+            // the release of the second tap is swallowed too
+            self.second_press = current_button_state;
+        } else {
+            outcome.pass = self.first_press.is_none();
+        }
+
+        outcome
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 enum Mode {
     Normal(u8),
     Shift(u8),
 }
 
+impl Mode {
+    fn with_shift(self, shift: bool) -> Self {
+        match (self, shift) {
+            (Self::Normal(mode) | Self::Shift(mode), true) => Self::Shift(mode),
+            (Self::Normal(mode) | Self::Shift(mode), false) => Self::Normal(mode),
+        }
+    }
+}
+
 pub struct Mapper {
-    enigo: Enigo,
+    backend: Box<dyn Backend>,
     mode: Mode,
+    // shift button state of the last report, and shift mode latched by it
+    shift_pressed: bool,
+    shift_latched: bool,
     click_state: ClickState,
-    button_state: ButtonState,
-    button_timer: ButtonTimer,
-    button_configs_token: ButtonConfigsToken,
-    mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
-    mouses_config_state_id: Arc<AtomicU32>,
-    last_mouses_config_state_id: u32,
+    device_model: &'static DeviceModel,
+    // per button state, in the button order of the device model
+    button_state: Vec<bool>,
+    // state of the previous report, swapped with the current one to avoid reallocating
+    previous_button_state: Vec<bool>,
+    // buttons part of a chord, ignored until released
+    button_in_chord: Vec<bool>,
+    // action of each active chord, in the chord order of the snapshot
+    chord_action: Vec<Option<ActionToken>>,
+    // latched state of the buttons having a toggle binding
+    toggle_state: Vec<bool>,
+    // action which played the down tokens of each held button
+    button_action: Vec<Option<ActionToken>>,
+    button_timer: Vec<Rc<RefCell<RepeatTimer>>>,
+    button_double_tap: Vec<Rc<RefCell<DoubleTap>>>,
+    button_debounce: Vec<Debounce>,
+    // press of the buttons having a hold action, until it's known to be a tap or a hold
+    button_hold_press: Vec<Option<Instant>>,
+    middle_debounce: Debounce,
+    movement_filter: MovementFilter,
+    report_log: ReportLog,
+    // with the tokens releasing them
+    held_output_vec: Vec<(HeldOutput, Token)>,
+    config_snapshot: ConfigSnapshot,
+    device_snapshot: Arc<DeviceSnapshot>,
+    profile_switch_tx: ProfileSwitchSender,
     serial_number: String,
-    emulation_worker_rx: Sender<Vec<Token>>,
+    variables: Variables,
+    #[cfg(feature = "rhai")]
+    script_engine: ScriptEngine,
+    // with the time they were sent at
+    emulation_worker_rx: Sender<(Instant, Vec<Token>)>,
+    turbo_worker_rx: Sender<TurboCommand>,
+    metrics: Arc<Metrics>,
+    // worker restarts already handled
+    seen_restart_count: u64,
+    report_rate: ReportRate,
+    // bindings released since the emulation was paused
+    paused: bool,
     mouse_relative_movement_condmutex: Arc<CondMutex<(i32, i32)>>,
+    event_tx: EventSender,
 }
 
 impl Mapper {
     pub fn new(
-        mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
-        mouses_config_state_id: Arc<AtomicU32>,
+        device_model: &'static DeviceModel,
+        config_snapshot: ConfigSnapshot,
+        profile_switch_tx: ProfileSwitchSender,
         serial_number: String,
+        event_tx: EventSender,
     ) -> Self {
-        let last_mouses_config_state_id = mouses_config_state_id.load(Ordering::SeqCst);
-        let button_configs = mouses_config_mutex.blocking_lock().config[&serial_number].clone();
+        // the snapshot is published before the device thread starts
+        let device_snapshot = config_snapshot.get(&serial_number).unwrap_or_else(|| {
+            Arc::new(DeviceSnapshot {
+                button_configs_token: ButtonConfigsToken::from_config(
+                    &ButtonConfigs::default(),
+                    device_model,
+                    &DeviceSettings::default(),
+                ),
+                active_profile: None,
+                led_colors: vec![],
+                polling_rate: None,
+                dpi_stages: vec![],
+                lift_off_distance: None,
+                read_timeout: polling::read_timeout(None),
+                tick_interval: polling::tick_interval(None, None),
+                backend: Default::default(),
+            })
+        });
+        let backend_factory = BackendFactory::new(device_snapshot.backend);
+
+        status::set_backend(serial_number.clone(), backend_factory.kind());
+
+        let chord_count = device_snapshot.button_configs_token.chords.len();
+        let movement_backend_factory = backend_factory.clone();
+        let emulation_backend_factory = backend_factory.clone();
+        let button_count = device_model.buttons.len();
         let (emulation_worker_rx, emulation_worker_tx) = channel();
         let mouse_relative_movement_condmutex = Arc::new(CondMutex::new((0, 0)));
         let mouse_relative_movement_condmutex_clone = mouse_relative_movement_condmutex.clone();
+        let emulation_movement_condmutex = mouse_relative_movement_condmutex.clone();
+        let metrics = Arc::new(Metrics::default());
+        let movement_metrics = metrics.clone();
+        let emulation_metrics = metrics.clone();
+        let turbo_worker_rx = turbo::spawn_worker(emulation_worker_rx.clone(), metrics.clone());
 
         // mouse movement worker
         spawn(move || {
             set_current_thread_priority(ThreadPriority::Max).ok();
 
-            let mut enigo = Enigo::new();
+            supervise("movement worker", &movement_metrics, || {
+                let mut backend = movement_backend_factory.create();
 
-            loop {
-                let mouse_relative_movement = {
-                    let mut mouse_relative_movement =
-                        mouse_relative_movement_condmutex_clone.wait_poisoned();
-                    let mouse_relative_movement_clone = mouse_relative_movement.clone();
+                loop {
+                    let mouse_relative_movement = {
+                        let mut mouse_relative_movement =
+                            mouse_relative_movement_condmutex_clone.wait_poisoned();
+                        let mouse_relative_movement_clone = mouse_relative_movement.clone();
 
-                    *mouse_relative_movement = (0, 0);
-                    mouse_relative_movement_clone
-                };
+                        *mouse_relative_movement = (0, 0);
+                        mouse_relative_movement_clone
+                    };
 
-                enigo.mouse_move_relative(mouse_relative_movement.0, mouse_relative_movement.1);
-            }
+                    backend
+                        .mouse_move_relative(mouse_relative_movement.0, mouse_relative_movement.1);
+                    movement_metrics.movement_played();
+                }
+            });
         });
 
         // emulation worker
         spawn(move || {
             set_current_thread_priority(ThreadPriority::Max).ok();
 
-            let mut enigo = Enigo::new();
+            supervise("emulation worker", &emulation_metrics, || {
+                let mut backend = emulation_backend_factory.create();
+                let mut gamepad = Gamepad::default();
 
-            while let Ok(token_vec) = emulation_worker_tx.recv() {
-                emulate_token_vec(&mut enigo, token_vec);
-            }
+                while let Ok((queued_at, token_vec)) = emulation_worker_tx.recv() {
+                    emulation_metrics.token_vec_played(queued_at);
+                    emulate_token_vec(
+                        backend.as_mut(),
+                        &mut gamepad,
+                        &emulation_movement_condmutex,
+                        token_vec,
+                    );
+                }
+            });
         });
 
         Self {
-            enigo: Enigo::new(),
+            backend: backend_factory.create(),
             mode: Mode::Normal(0),
+            shift_pressed: false,
+            shift_latched: false,
             click_state: ClickState {
                 left: false,
                 right: false,
                 middle: false,
             },
-            button_state: ButtonState {
-                back_button: false,
-                forwards_button: false,
-                button_1: false,
-                button_2: false,
-                button_3: false,
-                hat_top: false,
-                hat_bottom: false,
-                hat_left: false,
-                hat_right: false,
-                precision_aim: false,
-                thumb_clockwise: false,
-                thumb_anticlockwise: false,
-                scroll_button: false,
-                left_actionlock: false,
-                right_actionlock: false,
-            },
-            button_timer: ButtonTimer {
-                back_button: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                forwards_button: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                button_1: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                button_2: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                button_3: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                hat_top: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                hat_bottom: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                hat_left: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                hat_right: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                precision_aim: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                thumb_clockwise: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                thumb_anticlockwise: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                scroll_button: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                left_actionlock: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-                right_actionlock: Rc::new(RefCell::new(Timer::new(Duration::from_millis(50)))),
-            },
-            button_configs_token: ButtonConfigsToken::from_config(button_configs),
-            mouses_config_mutex,
-            mouses_config_state_id,
-            last_mouses_config_state_id,
+            device_model,
+            button_state: vec![false; button_count],
+            previous_button_state: vec![false; button_count],
+            button_in_chord: vec![false; button_count],
+            chord_action: vec![None; chord_count],
+            toggle_state: vec![false; button_count],
+            button_action: vec![None; button_count],
+            button_timer: (0..button_count)
+                .map(|_| Rc::new(RefCell::new(RepeatTimer::new(DEFAULT_REPEAT_INTERVAL))))
+                .collect(),
+            button_double_tap: (0..button_count)
+                .map(|_| Rc::new(RefCell::new(DoubleTap::default())))
+                .collect(),
+            button_debounce: (0..button_count).map(|_| Debounce::default()).collect(),
+            button_hold_press: vec![None; button_count],
+            middle_debounce: Debounce::default(),
+            movement_filter: MovementFilter::default(),
+            report_log: ReportLog::new(device_model),
+            held_output_vec: vec![],
+            config_snapshot,
+            device_snapshot,
+            profile_switch_tx,
             serial_number,
+            variables: Variables::default(),
+            #[cfg(feature = "rhai")]
+            script_engine: ScriptEngine::new(),
             emulation_worker_rx,
+            turbo_worker_rx,
+            metrics,
+            seen_restart_count: 0,
+            report_rate: ReportRate::default(),
+            paused: false,
             mouse_relative_movement_condmutex,
+            event_tx,
         }
     }
 
     pub fn emulate(&mut self, buffer: &[u8]) {
-        if self.config_has_change() {
-            self.button_configs_token = ButtonConfigsToken::from_config(
-                self.mouses_config_mutex.blocking_lock().config[&self.serial_number].clone(),
-            );
-        }
+        self.report_rate.count();
+        self.release_after_restart();
+        self.report_log.push(&self.serial_number, buffer);
+        self.reload_config();
 
         self.update_mode(buffer);
+        self.update_grab();
         self.basic_emulation(buffer);
         self.mapped_emulation(buffer);
     }
 
+    // last reports, only kept with the `--debug-reports` flag
+    pub fn report_lines(&self) -> Vec<String> {
+        self.report_log.lines()
+    }
+
+    pub fn driver_status(&self) -> DriverStatus {
+        metrics::driver_status(self.serial_number.clone(), &self.metrics, &self.report_rate)
+    }
+
+    // nothing held, latched or repeating, so the read timeouts have nothing to play
+    pub fn is_idle(&self) -> bool {
+        !self.button_state.contains(&true)
+            && !self.toggle_state.contains(&true)
+            && self.button_action.iter().all(Option::is_none)
+            && self.chord_action.iter().all(Option::is_none)
+            && self.button_hold_press.iter().all(Option::is_none)
+            && self.button_double_tap.iter().all(|double_tap| {
+                let double_tap = double_tap.borrow();
+
+                double_tap.first_press.is_none() && double_tap.latched.is_none()
+            })
+            && self.held_output_vec.is_empty()
+    }
+
     pub fn emulate_only_mapped(&mut self, buffer: &[u8]) {
-        if self.config_has_change() {
-            self.button_configs_token = ButtonConfigsToken::from_config(
-                self.mouses_config_mutex.blocking_lock().config[&self.serial_number].clone(),
-            );
-        }
+        self.release_after_restart();
+        self.reload_config();
 
         self.mapped_emulation(buffer);
     }
 
-    fn update_mode(&mut self, buffer: &[u8]) {
-        let modes = buffer[2] & 0b111;
+    // only loads the last published snapshot, never waits on the config lock
+    fn reload_config(&mut self) {
+        let device_snapshot = match self.config_snapshot.get(&self.serial_number) {
+            Some(device_snapshot) if !Arc::ptr_eq(&device_snapshot, &self.device_snapshot) => {
+                device_snapshot
+            }
+            _ => return,
+        };
+
+        self.release_latched();
+
+        let profile_has_changed =
+            self.device_snapshot.active_profile != device_snapshot.active_profile;
+
+        self.device_snapshot = device_snapshot;
+        self.chord_action = vec![None; self.device_snapshot.button_configs_token.chords.len()];
 
-        self.mode = match modes {
+        if profile_has_changed {
+            self.send_event(DriverEvent::Profile {
+                serial_number: self.serial_number.clone(),
+                profile: self.device_snapshot.active_profile.clone(),
+            });
+        }
+    }
+
+    fn send_event(&self, event: DriverEvent) {
+        self.event_tx.send(event).ok();
+    }
+
+    fn send_button_event(&self, button: &str, pressed: bool) {
+        self.send_event(DriverEvent::Button {
+            serial_number: self.serial_number.clone(),
+            button: button.to_string(),
+            pressed,
+        });
+    }
+
+    fn update_mode(&mut self, buffer: &[u8]) {
+        let modes = buffer[self.device_model.report.mode] & 0b111;
+        let mut mode = match modes {
             0 | 1 | 2 => Mode::Normal(modes),
             4 | 5 | 6 => Mode::Shift(modes - 0b100),
             _ => Mode::Normal(0),
         };
-    }
+        let shift_pressed = matches!(mode, Mode::Shift(_));
 
-    fn basic_emulation(&mut self, buffer: &[u8]) {
-        // button emulation
-        let click_state = ClickState {
-            left: (buffer[0] & 1) > 0,
-            right: (buffer[0] & 2) > 0,
-            middle: (buffer[0] & 4) > 0,
-        };
-        let middle_button_state_token =
-            self.get_state_token(&self.button_configs_token.scroll_button);
+        if self.device_snapshot.button_configs_token.shift_toggle {
+            if shift_pressed && !self.shift_pressed {
+                self.shift_latched = !self.shift_latched;
+            }
 
-        if click_state.left != self.click_state.left {
-            self.click_state.left = click_state.left;
+            mode = mode.with_shift(self.shift_latched);
+        } else {
+            self.shift_latched = false;
+        }
 
-            if click_state.left {
-                self.enigo.mouse_down(MouseButton::Left);
-            } else {
-                self.enigo.mouse_up(MouseButton::Left);
-            }
+        self.shift_pressed = shift_pressed;
+
+        if self.mode != mode {
+            self.mode = mode;
+            self.send_event(DriverEvent::Mode {
+                serial_number: self.serial_number.clone(),
+                mode: self.absolute_mode(),
+                shift: self.is_shift_mode(),
+            });
         }
-        if middle_button_state_token.down.is_empty()
-            && middle_button_state_token.repeat.is_empty()
-            && middle_button_state_token.up.is_empty()
-        {
-            if click_state.middle != self.click_state.middle {
-                self.click_state.middle = click_state.middle;
+    }
+
+    // the stock buttons with a binding in the current mode, their original events being
+    // suppressed with the exclusive grab
+    fn update_grab(&self) {
+        let mut button_mask = 0;
 
-                if click_state.middle {
-                    self.enigo.mouse_down(MouseButton::Middle);
-                } else {
-                    self.enigo.mouse_up(MouseButton::Middle);
+        if !is_paused() {
+            for (key, button) in [
+                ("left_click", grab::LEFT),
+                ("right_click", grab::RIGHT),
+                ("middle_click", grab::MIDDLE),
+                ("scroll_button", grab::MIDDLE),
+                ("back_button", grab::BACK),
+                ("forwards_button", grab::FORWARD),
+            ] {
+                if self.is_button_mapped(key) {
+                    button_mask |= button;
                 }
             }
-        }
-        if click_state.right != self.click_state.right {
-            self.click_state.right = click_state.right;
-
-            if click_state.right {
-                self.enigo.mouse_down(MouseButton::Right);
-            } else {
-                self.enigo.mouse_up(MouseButton::Right);
+            for (index, button) in [grab::WHEEL_UP, grab::WHEEL_DOWN].into_iter().enumerate() {
+                if !self
+                    .get_binding_token(&self.device_snapshot.button_configs_token.wheel[index])
+                    .is_empty()
+                {
+                    button_mask |= button;
+                }
             }
         }
 
+        grab::suppress(&self.serial_number, button_mask);
+    }
+
+    // the bindings aren't played while paused, so the clicks are left to the basic emulation
+    fn is_button_mapped(&self, key: &str) -> bool {
+        !is_paused()
+            && self
+                .device_model
+                .button_index(key)
+                .and_then(|index| self.device_snapshot.button_configs_token.buttons.get(index))
+                .map(|button_config_token| !self.get_binding_token(button_config_token).is_empty())
+                .unwrap_or(false)
+    }
+
+    fn basic_emulation(&mut self, buffer: &[u8]) {
+        // button emulation
+        let report = &self.device_model.report;
+        // the scroll button debounce applies to the middle click too
+        let middle_debounce = self
+            .device_model
+            .button_index("scroll_button")
+            .or_else(|| self.device_model.button_index("middle_click"))
+            .and_then(|index| {
+                self.device_snapshot
+                    .button_configs_token
+                    .debounce
+                    .get(index)
+            })
+            .copied()
+            .unwrap_or_default();
+        let click_state = ClickState {
+            left: report.left.is_set(buffer),
+            right: report.right.is_set(buffer),
+            middle: self.middle_debounce.filter(
+                self.click_state.middle,
+                report.middle.is_set(buffer),
+                middle_debounce,
+            ),
+        };
+        // the clicks are left to their binding when there is one,
+        // the scroll button binding taking the middle click too
+        let left_is_mapped = self.is_button_mapped("left_click");
+        let right_is_mapped = self.is_button_mapped("right_click");
+        let middle_is_mapped =
+            self.is_button_mapped("scroll_button") || self.is_button_mapped("middle_click");
+
+        // the primary click is under the index finger, on the right button for the left handed
+        let (left, right) = if self.device_snapshot.button_configs_token.left_handed {
+            (Button::Right, Button::Left)
+        } else {
+            (Button::Left, Button::Right)
+        };
+
+        self.emulate_click(left, click_state.left, left_is_mapped);
+        self.emulate_click(Button::Middle, click_state.middle, middle_is_mapped);
+        self.emulate_click(right, click_state.right, right_is_mapped);
+
         // movement emulation
         {
+            let (x, y) = self.scale_movement(
+                buffer,
+                buffer[report.x] as i8 as i32,
+                buffer[report.y] as i8 as i32,
+            );
             let mut mouse_relative_movement =
                 self.mouse_relative_movement_condmutex.lock_poisoned();
 
-            mouse_relative_movement.0 += if buffer[3] < 128 {
-                buffer[3] as i32
-            } else {
-                buffer[3] as i32 - 256
-            };
-            mouse_relative_movement.1 += if buffer[5] < 128 {
-                buffer[5] as i32
-            } else {
-                buffer[5] as i32 - 256
-            };
+            mouse_relative_movement.0 += x;
+            mouse_relative_movement.1 += y;
 
             self.mouse_relative_movement_condmutex.notify_one();
+            self.metrics.movement_queued();
         }
 
         // wheel emulation
-        if buffer[7] == 1 {
-            self.enigo.mouse_scroll_y(-1);
+        if buffer[report.wheel] == 1 && !self.emulate_wheel(0) {
+            self.scroll_y(-1);
         }
-        if buffer[7] == 255 {
-            self.enigo.mouse_scroll_y(1);
+        if buffer[report.wheel] == 255 && !self.emulate_wheel(1) {
+            self.scroll_y(1);
         }
     }
 
-    fn mapped_emulation(&mut self, buffer: &[u8]) {
-        let button_state = ButtonState {
-            back_button: (buffer[0] & 8) > 0,
-            forwards_button: (buffer[0] & 16) > 0,
-            button_1: (buffer[0] & 32) > 0,
-            button_2: (buffer[0] & 64) > 0,
-            button_3: (buffer[0] & 128) > 0,
-            hat_top: (buffer[1] & 1) > 0,
-            hat_bottom: (buffer[1] & 2) > 0,
-            hat_left: (buffer[1] & 4) > 0,
-            hat_right: (buffer[1] & 8) > 0,
-            precision_aim: (buffer[1] & 16) > 0,
-            thumb_clockwise: (buffer[1] & 32) > 0,
-            thumb_anticlockwise: (buffer[1] & 64) > 0,
-            scroll_button: (buffer[2] & 8) > 0,
-            left_actionlock: (buffer[2] & 16) > 0,
-            right_actionlock: (buffer[2] & 32) > 0,
+    // by notches, or by the delta of a notch when the profile scrolls smoothly
+    fn scroll_y(&mut self, length: i32) {
+        match self.device_snapshot.button_configs_token.smooth_scroll {
+            Some(notch_delta) => self.backend.mouse_smooth_scroll_y(length * notch_delta),
+            None => self.backend.mouse_scroll_y(length),
+        }
+    }
+
+    // a click held when its binding is set is released, so it isn't left pressed
+    fn emulate_click(&mut self, button: Button, pressed: bool, is_mapped: bool) {
+        let (name, click_state) = match button {
+            Button::Left => ("left", &mut self.click_state.left),
+            Button::Right => ("right", &mut self.click_state.right),
+            _ => ("middle", &mut self.click_state.middle),
         };
+        let pressed = pressed && !is_mapped;
 
-        self.emulate_button_config_token(
-            self.button_configs_token.back_button.clone(),
-            self.button_timer.back_button.clone(),
-            self.button_state.back_button,
-            button_state.back_button,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.forwards_button.clone(),
-            self.button_timer.forwards_button.clone(),
-            self.button_state.forwards_button,
-            button_state.forwards_button,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.button_1.clone(),
-            self.button_timer.button_1.clone(),
-            self.button_state.button_1,
-            button_state.button_1,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.button_2.clone(),
-            self.button_timer.button_2.clone(),
-            self.button_state.button_2,
-            button_state.button_2,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.button_3.clone(),
-            self.button_timer.button_3.clone(),
-            self.button_state.button_3,
-            button_state.button_3,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.hat_top.clone(),
-            self.button_timer.hat_top.clone(),
-            self.button_state.hat_top,
-            button_state.hat_top,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.hat_bottom.clone(),
-            self.button_timer.hat_bottom.clone(),
-            self.button_state.hat_bottom,
-            button_state.hat_bottom,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.hat_left.clone(),
-            self.button_timer.hat_left.clone(),
-            self.button_state.hat_left,
-            button_state.hat_left,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.hat_right.clone(),
-            self.button_timer.hat_right.clone(),
-            self.button_state.hat_right,
-            button_state.hat_right,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.precision_aim.clone(),
-            self.button_timer.precision_aim.clone(),
-            self.button_state.precision_aim,
-            button_state.precision_aim,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.thumb_clockwise.clone(),
-            self.button_timer.thumb_clockwise.clone(),
-            self.button_state.thumb_clockwise,
-            button_state.thumb_clockwise,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.thumb_anticlockwise.clone(),
-            self.button_timer.thumb_anticlockwise.clone(),
-            self.button_state.thumb_anticlockwise,
-            button_state.thumb_anticlockwise,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.scroll_button.clone(),
-            self.button_timer.scroll_button.clone(),
-            self.button_state.scroll_button,
-            button_state.scroll_button,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.left_actionlock.clone(),
-            self.button_timer.left_actionlock.clone(),
-            self.button_state.left_actionlock,
-            button_state.left_actionlock,
-        );
-        self.emulate_button_config_token(
-            self.button_configs_token.right_actionlock.clone(),
-            self.button_timer.right_actionlock.clone(),
-            self.button_state.right_actionlock,
-            button_state.right_actionlock,
-        );
+        if *click_state == pressed {
+            return;
+        }
+
+        *click_state = pressed;
+        self.send_button_event(name, pressed);
+
+        if pressed {
+            self.backend.mouse_down(button);
+        } else {
+            self.backend.mouse_up(button);
+        }
+    }
+
+    // applies the precision aim, the held sensitivity bindings and the response curve of the profile
+    fn scale_movement(&mut self, buffer: &[u8], x: i32, y: i32) -> (i32, i32) {
+        let precision_aim = self
+            .device_model
+            .button_index("precision_aim")
+            .map(|index| self.device_model.buttons[index].bit.is_set(buffer))
+            .unwrap_or(false);
+
+        self.movement_filter.apply(
+            &self.device_snapshot.button_configs_token.movement,
+            precision_aim,
+            x,
+            y,
+        )
+    }
+
+    // plays the wheel binding of the current mode as a tap, false when there is none
+    fn emulate_wheel(&mut self, wheel_index: usize) -> bool {
+        let device_snapshot = self.device_snapshot.clone();
+        let binding_token =
+            self.get_binding_token(&device_snapshot.button_configs_token.wheel[wheel_index]);
+
+        if binding_token.is_empty() {
+            return false;
+        }
+
+        let action_token = self.press(&binding_token.action);
+
+        self.release(&action_token);
+
+        true
+    }
+
+    fn mapped_emulation(&mut self, buffer: &[u8]) {
+        if is_paused() {
+            if !self.paused {
+                self.paused = true;
+                self.release_bindings();
+            }
+
+            return;
+        }
+
+        self.paused = false;
+
+        // only the snapshot is shared, the bindings are borrowed from it
+        let device_snapshot = self.device_snapshot.clone();
+
+        std::mem::swap(&mut self.button_state, &mut self.previous_button_state);
+
+        for (index, button) in self.device_model.buttons.iter().enumerate() {
+            self.button_state[index] = self.button_debounce[index].filter(
+                self.previous_button_state[index],
+                button.bit.is_set(buffer),
+                device_snapshot.button_configs_token.debounce[index],
+            );
+        }
+
+        // chords are matched first, as they override the bindings of their buttons
+        self.emulate_chords(&device_snapshot.button_configs_token.chords);
+
+        for (index, button_config_token) in device_snapshot
+            .button_configs_token
+            .buttons
+            .iter()
+            .enumerate()
+        {
+            let button = &self.device_model.buttons[index];
+            let previous_button_state = self.previous_button_state[index];
+            let current_button_state = self.button_state[index];
 
-        self.button_state = button_state;
+            if current_button_state != previous_button_state {
+                self.send_button_event(button.key, current_button_state);
+            }
+
+            if self.button_in_chord[index] {
+                self.button_in_chord[index] = current_button_state;
+                continue;
+            }
+
+            self.emulate_button_config_token(
+                index,
+                button_config_token,
+                previous_button_state,
+                current_button_state,
+            );
+        }
+    }
+
+    fn emulate_chords(&mut self, chord_token_vec: &[ChordToken]) {
+        for (chord_index, chord_token) in chord_token_vec.iter().enumerate() {
+            let is_held = chord_token
+                .buttons
+                .iter()
+                .all(|index| self.button_state[*index]);
+            let is_free = chord_token
+                .buttons
+                .iter()
+                .all(|index| !self.button_in_chord[*index]);
+
+            if is_held && is_free && self.chord_action[chord_index].is_none() {
+                // the buttons already pressed are released before playing the chord
+                for index in &chord_token.buttons {
+                    self.release_button(*index);
+                    self.toggle_state[*index] = false;
+                    self.button_hold_press[*index] = None;
+                    self.button_in_chord[*index] = true;
+                }
+
+                self.chord_action[chord_index] = Some(self.press(&chord_token.action));
+            } else if !is_held {
+                if let Some(action_token) = self.chord_action[chord_index].take() {
+                    self.release(&action_token);
+                }
+            }
+        }
     }
 
     fn is_shift_mode(&self) -> bool {
@@ -445,68 +1074,451 @@ impl Mapper {
         }
     }
 
-    fn config_has_change(&mut self) -> bool {
-        let mouses_config_state_id = self.mouses_config_state_id.load(Ordering::SeqCst);
+    fn get_binding_token<'a>(
+        &self,
+        button_config_token: &'a ButtonConfigToken,
+    ) -> &'a BindingToken {
+        &button_config_token[self.is_shift_mode() as usize][self.absolute_mode() as usize]
+    }
 
-        if self.last_mouses_config_state_id != mouses_config_state_id {
-            self.last_mouses_config_state_id = mouses_config_state_id;
+    // the outputs held by a restarted worker were lost with it
+    fn release_after_restart(&mut self) {
+        let restart_count = self.metrics.restart_count();
 
-            true
-        } else {
-            false
+        if restart_count != self.seen_restart_count {
+            self.seen_restart_count = restart_count;
+            self.release_all();
         }
     }
 
-    fn get_state_token(&self, button_config_token: &ButtonConfigToken) -> StateToken {
-        button_config_token[self.is_shift_mode() as usize][self.absolute_mode() as usize].clone()
+    // nothing would release the outputs still held once the device is gone
+    fn release_all(&mut self) {
+        self.release_bindings();
+
+        if self.click_state.left {
+            self.backend.mouse_up(Button::Left);
+        }
+        if self.click_state.middle {
+            self.backend.mouse_up(Button::Middle);
+        }
+        if self.click_state.right {
+            self.backend.mouse_up(Button::Right);
+        }
+    }
+
+    fn release_bindings(&mut self) {
+        self.release_latched();
+        self.turbo_worker_rx.send(TurboCommand::StopAll).ok();
+
+        let up_vec = self.held_output_vec.drain(..).map(|(_, up)| up).collect();
+
+        self.queue_token_vec(up_vec);
+    }
+
+    fn release_latched(&mut self) {
+        let device_snapshot = self.device_snapshot.clone();
+
+        for (index, double_tap) in self.button_double_tap.clone().iter().enumerate() {
+            let double_tap = double_tap.take();
+
+            // a single tap still waiting for its window is played, rather than lost
+            if double_tap.first_press.is_some() {
+                if let Some(button_config_token) =
+                    device_snapshot.button_configs_token.buttons.get(index)
+                {
+                    let action_token = self.get_binding_token(button_config_token).action.clone();
+
+                    let action_token = self.press(&action_token);
+
+                    self.release(&action_token);
+                }
+            }
+
+            if let Some(latched) = double_tap.latched {
+                self.send_token_vec(latched.up.clone());
+            }
+        }
+
+        for index in 0..self.toggle_state.len() {
+            if self.toggle_state[index] {
+                self.release_button(index);
+            }
+        }
+
+        for chord_index in 0..self.chord_action.len() {
+            if let Some(action_token) = self.chord_action[chord_index].take() {
+                self.release(&action_token);
+            }
+        }
+
+        // a pending hold has not pressed anything yet, it is dropped
+        self.toggle_state = vec![false; self.device_model.buttons.len()];
+        self.button_hold_press = vec![None; self.device_model.buttons.len()];
+    }
+
+    // plays the down then the up tokens, as a press and release of a button
+    pub fn play_macro(&mut self, token_string: String) {
+        let state_token = tokenize(token_string);
+
+        self.send_token_vec(state_token.down);
+        self.send_token_vec(state_token.up);
+    }
+
+    // profile, axis lock and variable tokens are handled by the mapper, the rest by the emulation worker
+    fn send_token_vec(&mut self, token_vec: Vec<Token>) {
+        let token_vec = self.handle_token_vec(token_vec);
+
+        for token in &token_vec {
+            if let Some((held_output, up)) = HeldOutput::from_token(token) {
+                self.held_output_vec
+                    .retain(|(other_held_output, _)| *other_held_output != held_output);
+
+                if let Some(up) = up {
+                    self.held_output_vec.push((held_output, up));
+                }
+            }
+        }
+
+        self.queue_token_vec(token_vec);
+    }
+
+    fn queue_token_vec(&self, token_vec: Vec<Token>) {
+        self.metrics.token_vec_queued();
+        self.emulation_worker_rx
+            .send((Instant::now(), token_vec))
+            .ok();
+    }
+
+    fn handle_token_vec(&mut self, token_vec: Vec<Token>) -> Vec<Token> {
+        token_vec
+            .into_iter()
+            .filter_map(|token| self.handle_token(token))
+            .collect()
+    }
+
+    // in the binding order, so the typed text sees the variables set before it
+    fn handle_token(&mut self, token: Token) -> Option<Token> {
+        let context = ScriptContext {
+            serial_number: &self.serial_number,
+            mode: self.absolute_mode(),
+            shift: self.is_shift_mode(),
+        };
+
+        // the scroll tokens of the profiles scrolling smoothly are played by the notch delta
+        if let Some(notch_delta) = self.device_snapshot.button_configs_token.smooth_scroll {
+            let smooth_scroll_token = match token {
+                Token::ScrollX(length) => Some(Token::SmoothScrollX(length * notch_delta)),
+                Token::ScrollY(length) => Some(Token::SmoothScrollY(length * notch_delta)),
+                Token::Click(Button::ScrollUp) => Some(Token::SmoothScrollY(notch_delta)),
+                Token::Click(Button::ScrollDown) => Some(Token::SmoothScrollY(-notch_delta)),
+                Token::Click(Button::ScrollLeft) => Some(Token::SmoothScrollX(notch_delta)),
+                Token::Click(Button::ScrollRight) => Some(Token::SmoothScrollX(-notch_delta)),
+                _ => None,
+            };
+
+            if smooth_scroll_token.is_some() {
+                return smooth_scroll_token;
+            }
+        }
+
+        match token {
+            Token::Profile(profile_switch) => self.switch_profile(profile_switch),
+            Token::AxisLock(step) => self.movement_filter.lock_axis(step),
+            Token::Sensitivity(multiplier, held) => {
+                self.movement_filter.hold_sensitivity(multiplier, held)
+            }
+            Token::SetVariable(name, value) => {
+                let value = interpret(&value, &mut self.variables, &context);
+
+                self.variables.set(name, value);
+            }
+            Token::AddVariable(name, value) => self.variables.add(name, value),
+            Token::Sequence(sequence)
+                if self.device_snapshot.button_configs_token.key_mode == KeyMode::Scancode =>
+            {
+                return Some(Token::Scancode(sequence))
+            }
+            Token::Type(text) => {
+                return Some(Token::Unicode(interpret(
+                    &text,
+                    &mut self.variables,
+                    &context,
+                )))
+            }
+            token => return Some(token),
+        }
+
+        None
+    }
+
+    fn switch_profile(&mut self, profile_switch: ProfileSwitch) {
+        self.profile_switch_tx
+            .send((self.serial_number.clone(), profile_switch))
+            .ok();
+    }
+
+    // returns the action to release, scripts being re-evaluated on each press
+    // into a static action, so repeat and up reuse the result
+    fn press(&mut self, action_token: &ActionToken) -> ActionToken {
+        let action_token = match action_token {
+            ActionToken::Script(script) => {
+                let context = ScriptContext {
+                    serial_number: &self.serial_number,
+                    mode: self.absolute_mode(),
+                    shift: self.is_shift_mode(),
+                };
+
+                ActionToken::Static(script.evaluate(&mut self.variables, &context))
+            }
+            #[cfg(feature = "rhai")]
+            ActionToken::Rhai(ast) => {
+                let token_vec = self.run_rhai(ast, true);
+
+                self.send_token_vec(token_vec);
+
+                return action_token.clone();
+            }
+            #[cfg(feature = "wasm")]
+            ActionToken::Wasm(module) => {
+                let token = self.wasm_call(module, "on_press");
+
+                self.send_token_vec(vec![token]);
+
+                return action_token.clone();
+            }
+            _ => action_token.clone(),
+        };
+
+        if let Some(state_token) = action_token.state_token() {
+            // the tokens of a turbo binding are handled once, then replayed until it's released
+            if let Some(turbo) = state_token.turbo {
+                let down = self.handle_token_vec(state_token.down.clone());
+                let up = self.handle_token_vec(state_token.up.clone());
+
+                self.turbo_worker_rx
+                    .send(TurboCommand::Start(state_token, turbo, down, up))
+                    .ok();
+            } else {
+                self.send_token_vec(state_token.down.clone());
+            }
+        }
+
+        action_token
+    }
+
+    // the action is remembered, so its own up tokens are sent even when the mode
+    // or the profile changed while the button was held
+    fn press_button(&mut self, index: usize, action_token: &ActionToken) {
+        self.button_action[index] = Some(self.press(action_token));
+    }
+
+    fn release_button(&mut self, index: usize) {
+        if let Some(action_token) = self.button_action[index].take() {
+            self.release(&action_token);
+        }
+    }
+
+    fn release(&mut self, action_token: &ActionToken) {
+        #[cfg(feature = "rhai")]
+        if let ActionToken::Rhai(ast) = action_token {
+            let token_vec = self.run_rhai(ast, false);
+
+            self.send_token_vec(token_vec);
+
+            return;
+        }
+
+        #[cfg(feature = "wasm")]
+        if let ActionToken::Wasm(module) = action_token {
+            let token = self.wasm_call(module, "on_release");
+
+            self.send_token_vec(vec![token]);
+
+            return;
+        }
+
+        if let Some(state_token) = action_token.state_token() {
+            if state_token.turbo.is_some() {
+                self.turbo_worker_rx
+                    .send(TurboCommand::Stop(state_token))
+                    .ok();
+            } else {
+                self.send_token_vec(state_token.up.clone());
+            }
+        }
+    }
+
+    #[cfg(feature = "rhai")]
+    fn run_rhai(&mut self, ast: &rhai::AST, pressed: bool) -> Vec<Token> {
+        let context = ScriptContext {
+            serial_number: &self.serial_number,
+            mode: self.absolute_mode(),
+            shift: self.is_shift_mode(),
+        };
+
+        self.script_engine.run(ast, &context, pressed)
+    }
+
+    // run by the emulation worker, which plays the tokens of the module right away
+    #[cfg(feature = "wasm")]
+    fn wasm_call(&self, module: &Arc<wasmtime::Module>, function: &'static str) -> Token {
+        Token::Wasm(WasmCall {
+            module: module.clone(),
+            function,
+            mode: self.absolute_mode(),
+            shift: self.is_shift_mode(),
+        })
     }
 
     fn emulate_button_config_token(
         &mut self,
-        button_config_token: ButtonConfigToken,
-        button_timer: Rc<RefCell<Timer>>,
+        index: usize,
+        button_config_token: &ButtonConfigToken,
         previous_button_state: bool,
         current_button_state: bool,
     ) {
-        let state_token = self.get_state_token(&button_config_token);
+        let binding_token = self.get_binding_token(button_config_token);
+        let button_timer = self.button_timer[index].clone();
+        let button_double_tap = self.button_double_tap[index].clone();
+        let (previous_button_state, current_button_state) = if binding_token.action.is_toggle() {
+            // each press flips the latched state, releases are ignored
+            let previous_toggle_state = self.toggle_state[index];
+
+            if current_button_state && !previous_button_state {
+                self.toggle_state[index] = !previous_toggle_state;
+            }
+
+            (previous_toggle_state, self.toggle_state[index])
+        } else {
+            (previous_button_state, current_button_state)
+        };
+
+        // only bindings with a hold action wait to know if the press is a tap or a hold,
+        // the hold action becomes the pressed action, released with the button
+        if let Some(hold_token) = &binding_token.hold {
+            if current_button_state && !previous_button_state {
+                self.button_hold_press[index] = Some(Instant::now());
+            }
+
+            if let Some(hold_press) = self.button_hold_press[index] {
+                if !current_button_state {
+                    self.button_hold_press[index] = None;
+                    self.press_button(index, &binding_token.action);
+                    self.release_button(index);
+                } else if hold_press.elapsed()
+                    >= self.device_snapshot.button_configs_token.hold_threshold
+                {
+                    self.button_hold_press[index] = None;
+                    self.press_button(index, &ActionToken::Static(hold_token.clone()));
+                }
+
+                return;
+            }
+        }
+
+        // only bindings with a double tap action wait for a second tap
+        if let Some(double_tap_token) = &binding_token.double_tap {
+            let mut double_tap = button_double_tap.borrow_mut();
+            let outcome = double_tap.update(
+                Instant::now(),
+                self.device_snapshot.button_configs_token.double_tap_window,
+                previous_button_state,
+                current_button_state,
+            );
+
+            if outcome.single_tap {
+                self.press_button(index, &binding_token.action);
+
+                if !previous_button_state {
+                    self.release_button(index);
+                }
+            }
+
+            if outcome.double_tap {
+                double_tap.latched = match double_tap.latched.take() {
+                    Some(latched) => {
+                        self.send_token_vec(latched.up.clone());
+
+                        None
+                    }
+                    None => {
+                        self.send_token_vec(double_tap_token.down.clone());
+
+                        Some(double_tap_token.clone())
+                    }
+                };
+            }
+
+            if !outcome.pass {
+                return;
+            }
+        }
 
         if current_button_state != previous_button_state {
             if current_button_state {
-                self.emulation_worker_rx.send(state_token.down).ok();
+                self.press_button(index, &binding_token.action);
             } else {
-                self.emulation_worker_rx.send(state_token.up).ok();
+                self.release_button(index);
             }
         }
 
-        if button_timer.borrow_mut().check() && current_button_state {
-            self.emulation_worker_rx.send(state_token.repeat).ok();
+        let state_token = self.button_action[index]
+            .as_ref()
+            .unwrap_or(&binding_token.action)
+            .state_token();
+
+        if let Some(state_token) = state_token {
+            let repeat_interval = state_token
+                .repeat_interval
+                .unwrap_or(binding_token.repeat_interval);
+
+            if button_timer.borrow_mut().check(repeat_interval) && current_button_state {
+                self.send_token_vec(state_token.repeat.clone());
+            }
         }
     }
 }
 
+// the mapper is dropped when the device disconnects
+impl Drop for Mapper {
+    fn drop(&mut self) {
+        grab::suppress(&self.serial_number, 0);
+        self.release_all();
+    }
+}
+
 trait ButtonConfigExt {
-    fn tokenize(&self) -> ButtonConfigToken;
+    fn tokenize(&self, repeat_interval: Duration) -> ButtonConfigToken;
 }
 
 impl ButtonConfigExt for ButtonConfig {
-    fn tokenize(&self) -> ButtonConfigToken {
+    fn tokenize(&self, repeat_interval: Duration) -> ButtonConfigToken {
         let mut button_config_token = [
             [
-                StateToken::default(),
-                StateToken::default(),
-                StateToken::default(),
+                BindingToken::default(),
+                BindingToken::default(),
+                BindingToken::default(),
             ],
             [
-                StateToken::default(),
-                StateToken::default(),
-                StateToken::default(),
+                BindingToken::default(),
+                BindingToken::default(),
+                BindingToken::default(),
             ],
         ];
 
+        // a conditional binding in the first mode applies to the modes missing from the config
+        let conditional_config = self[0]
+            .first()
+            .filter(|config| config.contains(tokenizer::IF_PREFIX));
+
         for mode_type_index in 0..2 {
             for mode_index in 0..3 {
-                if let Some(config) = self[mode_type_index].get(mode_index) {
-                    button_config_token[mode_type_index][mode_index] = tokenize(config.clone());
+                if let Some(config) = self[mode_type_index].get(mode_index).or(conditional_config) {
+                    let config =
+                        tokenizer::resolve_conditions(config, mode_type_index == 1, mode_index);
+
+                    button_config_token[mode_type_index][mode_index] =
+                        BindingToken::from_config(&config, repeat_interval);
                 }
             }
         }
@@ -515,47 +1527,244 @@ impl ButtonConfigExt for ButtonConfig {
     }
 }
 
-fn emulate_token_vec(enigo: &mut Enigo, token_vec: Vec<Token>) {
-    fn key_to_enigo(key: Key) -> enigo::Key {
-        match key {
-            Key::Shift => enigo::Key::Shift,
-            Key::Control => enigo::Key::Control,
-            Key::Alt => enigo::Key::Alt,
-            Key::Command => enigo::Key::Meta,
-        }
+// runs the worker again when it panics, until it returns
+fn supervise(name: &str, metrics: &Metrics, mut worker: impl FnMut()) {
+    while catch_unwind(AssertUnwindSafe(&mut worker)).is_err() {
+        status::error(format!("{} panicked, restarting it", name));
+        metrics.worker_restarted();
+    }
+}
+
+// how long a relative move token waits for the movement worker before the next tokens
+const MOVEMENT_WAIT_TIMEOUT: Duration = Duration::from_millis(20);
+
+// spread evenly on both sides of the duration, the keys of each random state being random
+fn jittered(duration: Duration, jitter: Duration) -> Duration {
+    let jitter_millis = jitter.as_millis() as u64;
+
+    if jitter_millis == 0 {
+        return duration;
+    }
+
+    // without random bytes the delay is kept as is
+    let mut bytes = [0; 8];
+
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return duration;
     }
 
+    let offset = u64::from_le_bytes(bytes) % (2 * jitter_millis + 1);
+
+    (duration + Duration::from_millis(offset)).saturating_sub(jitter)
+}
+
+fn emulate_token_vec(
+    backend: &mut dyn Backend,
+    gamepad: &mut Gamepad,
+    mouse_relative_movement_condmutex: &CondMutex<(i32, i32)>,
+    token_vec: Vec<Token>,
+) {
+    // a delay splits the token vector in batches played one after another
+    backend.start_batch();
+
     for token in token_vec {
         match token {
             Token::Sequence(sequence) => {
                 for key in sequence.chars() {
-                    enigo.key_click(enigo::Key::Layout(key));
+                    backend.key_click(enigo::Key::Layout(key));
                 }
             }
-            Token::Unicode(unicode_sequence) => enigo.key_sequence(unicode_sequence.as_str()),
-            Token::KeyUp(key) => enigo.key_up(key_to_enigo(key)),
-            Token::KeyDown(key) => enigo.key_down(key_to_enigo(key)),
-            Token::MouseUp(button) => match button {
-                Button::Left => enigo.mouse_up(enigo::MouseButton::Left),
-                Button::Middle => enigo.mouse_up(enigo::MouseButton::Middle),
-                Button::Right => enigo.mouse_up(enigo::MouseButton::Right),
-                _ => {}
-            },
-            Token::MouseDown(button) => match button {
-                Button::Left => enigo.mouse_down(enigo::MouseButton::Left),
-                Button::Middle => enigo.mouse_down(enigo::MouseButton::Middle),
-                Button::Right => enigo.mouse_down(enigo::MouseButton::Right),
-                _ => {}
-            },
+            Token::Scancode(sequence) => {
+                for key in sequence.chars() {
+                    backend.scancode_click(key);
+                }
+            }
+            Token::Unicode(unicode_sequence) => backend.key_sequence(unicode_sequence.as_str()),
+            Token::KeyUp(key) => backend.key_up(key),
+            Token::KeyDown(key) => backend.key_down(key),
+            Token::MouseUp(button) => backend.mouse_up(button),
+            Token::MouseDown(button) => backend.mouse_down(button),
             Token::Click(button) => match button {
-                Button::Left => enigo.mouse_click(enigo::MouseButton::Left),
-                Button::Middle => enigo.mouse_click(enigo::MouseButton::Middle),
-                Button::Right => enigo.mouse_click(enigo::MouseButton::Right),
-                Button::ScrollUp => enigo.mouse_scroll_y(1),
-                Button::ScrollDown => enigo.mouse_scroll_y(-1),
-                Button::ScrollLeft => enigo.mouse_scroll_x(1),
-                Button::ScrollRight => enigo.mouse_scroll_x(-1),
+                Button::ScrollUp => backend.mouse_scroll_y(1),
+                Button::ScrollDown => backend.mouse_scroll_y(-1),
+                Button::ScrollLeft => backend.mouse_scroll_x(1),
+                Button::ScrollRight => backend.mouse_scroll_x(-1),
+                _ => backend.mouse_click(button),
             },
+            Token::Delay(duration, jitter) => {
+                backend.end_batch();
+                sleep(jittered(duration, jitter));
+                backend.start_batch();
+            }
+            // handled by the mapper before reaching the worker
+            Token::Profile(_)
+            | Token::AxisLock(_)
+            | Token::Sensitivity(..)
+            | Token::SetVariable(..)
+            | Token::AddVariable(..)
+            | Token::Type(_) => {}
+            Token::Gamepad(input, pressed) => gamepad.input(input, pressed),
+            Token::ScrollX(length) => backend.mouse_scroll_x(length),
+            Token::ScrollY(length) => backend.mouse_scroll_y(length),
+            Token::SmoothScrollX(delta) => backend.mouse_smooth_scroll_x(delta),
+            Token::SmoothScrollY(delta) => backend.mouse_smooth_scroll_y(delta),
+            // added to the live movement, so both don't move the cursor at the same time
+            Token::MoveRelative(x, y) => {
+                backend.end_batch();
+
+                {
+                    let mut mouse_relative_movement =
+                        mouse_relative_movement_condmutex.lock_poisoned();
+
+                    mouse_relative_movement.0 += x;
+                    mouse_relative_movement.1 += y;
+
+                    mouse_relative_movement_condmutex.notify_one();
+                }
+
+                let start = Instant::now();
+
+                while *mouse_relative_movement_condmutex.lock_poisoned() != (0, 0)
+                    && start.elapsed() < MOVEMENT_WAIT_TIMEOUT
+                {
+                    sleep(Duration::from_millis(1));
+                }
+
+                backend.start_batch();
+            }
+            Token::MoveTo(x, y) => backend.mouse_move_to(x, y),
+            Token::Exec(command_line) => exec::run(&command_line),
+            Token::Focus(query) => {
+                window::focus_window(&query);
+            }
+            Token::Launch(command_line) => exec::launch(&command_line),
+            Token::CopyText(text) => {
+                if let Err(err) = clipboard::copy(&text) {
+                    status::error(format!("Clipboard not set : {}", err));
+                }
+
+                backend.end_batch();
+                sleep(clipboard::PASTE_DELAY);
+                backend.start_batch();
+            }
+            Token::Plugin(name, arguments) => plugin::send(&name, &arguments),
+            Token::Midi(message) => midi::send(message),
+            // the module runs here rather than on the device thread, so it can't delay the reports
+            #[cfg(feature = "wasm")]
+            Token::Wasm(wasm_call) => {
+                let token_vec = wasm::run(&wasm_call);
+
+                backend.end_batch();
+                emulate_token_vec(
+                    backend,
+                    gamepad,
+                    mouse_relative_movement_condmutex,
+                    token_vec,
+                );
+                backend.start_batch();
+            }
+            Token::Paste => {
+                backend.key_down(enigo::Key::Control);
+                backend.key_click(enigo::Key::Layout('v'));
+                backend.key_up(enigo::Key::Control);
+            }
+            Token::Hold(key, duration) => {
+                backend.key_down(key);
+                backend.end_batch();
+                sleep(duration);
+                backend.start_batch();
+                backend.key_up(key);
+            }
         }
     }
+
+    backend.end_batch();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(250);
+
+    fn outcome(single_tap: bool, double_tap: bool, pass: bool) -> TapOutcome {
+        TapOutcome {
+            single_tap,
+            double_tap,
+            pass,
+        }
+    }
+
+    #[test]
+    fn single_tap_is_played_once_the_window_expires() {
+        let mut double_tap = DoubleTap::default();
+        let start = Instant::now();
+
+        assert_eq!(
+            double_tap.update(start, WINDOW, false, true),
+            outcome(false, false, false)
+        );
+        assert_eq!(
+            double_tap.update(start + Duration::from_millis(50), WINDOW, true, false),
+            outcome(false, false, false)
+        );
+        assert_eq!(
+            double_tap.update(start + Duration::from_millis(100), WINDOW, false, false),
+            outcome(false, false, false)
+        );
+        assert_eq!(
+            double_tap.update(start + WINDOW, WINDOW, false, false),
+            outcome(true, false, true)
+        );
+        assert!(double_tap.first_press.is_none());
+    }
+
+    #[test]
+    fn double_tap_inside_the_window() {
+        let mut double_tap = DoubleTap::default();
+        let start = Instant::now();
+
+        double_tap.update(start, WINDOW, false, true);
+        double_tap.update(start + Duration::from_millis(50), WINDOW, true, false);
+
+        assert_eq!(
+            double_tap.update(start + Duration::from_millis(100), WINDOW, false, true),
+            outcome(false, true, false)
+        );
+        // the second tap is swallowed until released, without a late single tap
+        assert_eq!(
+            double_tap.update(start + WINDOW * 2, WINDOW, true, true),
+            outcome(false, false, false)
+        );
+        assert_eq!(
+            double_tap.update(start + WINDOW * 3, WINDOW, true, false),
+            outcome(false, false, false)
+        );
+        assert_eq!(
+            double_tap.update(start + WINDOW * 4, WINDOW, false, false),
+            outcome(false, false, true)
+        );
+    }
+
+    #[test]
+    fn hold_plays_the_single_tap_once_the_window_expires() {
+        let mut double_tap = DoubleTap::default();
+        let start = Instant::now();
+
+        double_tap.update(start, WINDOW, false, true);
+
+        assert_eq!(
+            double_tap.update(start + Duration::from_millis(100), WINDOW, true, true),
+            outcome(false, false, false)
+        );
+        // still held, so the momentary action stays pressed until the release
+        assert_eq!(
+            double_tap.update(start + WINDOW, WINDOW, true, true),
+            outcome(true, false, true)
+        );
+        assert_eq!(
+            double_tap.update(start + WINDOW * 2, WINDOW, true, false),
+            outcome(false, false, true)
+        );
+    }
 }
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rusb::{DeviceHandle, UsbContext};
+
+use crate::hid::{get_feature_report, set_feature_report};
+use crate::model::{DeviceModel, MemoryLayout};
+
+// the layout of the pages and the read command aren't documented, nor checked against a capture,
+// so the memory is only ever read, never written, and only once the user opts in
+const READ_PAGE: u8 = 0;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(unverified_memory_dump: bool) {
+    ENABLED.store(unverified_memory_dump, Ordering::SeqCst);
+}
+
+// pages are addressed with : report id, read command, page low byte, page high byte
+fn page_header(memory_layout: &MemoryLayout, command: u8, page: u16) -> Vec<u8> {
+    let [page_low, page_high] = page.to_le_bytes();
+
+    vec![memory_layout.report_id, command, page_low, page_high]
+}
+
+fn memory_layout(device_model: &DeviceModel) -> rusb::Result<&MemoryLayout> {
+    device_model
+        .memory
+        .as_ref()
+        .filter(|_| ENABLED.load(Ordering::SeqCst))
+        .ok_or(rusb::Error::NotSupported)
+}
+
+// reads the whole onboard profile memory
+pub fn dump<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    iface: u8,
+) -> rusb::Result<Vec<u8>> {
+    let memory_layout = memory_layout(device_model)?;
+    let mut memory = Vec::with_capacity(memory_layout.size());
+
+    for page in 0..memory_layout.page_count {
+        set_feature_report(
+            device_handle,
+            iface,
+            &page_header(memory_layout, READ_PAGE, page),
+        )?;
+
+        let data = get_feature_report(
+            device_handle,
+            iface,
+            memory_layout.report_id,
+            memory_layout.page_size + 1,
+        )?;
+
+        if data.len() != memory_layout.page_size + 1 {
+            return Err(rusb::Error::Io);
+        }
+
+        memory.extend(&data[1..]);
+    }
+
+    Ok(memory)
+}
@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::command::DriverStatus;
+
+// a report rate older than this is stale, the mouse isn't sending anything
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// counters shared by a mapper and its workers
+#[derive(Default)]
+pub struct Metrics {
+    // token vectors sent to the emulation worker, and played by it
+    queued_count: AtomicU64,
+    played_count: AtomicU64,
+    // between a token vector being sent and played
+    total_latency_micros: AtomicU64,
+    // reports moving the cursor, and moves done by the movement worker
+    movement_count: AtomicU64,
+    moved_count: AtomicU64,
+    // workers restarted after a panic
+    restart_count: AtomicU64,
+}
+
+impl Metrics {
+    pub fn token_vec_queued(&self) {
+        self.queued_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn token_vec_played(&self, queued_at: Instant) {
+        self.total_latency_micros
+            .fetch_add(queued_at.elapsed().as_micros() as u64, Ordering::Relaxed);
+        self.played_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn movement_queued(&self) {
+        self.movement_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn movement_played(&self) {
+        self.moved_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn worker_restarted(&self) {
+        self.restart_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn restart_count(&self) -> u64 {
+        self.restart_count.load(Ordering::Relaxed)
+    }
+}
+
+// reports read from the device during the last full second
+pub struct ReportRate {
+    window_start: Instant,
+    window_count: u32,
+    rate: u32,
+}
+
+impl Default for ReportRate {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window_count: 0,
+            rate: 0,
+        }
+    }
+}
+
+impl ReportRate {
+    pub fn count(&mut self) {
+        if self.window_start.elapsed() >= RATE_WINDOW {
+            self.rate = if self.window_start.elapsed() < RATE_WINDOW * 2 {
+                self.window_count
+            } else {
+                0
+            };
+            self.window_start = Instant::now();
+            self.window_count = 0;
+        }
+
+        self.window_count += 1;
+    }
+
+    fn rate(&self) -> u32 {
+        if self.window_start.elapsed() < RATE_WINDOW * 2 {
+            self.rate
+        } else {
+            0
+        }
+    }
+}
+
+pub fn driver_status(
+    serial_number: String,
+    metrics: &Metrics,
+    report_rate: &ReportRate,
+) -> DriverStatus {
+    let played_count = metrics.played_count.load(Ordering::Relaxed);
+    let total_latency_micros = metrics.total_latency_micros.load(Ordering::Relaxed);
+
+    DriverStatus::new(
+        serial_number,
+        report_rate.rate(),
+        metrics
+            .queued_count
+            .load(Ordering::Relaxed)
+            .saturating_sub(played_count),
+        total_latency_micros.checked_div(played_count).unwrap_or(0),
+        metrics
+            .movement_count
+            .load(Ordering::Relaxed)
+            .saturating_sub(metrics.moved_count.load(Ordering::Relaxed)),
+        metrics.restart_count(),
+    )
+}
@@ -0,0 +1,306 @@
+use std::sync::{Mutex, OnceLock};
+
+use util::thread::MutexTrait;
+
+use crate::status;
+
+// name of the virtual port created on linux, and part of the name of the output opened on windows,
+// which can't create virtual ports without a driver like loopMIDI
+const DEFAULT_PORT_NAME: &str = "Mad Rust MMO7";
+
+static PORT_NAME: OnceLock<String> = OnceLock::new();
+// opened by the first message, and again by the next one after a failure
+static OUTPUT: Mutex<Option<platform::Output>> = Mutex::new(None);
+
+// channels from 0 to 15
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+}
+
+pub fn configure(port_name: Option<String>) {
+    PORT_NAME
+        .set(port_name.unwrap_or_else(|| DEFAULT_PORT_NAME.to_string()))
+        .ok();
+}
+
+pub fn send(message: MidiMessage) {
+    let mut output = OUTPUT.lock_poisoned();
+
+    if output.is_none() {
+        let port_name = PORT_NAME
+            .get()
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_PORT_NAME);
+
+        match platform::Output::open(port_name) {
+            Ok(opened_output) => *output = Some(opened_output),
+            Err(err) => {
+                status::error(format!("MIDI output not opened : {}", err));
+                return;
+            }
+        }
+    }
+
+    if let Some(opened_output) = output.as_mut() {
+        if let Err(err) = opened_output.send(message) {
+            status::error(format!("MIDI message not sent : {}", err));
+            *output = None;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Write};
+    use std::mem::size_of;
+    use std::os::unix::io::AsRawFd;
+
+    use super::MidiMessage;
+
+    // ioctl requests and structures from sound/asequencer.h, the alsa sequencer
+    // being used through the kernel like libasound does
+    const SEQ_IOCTL_CLIENT_ID: libc::c_ulong = 0x80045301;
+    const SEQ_IOCTL_SET_CLIENT_INFO: libc::c_ulong = 0x40bc5311;
+    const SEQ_IOCTL_CREATE_PORT: libc::c_ulong =
+        0xc0005320 | (PORT_INFO_SIZE as libc::c_ulong) << 16;
+    const CLIENT_INFO_SIZE: usize = 188;
+    // the port info holds a pointer
+    const PORT_INFO_SIZE: usize = 160 + size_of::<usize>();
+    const EVENT_SIZE: usize = 28;
+    const USER_CLIENT: libc::c_int = 1;
+    const PORT_CAP_READ: u32 = 1 << 0;
+    const PORT_CAP_SUBS_READ: u32 = 1 << 5;
+    const PORT_TYPE_MIDI_GENERIC: u32 = 1 << 1;
+    const PORT_TYPE_SOFTWARE: u32 = 1 << 17;
+    const PORT_TYPE_APPLICATION: u32 = 1 << 20;
+    const EVENT_NOTE_ON: u8 = 6;
+    const EVENT_NOTE_OFF: u8 = 7;
+    const EVENT_CONTROLLER: u8 = 10;
+    const QUEUE_DIRECT: u8 = 253;
+    const ADDRESS_SUBSCRIBERS: u8 = 254;
+    const ADDRESS_UNKNOWN: u8 = 253;
+
+    // a port other programs subscribe to, like the ones of a daw or of a lighting software
+    pub struct Output {
+        file: File,
+        port: u8,
+    }
+
+    impl Output {
+        pub fn open(port_name: &str) -> io::Result<Self> {
+            let file = OpenOptions::new().write(true).open("/dev/snd/seq")?;
+            let fd = file.as_raw_fd();
+            let mut client: libc::c_int = 0;
+
+            if unsafe { libc::ioctl(fd, SEQ_IOCTL_CLIENT_ID, &mut client) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // the client is named after the port too, instead of its number
+            let mut client_info = [0u8; CLIENT_INFO_SIZE];
+
+            client_info[0..4].copy_from_slice(&client.to_ne_bytes());
+            client_info[4..8].copy_from_slice(&USER_CLIENT.to_ne_bytes());
+            write_name(&mut client_info[8..72], port_name);
+
+            unsafe { libc::ioctl(fd, SEQ_IOCTL_SET_CLIENT_INFO, client_info.as_ptr()) };
+
+            let mut port_info = [0u8; PORT_INFO_SIZE];
+            let capability = PORT_CAP_READ | PORT_CAP_SUBS_READ;
+            let port_type = PORT_TYPE_MIDI_GENERIC | PORT_TYPE_SOFTWARE | PORT_TYPE_APPLICATION;
+
+            port_info[0] = client as u8;
+            write_name(&mut port_info[2..66], port_name);
+            port_info[68..72].copy_from_slice(&capability.to_ne_bytes());
+            port_info[72..76].copy_from_slice(&port_type.to_ne_bytes());
+            port_info[76..80].copy_from_slice(&16i32.to_ne_bytes());
+
+            if unsafe { libc::ioctl(fd, SEQ_IOCTL_CREATE_PORT, port_info.as_mut_ptr()) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Self {
+                file,
+                port: port_info[1],
+            })
+        }
+
+        // sent right away to the subscribers of the port
+        pub fn send(&mut self, message: MidiMessage) -> io::Result<()> {
+            let mut event = [0u8; EVENT_SIZE];
+
+            match message {
+                MidiMessage::NoteOn {
+                    channel,
+                    note,
+                    velocity,
+                } => {
+                    event[0] = EVENT_NOTE_ON;
+                    event[16..19].copy_from_slice(&[channel, note, velocity]);
+                }
+                MidiMessage::NoteOff { channel, note } => {
+                    event[0] = EVENT_NOTE_OFF;
+                    event[16..18].copy_from_slice(&[channel, note]);
+                }
+                MidiMessage::ControlChange {
+                    channel,
+                    controller,
+                    value,
+                } => {
+                    event[0] = EVENT_CONTROLLER;
+                    event[16] = channel;
+                    event[20..24].copy_from_slice(&(controller as u32).to_ne_bytes());
+                    event[24..28].copy_from_slice(&(value as i32).to_ne_bytes());
+                }
+            }
+
+            event[3] = QUEUE_DIRECT;
+            event[13] = self.port;
+            event[14] = ADDRESS_SUBSCRIBERS;
+            event[15] = ADDRESS_UNKNOWN;
+
+            self.file.write_all(&event)
+        }
+    }
+
+    // null terminated, and cut to fit
+    fn write_name(buffer: &mut [u8], name: &str) {
+        let length = name.len().min(buffer.len() - 1);
+
+        buffer[..length].copy_from_slice(&name.as_bytes()[..length]);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::io;
+    use std::mem::{size_of, zeroed};
+
+    use windows_sys::Win32::Media::Audio::{
+        midiOutClose, midiOutGetDevCapsW, midiOutGetNumDevs, midiOutOpen, midiOutShortMsg,
+        CALLBACK_NULL, HMIDIOUT, MIDIOUTCAPSW,
+    };
+
+    use super::MidiMessage;
+
+    pub struct Output {
+        handle: HMIDIOUT,
+    }
+
+    impl Output {
+        // the first output whose name contains the port name
+        pub fn open(port_name: &str) -> io::Result<Self> {
+            let port_name = port_name.to_lowercase();
+            let device_id = (0..unsafe { midiOutGetNumDevs() })
+                .find(|device_id| {
+                    let mut capabilities: MIDIOUTCAPSW = unsafe { zeroed() };
+
+                    if unsafe {
+                        midiOutGetDevCapsW(
+                            *device_id as usize,
+                            &mut capabilities,
+                            size_of::<MIDIOUTCAPSW>() as u32,
+                        )
+                    } != 0
+                    {
+                        return false;
+                    }
+
+                    let name = capabilities.szPname;
+                    let length = name
+                        .iter()
+                        .position(|char| *char == 0)
+                        .unwrap_or(name.len());
+
+                    String::from_utf16_lossy(&name[..length])
+                        .to_lowercase()
+                        .contains(&port_name)
+                })
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "no MIDI output with this name")
+                })?;
+            let mut handle: HMIDIOUT = 0;
+
+            if unsafe { midiOutOpen(&mut handle, device_id, 0, 0, CALLBACK_NULL) } != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "MIDI output already in use",
+                ));
+            }
+
+            Ok(Self { handle })
+        }
+
+        // the status byte then the two data bytes, packed in the low bytes
+        pub fn send(&mut self, message: MidiMessage) -> io::Result<()> {
+            let bytes = match message {
+                MidiMessage::NoteOn {
+                    channel,
+                    note,
+                    velocity,
+                } => [0x90 | channel, note, velocity],
+                MidiMessage::NoteOff { channel, note } => [0x80 | channel, note, 0],
+                MidiMessage::ControlChange {
+                    channel,
+                    controller,
+                    value,
+                } => [0xb0 | channel, controller, value],
+            };
+
+            if unsafe {
+                midiOutShortMsg(
+                    self.handle,
+                    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]),
+                )
+            } != 0
+            {
+                return Err(io::Error::new(io::ErrorKind::Other, "MIDI output closed"));
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Drop for Output {
+        fn drop(&mut self) {
+            unsafe { midiOutClose(self.handle) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::io;
+
+    use super::MidiMessage;
+
+    pub struct Output;
+
+    impl Output {
+        pub fn open(_port_name: &str) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "no MIDI output on this system",
+            ))
+        }
+
+        pub fn send(&mut self, _message: MidiMessage) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
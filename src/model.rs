@@ -0,0 +1,354 @@
+use util::connection::command::DriverConfigurationDescriptor;
+
+// a bit of the interrupt report
+#[derive(Debug, Clone, Copy)]
+pub struct ReportBit {
+    pub byte: usize,
+    pub mask: u8,
+}
+
+impl ReportBit {
+    const fn new(byte: usize, mask: u8) -> Self {
+        Self { byte, mask }
+    }
+
+    pub fn is_set(&self, buffer: &[u8]) -> bool {
+        matches!(buffer.get(self.byte), Some(value) if value & self.mask > 0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReportLayout {
+    pub length: usize,
+    pub left: ReportBit,
+    pub right: ReportBit,
+    pub middle: ReportBit,
+    // byte holding the mode in its low bits, 0b100 being the shift button
+    pub mode: usize,
+    // bytes holding the signed relative movement
+    pub x: usize,
+    pub y: usize,
+    pub wheel: usize,
+}
+
+// binding keys of the wheel directions, which aren't part of the buttons
+pub const WHEEL_KEYS: [&str; 2] = ["wheel_up", "wheel_down"];
+
+// buttons swapped with each other for the left handed
+const MIRRORED_KEYS: [(&str, &str); 4] = [
+    ("left_click", "right_click"),
+    ("left_actionlock", "right_actionlock"),
+    ("hat_left", "hat_right"),
+    ("thumb_anticlockwise", "thumb_clockwise"),
+];
+
+pub fn mirrored_key(key: &str) -> &str {
+    for (left_key, right_key) in MIRRORED_KEYS {
+        if key == left_key {
+            return right_key;
+        }
+        if key == right_key {
+            return left_key;
+        }
+    }
+
+    key
+}
+
+#[derive(Debug)]
+pub struct ButtonLayout {
+    // shown by the client
+    pub name: &'static str,
+    // key of the binding in the config file
+    pub key: &'static str,
+    pub bit: ReportBit,
+}
+
+#[derive(Debug)]
+pub struct MemoryLayout {
+    // feature report used to read the pages
+    pub report_id: u8,
+    pub page_size: usize,
+    pub page_count: u16,
+}
+
+impl MemoryLayout {
+    pub fn size(&self) -> usize {
+        self.page_size * self.page_count as usize
+    }
+}
+
+#[derive(Debug)]
+pub struct DeviceModel {
+    pub name: &'static str,
+    // vendor and product ids, the first pair is sent in the driver configuration descriptor
+    pub ids: &'static [(u16, u16)],
+    pub icon: &'static [u8],
+    pub mode_count: u8,
+    pub shift_mode_count: u8,
+    pub buttons: &'static [ButtonLayout],
+    pub report: ReportLayout,
+    // feature report setting the mode leds color, none when they can't be changed
+    pub led_report_id: Option<u8>,
+    // onboard profile memory, none when the device has none
+    pub memory: Option<MemoryLayout>,
+    // feature report setting the report interval, none when the device keeps its own
+    pub polling_rate_report_id: Option<u8>,
+    // feature reports setting the dpi stages and the lift-off distance, none when unknown
+    pub dpi_report_id: Option<u8>,
+    pub lift_off_report_id: Option<u8>,
+}
+
+impl DeviceModel {
+    pub fn matches(&self, vendor_id: u16, product_id: u16) -> bool {
+        self.ids.contains(&(vendor_id, product_id))
+    }
+
+    pub fn button_index(&self, key: &str) -> Option<usize> {
+        self.buttons.iter().position(|button| button.key == key)
+    }
+
+    pub fn descriptor(&self) -> DriverConfigurationDescriptor {
+        let (vendor_id, product_id) = self.ids[0];
+
+        DriverConfigurationDescriptor::new(
+            vendor_id,
+            product_id,
+            self.name.to_string(),
+            self.icon.to_vec(),
+            self.mode_count,
+            self.shift_mode_count,
+            self.buttons
+                .iter()
+                .map(|button| button.name.to_string())
+                .collect(),
+        )
+    }
+}
+
+// the saitek made mice all share the mmo7 report layout
+const SAITEK_REPORT: ReportLayout = ReportLayout {
+    length: 8,
+    left: ReportBit::new(0, 1),
+    right: ReportBit::new(0, 2),
+    middle: ReportBit::new(0, 4),
+    mode: 2,
+    x: 3,
+    y: 5,
+    wheel: 7,
+};
+
+const MMO7: DeviceModel = DeviceModel {
+    name: "MMO7",
+    ids: &[(0x0738, 0x1713), (0x06a3, 0x0cd0)],
+    icon: include_bytes!("../icon.png"),
+    mode_count: 3,
+    shift_mode_count: 3,
+    buttons: &[
+        ButtonLayout {
+            name: "Scroll Button",
+            key: "scroll_button",
+            bit: ReportBit::new(2, 8),
+        },
+        ButtonLayout {
+            name: "Left ActionLock",
+            key: "left_actionlock",
+            bit: ReportBit::new(2, 16),
+        },
+        ButtonLayout {
+            name: "Right ActionLock",
+            key: "right_actionlock",
+            bit: ReportBit::new(2, 32),
+        },
+        ButtonLayout {
+            name: "Forwards Button",
+            key: "forwards_button",
+            bit: ReportBit::new(0, 16),
+        },
+        ButtonLayout {
+            name: "Back Button",
+            key: "back_button",
+            bit: ReportBit::new(0, 8),
+        },
+        ButtonLayout {
+            name: "Thumb Anticlockwise",
+            key: "thumb_anticlockwise",
+            bit: ReportBit::new(1, 64),
+        },
+        ButtonLayout {
+            name: "Thumb Clockwise",
+            key: "thumb_clockwise",
+            bit: ReportBit::new(1, 32),
+        },
+        ButtonLayout {
+            name: "Hat Top",
+            key: "hat_top",
+            bit: ReportBit::new(1, 1),
+        },
+        ButtonLayout {
+            name: "Hat Left",
+            key: "hat_left",
+            bit: ReportBit::new(1, 4),
+        },
+        ButtonLayout {
+            name: "Hat Right",
+            key: "hat_right",
+            bit: ReportBit::new(1, 8),
+        },
+        ButtonLayout {
+            name: "Hat Bottom",
+            key: "hat_bottom",
+            bit: ReportBit::new(1, 2),
+        },
+        ButtonLayout {
+            name: "Button 1",
+            key: "button_1",
+            bit: ReportBit::new(0, 32),
+        },
+        ButtonLayout {
+            name: "Precision Aim",
+            key: "precision_aim",
+            bit: ReportBit::new(1, 16),
+        },
+        ButtonLayout {
+            name: "Button 2",
+            key: "button_2",
+            bit: ReportBit::new(0, 64),
+        },
+        ButtonLayout {
+            name: "Button 3",
+            key: "button_3",
+            bit: ReportBit::new(0, 128),
+        },
+        // the middle click is bound through the scroll button
+        ButtonLayout {
+            name: "Left Click",
+            key: "left_click",
+            bit: SAITEK_REPORT.left,
+        },
+        ButtonLayout {
+            name: "Right Click",
+            key: "right_click",
+            bit: SAITEK_REPORT.right,
+        },
+    ],
+    report: SAITEK_REPORT,
+    // unverified, only sent with the unverified_led_report setting
+    led_report_id: Some(0x01),
+    // unverified, only read with the unverified_memory_dump setting
+    memory: Some(MemoryLayout {
+        report_id: 0x02,
+        page_size: 64,
+        page_count: 128,
+    }),
+    polling_rate_report_id: None,
+    dpi_report_id: None,
+    lift_off_report_id: None,
+};
+
+// the r.a.t. only have the side buttons and the precision aim button besides the clicks
+const RAT_BUTTONS: &[ButtonLayout] = &[
+    ButtonLayout {
+        name: "Forwards Button",
+        key: "forwards_button",
+        bit: ReportBit::new(0, 16),
+    },
+    ButtonLayout {
+        name: "Back Button",
+        key: "back_button",
+        bit: ReportBit::new(0, 8),
+    },
+    ButtonLayout {
+        name: "Precision Aim",
+        key: "precision_aim",
+        bit: ReportBit::new(1, 16),
+    },
+    ButtonLayout {
+        name: "Left Click",
+        key: "left_click",
+        bit: SAITEK_REPORT.left,
+    },
+    ButtonLayout {
+        name: "Right Click",
+        key: "right_click",
+        bit: SAITEK_REPORT.right,
+    },
+    ButtonLayout {
+        name: "Middle Click",
+        key: "middle_click",
+        bit: SAITEK_REPORT.middle,
+    },
+];
+
+const RAT7: DeviceModel = DeviceModel {
+    name: "R.A.T.7",
+    ids: &[(0x06a3, 0x0cd7), (0x06a3, 0x0ccb)],
+    icon: include_bytes!("../icon.png"),
+    mode_count: 3,
+    shift_mode_count: 0,
+    buttons: RAT_BUTTONS,
+    report: SAITEK_REPORT,
+    led_report_id: None,
+    memory: None,
+    polling_rate_report_id: None,
+    dpi_report_id: None,
+    lift_off_report_id: None,
+};
+
+const RAT9: DeviceModel = DeviceModel {
+    name: "R.A.T.9",
+    ids: &[(0x0738, 0x1709), (0x06a3, 0x0cfa)],
+    icon: include_bytes!("../icon.png"),
+    mode_count: 3,
+    shift_mode_count: 0,
+    buttons: RAT_BUTTONS,
+    report: SAITEK_REPORT,
+    led_report_id: None,
+    memory: None,
+    polling_rate_report_id: None,
+    dpi_report_id: None,
+    lift_off_report_id: None,
+};
+
+// new models only need an entry here, the mmo te being left out until its ids and its report
+// layout are known
+pub static DEVICE_MODELS: &[DeviceModel] = &[MMO7, RAT7, RAT9];
+
+pub fn find(vendor_id: u16, product_id: u16) -> Option<&'static DeviceModel> {
+    DEVICE_MODELS
+        .iter()
+        .find(|device_model| device_model.matches(vendor_id, product_id))
+}
+
+// each model once, in order, every model being given when none is so the client still
+// knows the driver without any device connected
+pub fn distinct_models(
+    device_model_iter: impl Iterator<Item = &'static DeviceModel>,
+) -> Vec<&'static DeviceModel> {
+    let mut device_model_vec: Vec<&'static DeviceModel> = vec![];
+
+    for device_model in device_model_iter {
+        if !device_model_vec
+            .iter()
+            .any(|other_device_model| other_device_model.name == device_model.name)
+        {
+            device_model_vec.push(device_model);
+        }
+    }
+
+    if device_model_vec.is_empty() {
+        return DEVICE_MODELS.iter().collect();
+    }
+
+    device_model_vec
+}
+
+// configs without a model were written for the mmo7
+pub fn find_by_name(name: Option<&str>) -> &'static DeviceModel {
+    name.and_then(|name| {
+        DEVICE_MODELS
+            .iter()
+            .find(|device_model| device_model.name == name)
+    })
+    .unwrap_or(&DEVICE_MODELS[0])
+}
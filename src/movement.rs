@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+// maps the speed of a report, in counts, to the speed of the cursor
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ResponseCurve {
+    Linear {
+        factor: f32,
+    },
+    // factor * speed ^ exponent, accelerating above an exponent of 1
+    Power {
+        exponent: f32,
+        #[serde(default = "default_factor")]
+        factor: f32,
+    },
+    // input and output speeds, interpolated between the points and from the origin
+    Custom {
+        points: Vec<[f32; 2]>,
+    },
+}
+
+fn default_factor() -> f32 {
+    1.0
+}
+
+impl ResponseCurve {
+    fn apply(&self, speed: f32) -> f32 {
+        match self {
+            Self::Linear { factor } => speed * factor,
+            Self::Power { exponent, factor } => speed.powf(*exponent) * factor,
+            Self::Custom { points } => {
+                let mut previous_point = [0.0, 0.0];
+
+                for point in points {
+                    if speed <= point[0] {
+                        let span = point[0] - previous_point[0];
+
+                        return previous_point[1]
+                            + (speed - previous_point[0]) / span * (point[1] - previous_point[1]);
+                    }
+
+                    previous_point = *point;
+                }
+
+                // past the last point, its ratio is kept
+                if previous_point[0] > 0.0 {
+                    speed * previous_point[1] / previous_point[0]
+                } else {
+                    speed
+                }
+            }
+        }
+    }
+
+    // why the curve can't be used
+    pub fn check(&self) -> Option<&'static str> {
+        match self {
+            Self::Linear { factor } | Self::Power { factor, .. } if *factor <= 0.0 => {
+                Some("expects a factor above 0")
+            }
+            Self::Power { exponent, .. } if *exponent <= 0.0 => Some("expects an exponent above 0"),
+            Self::Custom { points } => {
+                let mut previous_input = 0.0;
+
+                for [input, output] in points {
+                    if *input <= previous_input || *output < 0.0 {
+                        return Some("expects points with increasing positive speeds");
+                    }
+
+                    previous_input = *input;
+                }
+
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+// how the report deltas are turned into cursor movement
+#[derive(Debug, Clone, Default)]
+pub struct MovementSettings {
+    // divides the movement while the precision aim button is held
+    pub precision_aim_factor: Option<f32>,
+    pub response_curve: Option<ResponseCurve>,
+    // multiplies the x and y movement, set for the device rather than the profile
+    pub sensitivity: Option<[f32; 2]>,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub swap_axes: bool,
+}
+
+// distance in counts the movement travels before the locked direction is chosen
+const AXIS_LOCK_DISTANCE: f32 = 8.0;
+
+// locks the movement to the direction it starts in, snapped to the step
+struct AxisLock {
+    // in radians
+    step: f32,
+    travel: (f32, f32),
+    direction: Option<(f32, f32)>,
+}
+
+impl AxisLock {
+    fn apply(&mut self, x: f32, y: f32) -> (f32, f32) {
+        let ((x, y), (direction_x, direction_y)) = match self.direction {
+            Some(direction) => ((x, y), direction),
+            None => {
+                self.travel.0 += x;
+                self.travel.1 += y;
+
+                // held back until the direction is known
+                if self.travel.0.hypot(self.travel.1) < AXIS_LOCK_DISTANCE {
+                    return (0.0, 0.0);
+                }
+
+                let angle = (self.travel.1.atan2(self.travel.0) / self.step).round() * self.step;
+                let direction = (angle.cos(), angle.sin());
+
+                self.direction = Some(direction);
+
+                (self.travel, direction)
+            }
+        };
+        let length = x * direction_x + y * direction_y;
+
+        (length * direction_x, length * direction_y)
+    }
+}
+
+// keeps the fractions of the movement for the next reports
+#[derive(Default)]
+pub struct MovementFilter {
+    remainder: (f32, f32),
+    axis_lock: Option<AxisLock>,
+    // multipliers of the held sensitivity bindings
+    sensitivity_vec: Vec<f32>,
+}
+
+impl MovementFilter {
+    // step in degrees, unlocks when none
+    pub fn lock_axis(&mut self, step: Option<f32>) {
+        self.axis_lock = step.map(|step| AxisLock {
+            step: step.to_radians(),
+            travel: (0.0, 0.0),
+            direction: None,
+        });
+    }
+
+    // added while the binding is held, and removed once released
+    pub fn hold_sensitivity(&mut self, multiplier: f32, held: bool) {
+        if held {
+            self.sensitivity_vec.push(multiplier);
+        } else if let Some(index) = self
+            .sensitivity_vec
+            .iter()
+            .position(|other_multiplier| *other_multiplier == multiplier)
+        {
+            self.sensitivity_vec.remove(index);
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        settings: &MovementSettings,
+        precision_aim: bool,
+        x: i32,
+        y: i32,
+    ) -> (i32, i32) {
+        let (mut x, mut y) = (x as f32, y as f32);
+
+        if let (true, Some(factor)) = (precision_aim, settings.precision_aim_factor) {
+            x /= factor;
+            y /= factor;
+        }
+
+        let multiplier: f32 = self.sensitivity_vec.iter().product();
+
+        x *= multiplier;
+        y *= multiplier;
+
+        if let Some(response_curve) = &settings.response_curve {
+            let speed = x.hypot(y);
+
+            if speed > 0.0 {
+                let scale = response_curve.apply(speed) / speed;
+
+                x *= scale;
+                y *= scale;
+            }
+        }
+
+        if let Some([sensitivity_x, sensitivity_y]) = settings.sensitivity {
+            x *= sensitivity_x;
+            y *= sensitivity_y;
+        }
+
+        if settings.swap_axes {
+            (x, y) = (y, x);
+        }
+        if settings.invert_x {
+            x = -x;
+        }
+        if settings.invert_y {
+            y = -y;
+        }
+
+        if let Some(axis_lock) = &mut self.axis_lock {
+            (x, y) = axis_lock.apply(x, y);
+        }
+
+        x += self.remainder.0;
+        y += self.remainder.1;
+        self.remainder = (x.fract(), y.fract());
+
+        (x.trunc() as i32, y.trunc() as i32)
+    }
+}
@@ -0,0 +1,233 @@
+use std::io;
+use std::process;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::time::{interval, sleep};
+
+use crate::websocket::{DriverEvent, EventSender};
+
+// minimal mqtt 3.1.1 client, only publishing without acknowledgement
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBLISH_RETAIN: u8 = 0x01;
+const PINGREQ: u8 = 0xc0;
+const CLEAN_SESSION: u8 = 0x02;
+const PASSWORD_FLAG: u8 = 0x40;
+const USERNAME_FLAG: u8 = 0x80;
+// in seconds, a ping being sent every half of it
+const KEEP_ALIVE: u16 = 60;
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+// `{serial}` is replaced by the serial number of the mouse in the topics,
+// and `{button}` by the key of the button in the config file
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MqttSettings {
+    // host and port of the broker, like `192.168.1.10:1883`
+    pub broker: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "default_button_topic")]
+    pub button_topic: String,
+    #[serde(default = "default_mode_topic")]
+    pub mode_topic: String,
+}
+
+fn default_button_topic() -> String {
+    "mad-rust/mmo7/{serial}/button/{button}".to_string()
+}
+
+fn default_mode_topic() -> String {
+    "mad-rust/mmo7/{serial}/mode".to_string()
+}
+
+// the button events and mode changes, so spare buttons can toggle lights or scenes
+pub async fn run_mqtt(mqtt_settings: MqttSettings, event_tx: EventSender) {
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match connect(&mqtt_settings).await {
+                Ok(stream) => {
+                    println!("MQTT connected to {}", mqtt_settings.broker);
+
+                    // the events of the disconnection are outdated
+                    event_rx = event_rx.resubscribe();
+
+                    match publish_events(stream, &mqtt_settings, &mut event_rx).await {
+                        Ok(()) => return,
+                        Err(err) => println!("MQTT disconnected : {}", err),
+                    }
+                }
+                Err(err) => println!("MQTT broker {} not reached : {}", mqtt_settings.broker, err),
+            }
+
+            sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn connect(mqtt_settings: &MqttSettings) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(&mqtt_settings.broker).await?;
+
+    stream.write_all(&connect_packet(mqtt_settings)).await?;
+
+    let mut connack = [0; 4];
+
+    stream.read_exact(&mut connack).await?;
+
+    match connack {
+        [CONNACK, 2, _, 0] => Ok(stream),
+        [CONNACK, 2, _, code] => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("connection refused with code {}", code),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an MQTT broker",
+        )),
+    }
+}
+
+// until the events end, errors being the loss of the connection
+async fn publish_events(
+    mut stream: TcpStream,
+    mqtt_settings: &MqttSettings,
+    event_rx: &mut Receiver<DriverEvent>,
+) -> io::Result<()> {
+    let mut ping_interval = interval(Duration::from_secs(KEEP_ALIVE as u64 / 2));
+    let mut buffer = [0; 256];
+
+    loop {
+        tokio::select! {
+            event = event_rx.recv() => {
+                let (topic, payload, retain) = match event {
+                    Ok(DriverEvent::Button {
+                        serial_number,
+                        button,
+                        pressed,
+                    }) => (
+                        mqtt_settings
+                            .button_topic
+                            .replace("{serial}", &serial_number)
+                            .replace("{button}", &button),
+                        if pressed { "pressed" } else { "released" }.to_string(),
+                        false,
+                    ),
+                    // retained, so the current mode is known as soon as subscribed
+                    Ok(DriverEvent::Mode {
+                        serial_number,
+                        mode,
+                        shift,
+                    }) => (
+                        mqtt_settings.mode_topic.replace("{serial}", &serial_number),
+                        json!({ "mode": mode + 1, "shift": shift }).to_string(),
+                        true,
+                    ),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+
+                stream
+                    .write_all(&publish_packet(&topic, payload.as_bytes(), retain))
+                    .await?;
+            }
+            _ = ping_interval.tick() => stream.write_all(&[PINGREQ, 0]).await?,
+            // the answers of the broker are only read to notice the end of the connection
+            read = stream.read(&mut buffer) => {
+                if read? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+    }
+}
+
+fn connect_packet(mqtt_settings: &MqttSettings) -> Vec<u8> {
+    let mut flags = CLEAN_SESSION;
+    let mut body = vec![];
+
+    // the password is only sent along with a username
+    let password = mqtt_settings
+        .password
+        .as_ref()
+        .filter(|_| mqtt_settings.username.is_some());
+
+    if mqtt_settings.username.is_some() {
+        flags |= USERNAME_FLAG;
+    }
+
+    if password.is_some() {
+        flags |= PASSWORD_FLAG;
+    }
+
+    push_string(&mut body, "MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend(KEEP_ALIVE.to_be_bytes());
+    push_string(&mut body, &format!("mad-rust-mmo7-{}", process::id()));
+
+    if let Some(username) = &mqtt_settings.username {
+        push_string(&mut body, username);
+    }
+
+    if let Some(password) = password {
+        push_string(&mut body, password);
+    }
+
+    packet(CONNECT, body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = vec![];
+
+    push_string(&mut body, topic);
+    body.extend(payload);
+
+    packet(
+        if retain {
+            PUBLISH | PUBLISH_RETAIN
+        } else {
+            PUBLISH
+        },
+        body,
+    )
+}
+
+// the remaining length is written 7 bits per byte, the high bit telling another byte follows
+fn packet(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+
+    loop {
+        let mut byte = (length % 128) as u8;
+
+        length /= 128;
+
+        if length > 0 {
+            byte |= 0x80;
+        }
+
+        packet.push(byte);
+
+        if length == 0 {
+            break;
+        }
+    }
+
+    packet.extend(body);
+    packet
+}
+
+fn push_string(body: &mut Vec<u8>, string: &str) {
+    body.extend((string.len() as u16).to_be_bytes());
+    body.extend(string.as_bytes());
+}
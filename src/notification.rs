@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+use std::thread::spawn;
+
+use notify_rust::{Notification, Timeout};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Connected,
+    Disconnected,
+    // device access and config errors
+    Error,
+}
+
+// id of the on screen display, each flash replacing the previous one rather than piling up
+const FLASH_ID: u32 = 0x4d4d4f37;
+const FLASH_TIMEOUT_MILLIS: u32 = 1500;
+
+// set once from the driver settings, everything is shown until then
+static SILENCED: OnceLock<Vec<NotificationKind>> = OnceLock::new();
+
+pub fn silence(silenced_vec: Vec<NotificationKind>) {
+    SILENCED.set(silenced_vec).ok();
+}
+
+// shown from its own thread, as the notification server can be slow to answer,
+// errors are ignored as there might be no desktop session
+pub fn notify(kind: NotificationKind, body: String) {
+    if SILENCED
+        .get()
+        .map(|silenced_vec| silenced_vec.contains(&kind))
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    spawn(move || {
+        Notification::new()
+            .appname("Mad Rust")
+            .summary("Mad Rust MMO7")
+            .body(&body)
+            .icon("input-mouse")
+            .show()
+            .ok();
+    });
+}
+
+// short notification used as an on screen display, never silenced as it's enabled on its own
+pub fn flash(body: String) {
+    spawn(move || {
+        Notification::new()
+            .appname("Mad Rust")
+            .summary("Mad Rust MMO7")
+            .body(&body)
+            .icon("input-mouse")
+            .id(FLASH_ID)
+            .timeout(Timeout::Milliseconds(FLASH_TIMEOUT_MILLIS))
+            .show()
+            .ok();
+    });
+}
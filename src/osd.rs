@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::notification;
+use crate::websocket::{DriverEvent, EventSender};
+
+// last mode and profile of a device, the profile being none for the default one
+#[derive(Default)]
+struct DeviceLayer {
+    mode: u8,
+    shift: bool,
+    profile: Option<String>,
+}
+
+impl DeviceLayer {
+    fn describe(&self, serial_number: &str) -> String {
+        let profile = match &self.profile {
+            Some(profile) => format!("profile '{}'", profile),
+            None => "default profile".to_string(),
+        };
+
+        format!(
+            "{} : mode {}{}, {}",
+            serial_number,
+            self.mode + 1,
+            if self.shift { " shift" } else { "" },
+            profile
+        )
+    }
+}
+
+// flashes the active mode and profile of a mouse each time one of them changes,
+// as it's easy to lose track of the layer in use
+pub async fn run_osd(event_tx: EventSender) {
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        let mut device_layer_map: BTreeMap<String, DeviceLayer> = BTreeMap::new();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let serial_number = match event {
+                DriverEvent::Device {
+                    serial_number,
+                    connected,
+                } => {
+                    // the mode and profile are sent again by the next mapper
+                    if !connected {
+                        device_layer_map.remove(&serial_number);
+                    }
+
+                    continue;
+                }
+                DriverEvent::Mode {
+                    serial_number,
+                    mode,
+                    shift,
+                } => {
+                    let device_layer = device_layer_map.entry(serial_number.clone()).or_default();
+
+                    device_layer.mode = mode;
+                    device_layer.shift = shift;
+                    serial_number
+                }
+                DriverEvent::Profile {
+                    serial_number,
+                    profile,
+                } => {
+                    device_layer_map
+                        .entry(serial_number.clone())
+                        .or_default()
+                        .profile = profile;
+                    serial_number
+                }
+                DriverEvent::Button { .. } => continue,
+            };
+
+            if let Some(device_layer) = device_layer_map.get(&serial_number) {
+                notification::flash(device_layer.describe(&serial_number));
+            }
+        }
+    });
+}
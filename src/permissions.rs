@@ -0,0 +1,147 @@
+use std::ffi::CString;
+use std::fs;
+use std::process::Command;
+
+use crate::model::{self, DEVICE_MODELS};
+
+use rusb::{Context, Device, UsbContext};
+
+const RULES_PATH: &str = "/etc/udev/rules.d/70-mad-rust-mmo7.rules";
+// acl entry tag of a named user, and read write permissions
+const ACL_USER: u16 = 0x02;
+const ACL_READ_WRITE: u16 = 0b110;
+
+// gives the user of the active session access to every supported mouse
+fn rules() -> String {
+    let mut rules = String::from("# written by mad-rust-mmo7-driver --setup-permissions\n");
+
+    for device_model in DEVICE_MODELS {
+        for (vendor_id, product_id) in device_model.ids {
+            rules.push_str(&format!(
+                "SUBSYSTEM==\"usb\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0660\", TAG+=\"uaccess\"\n",
+                vendor_id, product_id
+            ));
+        }
+    }
+
+    rules
+}
+
+fn udevadm(args: &[&str]) -> bool {
+    match Command::new("udevadm").args(args).status() {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            println!("udevadm {} failed : {}", args.join(" "), status);
+            false
+        }
+        Err(err) => {
+            println!("udevadm {} failed : {}", args.join(" "), err);
+            false
+        }
+    }
+}
+
+// the uaccess tag grants the access through an acl entry of the session user
+fn has_acl_access(path: &str, uid: u32) -> bool {
+    let (path, name) = match (CString::new(path), CString::new("system.posix_acl_access")) {
+        (Ok(path), Ok(name)) => (path, name),
+        _ => return false,
+    };
+    let mut data = [0u8; 256];
+    let length = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            data.as_mut_ptr() as *mut libc::c_void,
+            data.len(),
+        )
+    };
+
+    if length < 4 {
+        return false;
+    }
+
+    // a version header, then entries of tag, permissions and id
+    data[4..length as usize].chunks_exact(8).any(|entry| {
+        u16::from_le_bytes([entry[0], entry[1]]) == ACL_USER
+            && u16::from_le_bytes([entry[2], entry[3]]) & ACL_READ_WRITE == ACL_READ_WRITE
+            && u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]) == uid
+    })
+}
+
+// checked for the user who ran sudo when there's one, as root can always open the devices
+fn can_open(device: &Device<Context>, sudo_uid: Option<u32>) -> bool {
+    match sudo_uid {
+        Some(uid) => has_acl_access(
+            &format!(
+                "/dev/bus/usb/{:03}/{:03}",
+                device.bus_number(),
+                device.address()
+            ),
+            uid,
+        ),
+        None => !matches!(device.open(), Err(rusb::Error::Access)),
+    }
+}
+
+// names of the connected mice which can't be opened
+fn denied_devices(sudo_uid: Option<u32>) -> Vec<&'static str> {
+    let mut denied_vec = vec![];
+
+    if let Ok(devices) = Context::new().and_then(|context| context.devices()) {
+        for device in devices.iter() {
+            if let Ok(device_descriptor) = device.device_descriptor() {
+                if let Some(device_model) = model::find(
+                    device_descriptor.vendor_id(),
+                    device_descriptor.product_id(),
+                ) {
+                    if !can_open(&device, sudo_uid) {
+                        denied_vec.push(device_model.name);
+                    }
+                }
+            }
+        }
+    }
+
+    denied_vec
+}
+
+// writes the udev rule and reloads udev, which needs root, then checks the mice can be opened
+pub fn setup_permissions() -> bool {
+    let rules = rules();
+
+    if fs::read_to_string(RULES_PATH).ok().as_deref() != Some(rules.as_str()) {
+        if let Err(err) = fs::write(RULES_PATH, &rules) {
+            println!(
+                "{} not written : {}, run this command with sudo",
+                RULES_PATH, err
+            );
+            return false;
+        }
+
+        println!("{} written", RULES_PATH);
+
+        if !(udevadm(&["control", "--reload-rules"])
+            && udevadm(&["trigger", "--subsystem-match=usb", "--action=add"])
+            && udevadm(&["settle"]))
+        {
+            return false;
+        }
+    }
+
+    let sudo_uid = std::env::var("SUDO_UID")
+        .ok()
+        .and_then(|uid| uid.parse().ok());
+    let denied_vec = denied_devices(sudo_uid);
+
+    if denied_vec.is_empty() {
+        println!("Permissions set up");
+        true
+    } else {
+        println!(
+            "Permissions set up, but {} can't be opened yet, replugging it or logging in again might help",
+            denied_vec.join(", ")
+        );
+        false
+    }
+}
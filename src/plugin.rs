@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread::spawn;
+
+use util::thread::MutexTrait;
+
+use crate::exec;
+
+// receives the arguments of the `{plugin:name,arguments}` tokens, played by the emulation worker
+pub trait Plugin: Send {
+    fn handle(&mut self, arguments: &str);
+}
+
+// none until loaded
+static PLUGINS: OnceLock<Mutex<HashMap<String, Box<dyn Plugin>>>> = OnceLock::new();
+
+// executables installed by the user, named after their file
+fn plugin_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("mad-rust").join("mmo7_plugins"))
+}
+
+// the plugins built into the driver are registered here too, along with the executables,
+// loaded after the exec allowlist is configured
+pub fn load() {
+    let mut plugin_map: HashMap<String, Box<dyn Plugin>> = HashMap::new();
+
+    if let Some(entries) = plugin_dir().and_then(|plugin_dir| fs::read_dir(plugin_dir).ok()) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let name = path
+                .file_stem()
+                .map(|file_stem| file_stem.to_string_lossy().to_string());
+
+            let name = match name.filter(|_| is_executable(&path)) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // as shared profiles can use any plugin, like the programs of the exec tokens
+            if !exec::is_allowed(&path.to_string_lossy()) {
+                println!(
+                    "Plugin {} not loaded : {} isn't in the exec allowlist",
+                    name,
+                    path.display()
+                );
+                continue;
+            }
+
+            plugin_map.insert(name, Box::new(ProcessPlugin::new(path)));
+        }
+    }
+
+    if !plugin_map.is_empty() {
+        let mut name_vec: Vec<&str> = plugin_map.keys().map(String::as_str).collect();
+
+        name_vec.sort();
+        println!("Plugins loaded : {}", name_vec.join(", "));
+    }
+
+    PLUGINS.set(Mutex::new(plugin_map)).ok();
+}
+
+// unknown plugins are only reported once the plugins are loaded
+pub fn exists(name: &str) -> bool {
+    PLUGINS
+        .get()
+        .map(|plugin_map| plugin_map.lock_poisoned().contains_key(name))
+        .unwrap_or(true)
+}
+
+pub fn send(name: &str, arguments: &str) {
+    let mut plugin_map = match PLUGINS.get() {
+        Some(plugin_map) => plugin_map.lock_poisoned(),
+        None => return,
+    };
+
+    match plugin_map.get_mut(name) {
+        Some(plugin) => plugin.handle(arguments),
+        None => println!("Plugin {} not found", name),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("exe"))
+            .unwrap_or(false)
+}
+
+// started on its first token and kept running, each token being written as a line
+// on its standard input, so it can keep a connection open to the program it drives
+struct ProcessPlugin {
+    line_tx: Sender<String>,
+}
+
+impl ProcessPlugin {
+    // the lines are written by a thread of its own, so a plugin not reading its input
+    // never blocks the emulation worker or the other plugins
+    fn new(path: PathBuf) -> Self {
+        let (line_tx, line_rx) = channel::<String>();
+
+        spawn(move || {
+            let mut child: Option<(Child, ChildStdin)> = None;
+
+            for line in line_rx {
+                // started again once exited
+                if let Some((process, _)) = &mut child {
+                    if !matches!(process.try_wait(), Ok(None)) {
+                        child = None;
+                    }
+                }
+
+                if child.is_none() {
+                    child = start(&path);
+                }
+
+                if let Some((_, stdin)) = &mut child {
+                    if writeln!(stdin, "{}", line)
+                        .and_then(|_| stdin.flush())
+                        .is_err()
+                    {
+                        child = None;
+                    }
+                }
+            }
+        });
+
+        Self { line_tx }
+    }
+}
+
+fn start(path: &Path) -> Option<(Child, ChildStdin)> {
+    let mut child = match Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            println!("Plugin {} not started : {}", path.display(), err);
+            return None;
+        }
+    };
+    let stdin = child.stdin.take()?;
+
+    Some((child, stdin))
+}
+
+impl Plugin for ProcessPlugin {
+    fn handle(&mut self, arguments: &str) {
+        self.line_tx.send(arguments.replace(['\r', '\n'], " ")).ok();
+    }
+}
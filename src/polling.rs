@@ -0,0 +1,108 @@
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use rusb::{DeviceHandle, UsbContext};
+
+use crate::hid::set_feature_report;
+use crate::model::DeviceModel;
+
+// in hz
+pub const POLLING_RATES: [u16; 4] = [125, 250, 500, 1000];
+// in milliseconds
+pub const READ_TIMEOUT_RANGE: RangeInclusive<u64> = 10..=1000;
+pub const TICK_INTERVAL_RANGE: RangeInclusive<u64> = 1..=100;
+// used while no binding is held or repeated, only delaying the config changes
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(100);
+// used when neither the tick interval nor the polling rate is set
+const DEFAULT_TICK_INTERVAL: Duration = Duration::from_millis(25);
+// reports missed before the mapper is ticked without one
+const MISSED_REPORTS: u64 = 3;
+
+pub fn read_timeout(read_timeout: Option<u64>) -> Duration {
+    match read_timeout {
+        Some(read_timeout) => Duration::from_millis(
+            read_timeout.clamp(*READ_TIMEOUT_RANGE.start(), *READ_TIMEOUT_RANGE.end()),
+        ),
+        None => DEFAULT_READ_TIMEOUT,
+    }
+}
+
+// the held and repeated bindings are played on each tick,
+// a few report intervals by default keeps them as precise as the reports
+pub fn tick_interval(tick_interval: Option<u64>, polling_rate: Option<u16>) -> Duration {
+    match (tick_interval, polling_rate) {
+        (Some(tick_interval), _) => Duration::from_millis(
+            tick_interval.clamp(*TICK_INTERVAL_RANGE.start(), *TICK_INTERVAL_RANGE.end()),
+        ),
+        (None, Some(polling_rate)) if polling_rate > 0 => {
+            Duration::from_micros(MISSED_REPORTS * 1_000_000 / polling_rate as u64)
+        }
+        _ => DEFAULT_TICK_INTERVAL,
+    }
+}
+
+// monotonic tick of the held and repeated bindings, whatever wakes the device thread up
+#[derive(Default)]
+pub struct Ticker {
+    next_tick: Option<Instant>,
+}
+
+impl Ticker {
+    // stopped while nothing has to be played, true when a tick is due
+    pub fn update(&mut self, tick_interval: Duration, is_running: bool) -> bool {
+        if !is_running {
+            self.next_tick = None;
+            return false;
+        }
+
+        let now = Instant::now();
+        let next_tick = *self.next_tick.get_or_insert(now + tick_interval);
+
+        if now < next_tick {
+            return false;
+        }
+
+        // scheduled from the previous tick so the cadence doesn't drift,
+        // the missed ticks being skipped
+        let following_tick = next_tick + tick_interval;
+
+        self.next_tick = Some(if following_tick > now {
+            following_tick
+        } else {
+            now + tick_interval
+        });
+
+        true
+    }
+
+    // until the next tick, or the read timeout while stopped
+    pub fn timeout(&self, read_timeout: Duration) -> Duration {
+        match self.next_tick {
+            Some(next_tick) => next_tick
+                .saturating_duration_since(Instant::now())
+                .min(read_timeout),
+            None => read_timeout,
+        }
+    }
+}
+
+// feature report : report id, report interval in milliseconds
+pub fn set_polling_rate<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    iface: u8,
+    polling_rate: u16,
+) -> rusb::Result<()> {
+    let report_id = match device_model.polling_rate_report_id {
+        Some(report_id) => report_id,
+        None => return Ok(()),
+    };
+
+    set_feature_report(
+        device_handle,
+        iface,
+        &[report_id, (1000 / polling_rate.max(1)) as u8],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+// receives a message each time the system resumes from sleep, the device handles being stale
+pub fn watch_resume() -> UnboundedReceiver<()> {
+    let (resume_tx, resume_rx) = unbounded_channel();
+
+    platform::watch_resume(resume_tx);
+
+    resume_rx
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use futures_util::StreamExt;
+    use tokio::sync::mpsc::UnboundedSender;
+    use zbus::{Connection, Proxy};
+
+    // logind sends `PrepareForSleep` with true before sleeping, and false once resumed
+    pub fn watch_resume(resume_tx: UnboundedSender<()>) {
+        tokio::spawn(async move {
+            if let Err(err) = receive_resume(&resume_tx).await {
+                println!("Resume not watched : {}", err);
+            }
+        });
+    }
+
+    async fn receive_resume(resume_tx: &UnboundedSender<()>) -> zbus::Result<()> {
+        let connection = Connection::system().await?;
+        let proxy = Proxy::new(
+            &connection,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )
+        .await?;
+        let mut signal_stream = proxy.receive_signal("PrepareForSleep").await?;
+
+        while let Some(message) = signal_stream.next().await {
+            if let Ok(false) = message.body::<bool>() {
+                resume_tx.send(()).ok();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::c_void;
+    use std::ptr::null_mut;
+    use std::sync::OnceLock;
+
+    use tokio::sync::mpsc::UnboundedSender;
+    use windows_sys::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+    use windows_sys::Win32::System::Power::{
+        PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC,
+    };
+
+    static RESUME_TX: OnceLock<UnboundedSender<()>> = OnceLock::new();
+
+    unsafe extern "system" fn power_callback(
+        _context: *const c_void,
+        event_type: u32,
+        _setting: *const c_void,
+    ) -> u32 {
+        if event_type == PBT_APMRESUMEAUTOMATIC {
+            if let Some(resume_tx) = RESUME_TX.get() {
+                resume_tx.send(()).ok();
+            }
+        }
+
+        ERROR_SUCCESS
+    }
+
+    // the registration is kept for the lifetime of the driver
+    pub fn watch_resume(resume_tx: UnboundedSender<()>) {
+        if RESUME_TX.set(resume_tx).is_err() {
+            return;
+        }
+
+        let parameters = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(power_callback),
+            Context: null_mut(),
+        }));
+        let mut registration = null_mut();
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                parameters as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as HANDLE,
+                &mut registration,
+            )
+        };
+
+        if result != ERROR_SUCCESS {
+            println!("Resume not watched : error {}", result);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use tokio::sync::mpsc::UnboundedSender;
+
+    pub fn watch_resume(_resume_tx: UnboundedSender<()>) {}
+}
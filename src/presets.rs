@@ -0,0 +1,56 @@
+use crate::command::PresetInfo;
+use crate::ButtonConfigs;
+
+// bindings shipped with the driver, applied to a device as a profile
+pub struct Preset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // toml profile, with the same fields as the profiles of the config file
+    data: &'static str,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        id: "generic_mmo",
+        name: "Generic MMO",
+        description: "A hotbar of twelve keys on the side buttons, with ctrl and alt in the second and third modes",
+        data: include_str!("../presets/generic_mmo.toml"),
+    },
+    Preset {
+        id: "wow_healer",
+        name: "WoW healer",
+        description: "Heals on the thumb buttons and utility on the hat, for mouseover casting on the group frames",
+        data: include_str!("../presets/wow_healer.toml"),
+    },
+    Preset {
+        id: "ffxiv_hotbars",
+        name: "FFXIV hotbars",
+        description: "The first three hotbars on the modes, using their default keys",
+        data: include_str!("../presets/ffxiv_hotbars.toml"),
+    },
+    Preset {
+        id: "productivity",
+        name: "Productivity",
+        description: "Copy, paste and undo on the side buttons, browser tabs on the hat and horizontal scroll on the thumb wheel",
+        data: include_str!("../presets/productivity.toml"),
+    },
+];
+
+impl Preset {
+    pub fn button_configs(&self) -> Result<ButtonConfigs, String> {
+        toml::from_str(self.data).map_err(|err| format!("invalid preset {} : {}", self.id, err))
+    }
+
+    pub fn info(&self) -> PresetInfo {
+        PresetInfo::new(
+            self.id.to_string(),
+            self.name.to_string(),
+            self.description.to_string(),
+        )
+    }
+}
+
+pub fn find(id: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.id == id)
+}
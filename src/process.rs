@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use tokio::time::{interval, MissedTickBehavior};
+use util::config::ConfigManager;
+
+use crate::snapshot::ProfileSwitchSender;
+use crate::tokenizer::ProfileSwitch;
+use crate::{MouseConfig, MousesConfig};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct ProcessRule {
+    // process name, with or without extension, case insensitive
+    pub process: String,
+    pub profile: String,
+}
+
+// lowercase and without extension, so `Wow.exe` under wine matches `wow`
+fn normalize(process: &str) -> String {
+    let process = process.trim().to_lowercase();
+
+    process.strip_suffix(".exe").unwrap_or(&process).to_string()
+}
+
+// profile of the first rule whose process is running, the rules being in priority order
+fn matching_profile(mouse_config: &MouseConfig, process_set: &HashSet<String>) -> Option<String> {
+    mouse_config
+        .process_rules
+        .iter()
+        .find(|process_rule| {
+            process_set.contains(&normalize(&process_rule.process))
+                && mouse_config.profiles.contains_key(&process_rule.profile)
+        })
+        .map(|process_rule| process_rule.profile.clone())
+}
+
+// switches the profile of the devices when the result of their process rules changes,
+// through the same path as the profile tokens, so a profile switched by hand stays active
+pub async fn watch_processes(
+    mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    profile_switch_tx: ProfileSwitchSender,
+) {
+    tokio::spawn(async move {
+        let mut interval_ = interval(POLL_INTERVAL);
+        let mut process_profile_map: HashMap<String, Option<String>> = HashMap::new();
+
+        interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            interval_.tick().await;
+
+            let has_rules = mouses_config_mutex
+                .lock()
+                .await
+                .config
+                .values()
+                .any(|mouse_config| !mouse_config.process_rules.is_empty());
+
+            if !has_rules && process_profile_map.is_empty() {
+                continue;
+            }
+
+            let process_set = match spawn_blocking(running_processes).await {
+                Ok(process_set) => process_set,
+                Err(_) => continue,
+            };
+            let mouses_config = mouses_config_mutex.lock().await;
+
+            process_profile_map.retain(|serial_number, _| {
+                mouses_config
+                    .config
+                    .get(serial_number)
+                    .map(|mouse_config| !mouse_config.process_rules.is_empty())
+                    .unwrap_or(false)
+            });
+
+            for (serial_number, mouse_config) in mouses_config.config.iter() {
+                if mouse_config.process_rules.is_empty() {
+                    continue;
+                }
+
+                let process_profile = matching_profile(mouse_config, &process_set);
+                let last_process_profile = process_profile_map
+                    .insert(serial_number.clone(), process_profile.clone())
+                    .flatten();
+
+                if process_profile == last_process_profile {
+                    continue;
+                }
+
+                // once the process exits, the fallback profile takes over,
+                // then the profile activated by the user
+                let profile_switch = match process_profile.or_else(|| {
+                    mouse_config
+                        .process_fallback_profile
+                        .clone()
+                        .or_else(|| mouse_config.selected_profile.clone())
+                }) {
+                    Some(profile) => ProfileSwitch::Named(profile),
+                    None => ProfileSwitch::Default,
+                };
+
+                profile_switch_tx
+                    .send((serial_number.clone(), profile_switch))
+                    .ok();
+            }
+        }
+    });
+}
+
+use platform::running_processes;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashSet;
+    use std::fs;
+
+    use super::normalize;
+
+    // the command line is read before the truncated comm name, so wine games show their
+    // executable name, like `C:\Games\WoW\Wow.exe`
+    pub fn running_processes() -> HashSet<String> {
+        let mut process_set = HashSet::new();
+        let entries = match fs::read_dir("/proc") {
+            Ok(entries) => entries,
+            Err(_) => return process_set,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if !entry
+                .file_name()
+                .to_string_lossy()
+                .bytes()
+                .all(|byte| byte.is_ascii_digit())
+            {
+                continue;
+            }
+
+            if let Ok(cmdline) = fs::read(path.join("cmdline")) {
+                let program = String::from_utf8_lossy(
+                    cmdline.split(|byte| *byte == 0).next().unwrap_or_default(),
+                )
+                .to_string();
+
+                if let Some(name) = program
+                    .rsplit(['/', '\\'])
+                    .next()
+                    .filter(|name| !name.is_empty())
+                {
+                    process_set.insert(normalize(name));
+                }
+            }
+            if let Ok(comm) = fs::read_to_string(path.join("comm")) {
+                process_set.insert(normalize(&comm));
+            }
+        }
+
+        process_set
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::collections::HashSet;
+    use std::mem::size_of;
+
+    use super::normalize;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    pub fn running_processes() -> HashSet<String> {
+        let mut process_set = HashSet::new();
+
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+
+            if snapshot == INVALID_HANDLE_VALUE {
+                return process_set;
+            }
+
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+
+            entry.dwSize = size_of::<PROCESSENTRY32W>() as u32;
+
+            let mut has_entry = Process32FirstW(snapshot, &mut entry) != 0;
+
+            while has_entry {
+                let length = entry
+                    .szExeFile
+                    .iter()
+                    .position(|char| *char == 0)
+                    .unwrap_or(entry.szExeFile.len());
+
+                process_set.insert(normalize(&String::from_utf16_lossy(
+                    &entry.szExeFile[..length],
+                )));
+                has_entry = Process32NextW(snapshot, &mut entry) != 0;
+            }
+
+            CloseHandle(snapshot);
+        }
+
+        process_set
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::collections::HashSet;
+
+    pub fn running_processes() -> HashSet<String> {
+        HashSet::new()
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_watcher::profiles_path;
+use crate::validation::profile_errors;
+use crate::{check_profile_name, ButtonConfigs, MouseConfig};
+
+// standalone profile, written as toml when the file has a toml extension and as json otherwise
+#[derive(Deserialize, Serialize)]
+struct ProfileFile {
+    // name of the device model the bindings were made for
+    model: String,
+    profile: ButtonConfigs,
+}
+
+#[derive(Debug)]
+pub enum ProfileFileError {
+    Io(io::Error),
+    Format(String),
+    UnknownProfile(String),
+    WrongModel(String),
+    // the profile name or the bindings, as they would be refused from the client too
+    Invalid(String),
+    // anything but the name of a file of the profile file directory
+    InvalidFileName(String),
+}
+
+impl fmt::Display for ProfileFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Format(err) => write!(f, "invalid profile file : {}", err),
+            Self::UnknownProfile(profile) => write!(f, "unknown profile {}", profile),
+            Self::WrongModel(model) => write!(f, "profile made for the {}", model),
+            Self::Invalid(err) => write!(f, "{}", err),
+            Self::InvalidFileName(file_name) => write!(f, "invalid file name {}", file_name),
+        }
+    }
+}
+
+impl From<io::Error> for ProfileFileError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+// the files are kept next to the profiles file, as the driver can run as root
+fn profile_file_dir() -> Option<PathBuf> {
+    Some(profiles_path()?.parent()?.join("mmo7_profile_files"))
+}
+
+// a plain file name, resolved in the profile file directory, which a symlink can't lead out of
+fn profile_file_path(file_name: &str) -> Result<PathBuf, ProfileFileError> {
+    let invalid = || ProfileFileError::InvalidFileName(file_name.to_string());
+    let mut component_iter = Path::new(file_name).components();
+
+    if !matches!(
+        (component_iter.next(), component_iter.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(invalid());
+    }
+
+    let dir = profile_file_dir().ok_or_else(invalid)?;
+
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(file_name);
+
+    if fs::symlink_metadata(&path).is_ok()
+        && !fs::canonicalize(&path)
+            .map(|target| target.starts_with(fs::canonicalize(&dir).unwrap_or(dir)))
+            .unwrap_or(false)
+    {
+        return Err(invalid());
+    }
+
+    Ok(path)
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+// exports a named profile, or the default one when none is given
+pub fn export(
+    mouse_config: &MouseConfig,
+    profile: Option<&str>,
+    file_name: &str,
+) -> Result<(), ProfileFileError> {
+    let path = profile_file_path(file_name)?;
+    let button_configs = match profile {
+        Some(profile) => mouse_config
+            .profiles
+            .get(profile)
+            .ok_or_else(|| ProfileFileError::UnknownProfile(profile.to_string()))?,
+        None => &mouse_config.button_configs,
+    };
+    let profile_file = ProfileFile {
+        model: mouse_config.device_model().name.to_string(),
+        profile: button_configs.clone(),
+    };
+    let data = if is_toml(&path) {
+        toml::to_string_pretty(&profile_file).map_err(|err| err.to_string())
+    } else {
+        serde_json::to_string_pretty(&profile_file).map_err(|err| err.to_string())
+    }
+    .map_err(ProfileFileError::Format)?;
+
+    fs::write(path, data)?;
+
+    Ok(())
+}
+
+// imports the file as a named profile, an existing profile with the same name is only replaced
+// when overwriting, otherwise a number is appended to the name, returns the name used
+pub fn import(
+    mouse_config: &mut MouseConfig,
+    file_name: &str,
+    profile: &str,
+    overwrite: bool,
+) -> Result<String, ProfileFileError> {
+    check_profile_name(profile).map_err(ProfileFileError::Invalid)?;
+
+    let path = profile_file_path(file_name)?;
+    let data = fs::read_to_string(&path)?;
+    let profile_file: ProfileFile = if is_toml(&path) {
+        toml::from_str(&data).map_err(|err| err.to_string())
+    } else {
+        serde_json::from_str(&data).map_err(|err| err.to_string())
+    }
+    .map_err(ProfileFileError::Format)?;
+
+    if profile_file.model != mouse_config.device_model().name {
+        return Err(ProfileFileError::WrongModel(profile_file.model));
+    }
+
+    let error_vec = profile_errors(profile, profile, &profile_file.profile, mouse_config);
+
+    if !error_vec.is_empty() {
+        let message_vec: Vec<String> = error_vec.iter().map(ToString::to_string).collect();
+
+        return Err(ProfileFileError::Invalid(message_vec.join(", ")));
+    }
+
+    let mut profile_name = profile.to_string();
+
+    if !overwrite {
+        let mut index = 2;
+
+        while mouse_config.profiles.contains_key(&profile_name) {
+            profile_name = format!("{} ({})", profile, index);
+            index += 1;
+        }
+    }
+
+    mouse_config
+        .profiles
+        .insert(profile_name.clone(), profile_file.profile);
+
+    Ok(profile_name)
+}
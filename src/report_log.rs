@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::model::DeviceModel;
+
+// last reports kept per device
+const REPORT_LOG_CAPACITY: usize = 512;
+
+// set by the `--debug-reports` flag, the reports aren't logged otherwise
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+// reports read from a device, printed and kept in a ring buffer while enabled
+pub struct ReportLog {
+    device_model: &'static DeviceModel,
+    start: Instant,
+    line_deque: VecDeque<String>,
+}
+
+impl ReportLog {
+    pub fn new(device_model: &'static DeviceModel) -> Self {
+        Self {
+            device_model,
+            start: Instant::now(),
+            line_deque: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, serial_number: &str, buffer: &[u8]) {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let line = self.format(buffer);
+
+        println!("{} report : {}", serial_number, line);
+
+        if self.line_deque.len() == REPORT_LOG_CAPACITY {
+            self.line_deque.pop_front();
+        }
+
+        self.line_deque.push_back(line);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.line_deque.iter().cloned().collect()
+    }
+
+    // the time and bytes can be replayed with `--simulate`, the decoded report follows the `#`
+    fn format(&self, buffer: &[u8]) -> String {
+        let report = &self.device_model.report;
+        let hex_vec: Vec<String> = buffer.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let modes = buffer[report.mode] & 0b111;
+        let mut decoded_vec = vec![format!("mode {}", (modes & 0b11) + 1)];
+
+        if modes & 0b100 > 0 {
+            decoded_vec.push("shift".to_string());
+        }
+
+        for (name, value) in [
+            ("x", buffer[report.x] as i8),
+            ("y", buffer[report.y] as i8),
+            ("wheel", buffer[report.wheel] as i8),
+        ] {
+            if value != 0 {
+                decoded_vec.push(format!("{} {}", name, value));
+            }
+        }
+
+        for (name, bit) in [
+            ("left", &report.left),
+            ("right", &report.right),
+            ("middle", &report.middle),
+        ] {
+            if bit.is_set(buffer) {
+                decoded_vec.push(name.to_string());
+            }
+        }
+
+        for button in self.device_model.buttons {
+            if button.bit.is_set(buffer) {
+                decoded_vec.push(button.key.to_string());
+            }
+        }
+
+        format!(
+            "{} {} # {}",
+            self.start.elapsed().as_millis(),
+            hex_vec.join(" "),
+            decoded_vec.join(", ")
+        )
+    }
+}
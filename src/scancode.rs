@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+// pc scancodes of the set 1, which are also the evdev codes of these keys
+pub const LEFT_SHIFT: u16 = 42;
+
+// how the characters of the bindings are typed
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyMode {
+    // the character itself, through the key typing it in the current layout
+    #[default]
+    Layout,
+    // the key at the position of the character on a us keyboard, so `{ctrl+z}` or `wasd`
+    // stay on the same keys with an azerty or a qwertz layout
+    Scancode,
+}
+
+// scancode and shift state of the key typing the character on a us keyboard,
+// other characters can't be typed
+pub fn char_to_scancode(char: char) -> Option<(u16, bool)> {
+    const LETTERS: [u16; 26] = [
+        30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47, 17,
+        45, 21, 44,
+    ];
+
+    let key = match char {
+        'a'..='z' => (LETTERS[char as usize - 'a' as usize], false),
+        'A'..='Z' => (LETTERS[char as usize - 'A' as usize], true),
+        '1'..='9' => (char as u16 - '1' as u16 + 2, false),
+        '0' => (11, false),
+        '!' => (2, true),
+        '@' => (3, true),
+        '#' => (4, true),
+        '$' => (5, true),
+        '%' => (6, true),
+        '^' => (7, true),
+        '&' => (8, true),
+        '*' => (9, true),
+        '(' => (10, true),
+        ')' => (11, true),
+        '-' => (12, false),
+        '_' => (12, true),
+        '=' => (13, false),
+        '+' => (13, true),
+        '\t' => (15, false),
+        '[' => (26, false),
+        '{' => (26, true),
+        ']' => (27, false),
+        '}' => (27, true),
+        '\n' => (28, false),
+        ';' => (39, false),
+        ':' => (39, true),
+        '\'' => (40, false),
+        '"' => (40, true),
+        '`' => (41, false),
+        '~' => (41, true),
+        '\\' => (43, false),
+        '|' => (43, true),
+        ',' => (51, false),
+        '<' => (51, true),
+        '.' => (52, false),
+        '>' => (52, true),
+        '/' => (53, false),
+        '?' => (53, true),
+        ' ' => (57, false),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+// presses the key of the character, with shift when needed
+pub fn type_char(char: char, mut key: impl FnMut(u16, bool)) {
+    if let Some((code, shift)) = char_to_scancode(char) {
+        if shift {
+            key(LEFT_SHIFT, true);
+        }
+
+        key(code, true);
+        key(code, false);
+
+        if shift {
+            key(LEFT_SHIFT, false);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::tokenizer::{tokenize, StateToken, TOGGLE_FLAG};
+
+use hashbrown::HashMap;
+
+// a binding starting with this prefix is evaluated at press time instead of at config load
+pub const SCRIPT_PREFIX: &str = "{script}";
+
+#[derive(Debug, Clone, Default)]
+pub struct Variables(HashMap<String, String>);
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.0.get(name)
+    }
+
+    pub fn set(&mut self, name: String, value: String) {
+        self.0.insert(name, value);
+    }
+
+    pub fn add(&mut self, name: String, value: i64) {
+        let current = self
+            .0
+            .get(&name)
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(0);
+
+        self.0
+            .insert(name, current.saturating_add(value).to_string());
+    }
+}
+
+pub struct ScriptContext<'a> {
+    pub serial_number: &'a str,
+    pub mode: u8,
+    pub shift: bool,
+}
+
+impl ScriptContext<'_> {
+    fn builtin(&self, name: &str) -> Option<String> {
+        match name {
+            "serial" => Some(self.serial_number.to_string()),
+            "mode" => Some((self.mode + 1).to_string()),
+            "shift" => Some(self.shift.to_string()),
+            "time" => Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0)
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+// only produce text for the tokenizer, scripts have no access to the shell or the file system,
+// the result of an evaluation being kept by the mapper of the device which pressed it
+#[derive(Debug, Clone)]
+pub struct Script {
+    source: String,
+    toggle: bool,
+}
+
+impl Script {
+    pub fn new(source: String) -> Self {
+        Self {
+            toggle: source.contains(TOGGLE_FLAG),
+            source,
+        }
+    }
+
+    pub fn is_toggle(&self) -> bool {
+        self.toggle
+    }
+
+    pub fn evaluate(&self, variables: &mut Variables, context: &ScriptContext) -> Arc<StateToken> {
+        Arc::new(tokenize(interpret(&self.source, variables, context)))
+    }
+}
+
+// runs the statements and inserts the variables of the text
+pub fn interpret(source: &str, variables: &mut Variables, context: &ScriptContext) -> String {
+    let mut output = String::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '{' => {
+                let rest: String = chars.clone().collect();
+
+                if let Some(end) = rest.find('}') {
+                    if run_statement(&rest[..end], variables, context) {
+                        for _ in rest[..=end].chars() {
+                            chars.next();
+                        }
+                        continue;
+                    }
+                }
+
+                output.push(char);
+            }
+            '$' => match chars.peek() {
+                Some('$') => {
+                    chars.next();
+                    output.push('$');
+                }
+                Some('{') => {
+                    chars.next();
+
+                    let name: String = chars.by_ref().take_while(|char| *char != '}').collect();
+
+                    output.push_str(&lookup(&name, variables, context));
+                }
+                _ => {
+                    let mut name = String::new();
+
+                    while let Some(char) = chars.peek() {
+                        if char.is_ascii_alphanumeric() || *char == '_' {
+                            name.push(*char);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if name.is_empty() {
+                        output.push('$');
+                    } else {
+                        output.push_str(&lookup(&name, variables, context));
+                    }
+                }
+            },
+            _ => output.push(char),
+        }
+    }
+
+    output
+}
+
+fn lookup(name: &str, variables: &Variables, context: &ScriptContext) -> String {
+    context
+        .builtin(name)
+        .or_else(|| variables.get(name).cloned())
+        .unwrap_or_default()
+}
+
+fn run_statement(statement: &str, variables: &mut Variables, context: &ScriptContext) -> bool {
+    if let Some(assignment) = statement.strip_prefix("set:") {
+        if let Some((name, value)) = assignment.split_once('=') {
+            let value = interpret(value, variables, context);
+
+            variables.set(name.trim().to_string(), value);
+
+            return true;
+        }
+    } else if let Some(name) = statement.strip_prefix("inc:") {
+        variables.add(name.trim().to_string(), 1);
+
+        return true;
+    } else if let Some(name) = statement.strip_prefix("dec:") {
+        variables.add(name.trim().to_string(), -1);
+
+        return true;
+    }
+
+    false
+}
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::script::ScriptContext;
+use crate::tokenizer::{input_token, parse_click_button, Token};
+
+use rhai::{Engine, Scope, AST};
+use util::thread::MutexTrait;
+
+// a binding starting with this prefix is a rhai script run on press and on release
+pub const RHAI_PREFIX: &str = "{rhai}";
+
+// bound the work a single press can do
+const MAX_OPERATIONS: u64 = 100_000;
+
+pub struct ScriptEngine {
+    engine: Engine,
+    token_vec: Arc<Mutex<Vec<Token>>>,
+    // per device variables, kept between runs
+    scope: Scope<'static>,
+}
+
+impl ScriptEngine {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        let token_vec = Arc::new(Mutex::new(vec![]));
+
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("press", move |input: &str| {
+                if let Some(token) = input_token(input, true) {
+                    token_vec.lock_poisoned().push(token);
+                }
+            });
+        }
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("release", move |input: &str| {
+                if let Some(token) = input_token(input, false) {
+                    token_vec.lock_poisoned().push(token);
+                }
+            });
+        }
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("click", move |input: &str| {
+                if let Some(button) = parse_click_button(input) {
+                    token_vec.lock_poisoned().push(Token::Click(button));
+                }
+            });
+        }
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("type", move |text: &str| {
+                token_vec
+                    .lock_poisoned()
+                    .push(Token::Unicode(text.to_string()));
+            });
+        }
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("move", move |x: i64, y: i64| {
+                token_vec
+                    .lock_poisoned()
+                    .push(Token::MoveRelative(x as i32, y as i32));
+            });
+        }
+        {
+            let token_vec = token_vec.clone();
+
+            engine.register_fn("delay", move |millis: i64| {
+                token_vec.lock_poisoned().push(Token::Delay(
+                    Duration::from_millis(millis.max(0) as u64),
+                    Duration::ZERO,
+                ));
+            });
+        }
+
+        Self {
+            engine,
+            token_vec,
+            scope: Scope::new(),
+        }
+    }
+
+    pub fn run(&mut self, ast: &AST, context: &ScriptContext, pressed: bool) -> Vec<Token> {
+        self.scope
+            .set_value("serial", context.serial_number.to_string());
+        self.scope.set_value("mode", context.mode as i64 + 1);
+        self.scope.set_value("shift", context.shift);
+        self.scope.set_value("pressed", pressed);
+
+        if let Err(error) = self.engine.run_ast_with_scope(&mut self.scope, ast) {
+            println!("rhai script error : {}", error);
+        }
+
+        // `let` pushes a new variable on each run, only keep the latest values
+        let mut scope = Scope::new();
+
+        for (name, _, value) in self.scope.iter() {
+            scope.set_value(name.to_string(), value);
+        }
+
+        self.scope = scope;
+        std::mem::take(&mut *self.token_vec.lock_poisoned())
+    }
+}
+
+// compile error of the script, without logging it
+pub fn check(source: &str) -> Option<String> {
+    Engine::new_raw()
+        .compile(source)
+        .err()
+        .map(|error| error.to_string())
+}
+
+pub fn compile(source: &str) -> Option<AST> {
+    match Engine::new_raw().compile(source) {
+        Ok(ast) => Some(ast),
+        Err(error) => {
+            println!("rhai script error : {}", error);
+
+            None
+        }
+    }
+}
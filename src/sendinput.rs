@@ -0,0 +1,321 @@
+use std::mem::size_of;
+
+use crate::backend::Backend;
+use crate::scancode::type_char;
+use crate::tokenizer::Button;
+
+use enigo::Key;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, VkKeyScanW, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT,
+    KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE,
+    MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
+    MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+    MOUSE_EVENT_FLAGS, VIRTUAL_KEY, VK_BACK, VK_CAPITAL, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
+    VK_ESCAPE, VK_F1, VK_F10, VK_F11, VK_F12, VK_F2, VK_F3, VK_F4, VK_F5, VK_F6, VK_F7, VK_F8,
+    VK_F9, VK_HOME, VK_LEFT, VK_LWIN, VK_MENU, VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SHIFT,
+    VK_SPACE, VK_TAB, VK_UP,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN, WHEEL_DELTA, XBUTTON1, XBUTTON2,
+};
+
+// inputs of a batch are sent with a single SendInput call,
+// so they can't be interleaved with real user input
+#[derive(Default)]
+pub struct SendInputBackend {
+    batch: Option<Vec<INPUT>>,
+}
+
+impl SendInputBackend {
+    fn push(&mut self, input_vec: &[INPUT]) {
+        match &mut self.batch {
+            Some(batch) => batch.extend_from_slice(input_vec),
+            None => send(input_vec),
+        }
+    }
+
+    fn key(&mut self, virtual_key: VIRTUAL_KEY, pressed: bool) {
+        let flags = if pressed { 0 } else { KEYEVENTF_KEYUP };
+
+        self.push(&[keyboard_input(virtual_key, 0, flags)]);
+    }
+
+    // the key at this position whatever the layout
+    fn scancode(&mut self, scancode: u16, pressed: bool) {
+        let flags = if pressed {
+            KEYEVENTF_SCANCODE
+        } else {
+            KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP
+        };
+
+        self.push(&[keyboard_input(0, scancode, flags)]);
+    }
+
+    fn mouse(&mut self, dx: i32, dy: i32, mouse_data: i32, flags: MOUSE_EVENT_FLAGS) {
+        self.push(&[INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT {
+                    dx,
+                    dy,
+                    mouseData: mouse_data,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }]);
+    }
+
+    // characters missing from the keyboard layout are typed as unicode
+    fn type_char(&mut self, char: char) {
+        match layout_key(char) {
+            Some((virtual_key, modifier_vec)) => {
+                for modifier in &modifier_vec {
+                    self.key(*modifier, true);
+                }
+
+                self.key(virtual_key, true);
+                self.key(virtual_key, false);
+
+                for modifier in modifier_vec.iter().rev() {
+                    self.key(*modifier, false);
+                }
+            }
+            None => self.type_unicode(char),
+        }
+    }
+
+    // the characters outside of the basic plane, like the emojis, are sent as two surrogates,
+    // both pressed before being released
+    fn type_unicode(&mut self, char: char) {
+        let mut utf16 = [0; 2];
+        let unit_slice = char.encode_utf16(&mut utf16);
+
+        for unit in unit_slice.iter() {
+            self.push(&[keyboard_input(0, *unit, KEYEVENTF_UNICODE)]);
+        }
+        for unit in unit_slice.iter() {
+            self.push(&[keyboard_input(
+                0,
+                *unit,
+                KEYEVENTF_UNICODE | KEYEVENTF_KEYUP,
+            )]);
+        }
+    }
+}
+
+fn send(input_vec: &[INPUT]) {
+    if !input_vec.is_empty() {
+        unsafe {
+            SendInput(
+                input_vec.len() as u32,
+                input_vec.as_ptr(),
+                size_of::<INPUT>() as i32,
+            );
+        }
+    }
+}
+
+fn keyboard_input(virtual_key: VIRTUAL_KEY, scan: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: virtual_key,
+                wScan: scan,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+// virtual key of the character in the current layout, with the modifiers needed to type it
+fn layout_key(char: char) -> Option<(VIRTUAL_KEY, Vec<VIRTUAL_KEY>)> {
+    let mut utf16 = [0; 2];
+
+    if char.encode_utf16(&mut utf16).len() != 1 {
+        return None;
+    }
+
+    let scan = unsafe { VkKeyScanW(utf16[0]) };
+
+    if scan == -1 {
+        return None;
+    }
+
+    let [virtual_key, state] = (scan as u16).to_le_bytes();
+    let modifier_vec = [(1, VK_SHIFT), (2, VK_CONTROL), (4, VK_MENU)]
+        .into_iter()
+        .filter(|(mask, _)| state & mask > 0)
+        .map(|(_, modifier)| modifier)
+        .collect();
+
+    Some((virtual_key as VIRTUAL_KEY, modifier_vec))
+}
+
+fn virtual_key(key: Key) -> Option<VIRTUAL_KEY> {
+    match key {
+        Key::Shift => Some(VK_SHIFT),
+        Key::Control => Some(VK_CONTROL),
+        Key::Alt => Some(VK_MENU),
+        Key::Meta => Some(VK_LWIN),
+        Key::Escape => Some(VK_ESCAPE),
+        Key::Backspace => Some(VK_BACK),
+        Key::Tab => Some(VK_TAB),
+        Key::Return => Some(VK_RETURN),
+        Key::Space => Some(VK_SPACE),
+        Key::CapsLock => Some(VK_CAPITAL),
+        Key::F1 => Some(VK_F1),
+        Key::F2 => Some(VK_F2),
+        Key::F3 => Some(VK_F3),
+        Key::F4 => Some(VK_F4),
+        Key::F5 => Some(VK_F5),
+        Key::F6 => Some(VK_F6),
+        Key::F7 => Some(VK_F7),
+        Key::F8 => Some(VK_F8),
+        Key::F9 => Some(VK_F9),
+        Key::F10 => Some(VK_F10),
+        Key::F11 => Some(VK_F11),
+        Key::F12 => Some(VK_F12),
+        Key::Home => Some(VK_HOME),
+        Key::End => Some(VK_END),
+        Key::PageUp => Some(VK_PRIOR),
+        Key::PageDown => Some(VK_NEXT),
+        Key::UpArrow => Some(VK_UP),
+        Key::DownArrow => Some(VK_DOWN),
+        Key::LeftArrow => Some(VK_LEFT),
+        Key::RightArrow => Some(VK_RIGHT),
+        Key::Delete => Some(VK_DELETE),
+        Key::Layout(char) => layout_key(char).map(|(virtual_key, _)| virtual_key),
+        _ => None,
+    }
+}
+
+// the down and up flags, with the mouse data telling the side buttons apart
+fn button_flags(button: Button) -> Option<(MOUSE_EVENT_FLAGS, MOUSE_EVENT_FLAGS, i32)> {
+    match button {
+        Button::Left => Some((MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, 0)),
+        Button::Right => Some((MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, 0)),
+        Button::Middle => Some((MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, 0)),
+        Button::Back => Some((MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON1 as i32)),
+        Button::Forward => Some((MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON2 as i32)),
+        _ => None,
+    }
+}
+
+impl Backend for SendInputBackend {
+    fn start_batch(&mut self) {
+        self.batch.get_or_insert_with(Vec::new);
+    }
+
+    fn end_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            send(&batch);
+        }
+    }
+
+    fn mouse_move_relative(&mut self, x: i32, y: i32) {
+        self.mouse(x, y, 0, MOUSEEVENTF_MOVE);
+    }
+
+    // absolute coordinates are normalized to 0..65535 over the primary screen
+    fn mouse_move_to(&mut self, x: i32, y: i32) {
+        let (width, height) =
+            unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) };
+        let normalize = |position: i32, size: i32| position * 65535 / (size - 1).max(1);
+
+        self.mouse(
+            normalize(x, width),
+            normalize(y, height),
+            0,
+            MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE,
+        );
+    }
+
+    fn mouse_down(&mut self, button: Button) {
+        if let Some((down, _, mouse_data)) = button_flags(button) {
+            self.mouse(0, 0, mouse_data, down);
+        }
+    }
+
+    fn mouse_up(&mut self, button: Button) {
+        if let Some((_, up, mouse_data)) = button_flags(button) {
+            self.mouse(0, 0, mouse_data, up);
+        }
+    }
+
+    fn mouse_click(&mut self, button: Button) {
+        if let Some((down, up, mouse_data)) = button_flags(button) {
+            self.mouse(0, 0, mouse_data, down);
+            self.mouse(0, 0, mouse_data, up);
+        }
+    }
+
+    // like enigo, positive lengths scroll right and down
+    fn mouse_scroll_x(&mut self, length: i32) {
+        self.mouse(0, 0, length * WHEEL_DELTA as i32, MOUSEEVENTF_HWHEEL);
+    }
+
+    fn mouse_scroll_y(&mut self, length: i32) {
+        self.mouse(0, 0, -length * WHEEL_DELTA as i32, MOUSEEVENTF_WHEEL);
+    }
+
+    // windows takes fractions of WHEEL_DELTA as they are, scrolling part of the lines
+    fn mouse_smooth_scroll_x(&mut self, delta: i32) {
+        self.mouse(0, 0, delta, MOUSEEVENTF_HWHEEL);
+    }
+
+    fn mouse_smooth_scroll_y(&mut self, delta: i32) {
+        self.mouse(0, 0, -delta, MOUSEEVENTF_WHEEL);
+    }
+
+    // the text is typed as unicode whatever the layout, so the dead keys and the modifiers
+    // held by the user don't change it, only the line breaks and tabs being keys
+    fn key_sequence(&mut self, sequence: &str) {
+        for char in sequence.chars() {
+            match char {
+                '\n' => {
+                    self.key(VK_RETURN, true);
+                    self.key(VK_RETURN, false);
+                }
+                '\t' => {
+                    self.key(VK_TAB, true);
+                    self.key(VK_TAB, false);
+                }
+                char => self.type_unicode(char),
+            }
+        }
+    }
+
+    fn key_down(&mut self, key: Key) {
+        if let Some(virtual_key) = virtual_key(key) {
+            self.key(virtual_key, true);
+        }
+    }
+
+    fn key_up(&mut self, key: Key) {
+        if let Some(virtual_key) = virtual_key(key) {
+            self.key(virtual_key, false);
+        }
+    }
+
+    fn scancode_click(&mut self, char: char) {
+        type_char(char, |scancode, pressed| self.scancode(scancode, pressed));
+    }
+
+    fn key_click(&mut self, key: Key) {
+        match key {
+            Key::Layout(char) => self.type_char(char),
+            key => {
+                if let Some(virtual_key) = virtual_key(key) {
+                    self.key(virtual_key, true);
+                    self.key(virtual_key, false);
+                }
+            }
+        }
+    }
+}
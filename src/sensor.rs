@@ -0,0 +1,51 @@
+use std::ops::RangeInclusive;
+
+use rusb::{DeviceHandle, UsbContext};
+
+use crate::hid::set_feature_report;
+use crate::model::DeviceModel;
+
+// range of the sensors of the supported models
+pub const DPI_RANGE: RangeInclusive<u16> = 100..=6400;
+pub const DPI_STEP: u16 = 25;
+pub const MAX_DPI_STAGES: usize = 4;
+// in millimeters
+pub const LIFT_OFF_DISTANCES: RangeInclusive<u8> = 1..=5;
+
+// one feature report per stage : report id, stage, dpi in steps, little endian
+pub fn set_dpi_stages<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    iface: u8,
+    dpi_stages: &[u16],
+) -> rusb::Result<()> {
+    let report_id = match device_model.dpi_report_id {
+        Some(report_id) => report_id,
+        None => return Ok(()),
+    };
+
+    for (stage, dpi) in dpi_stages.iter().take(MAX_DPI_STAGES).enumerate() {
+        let [low, high] = (dpi / DPI_STEP).to_le_bytes();
+
+        set_feature_report(device_handle, iface, &[report_id, stage as u8, low, high])?;
+    }
+
+    Ok(())
+}
+
+// feature report : report id, distance in millimeters
+pub fn set_lift_off_distance<T: UsbContext>(
+    device_handle: &DeviceHandle<T>,
+    device_model: &DeviceModel,
+    iface: u8,
+    lift_off_distance: u8,
+) -> rusb::Result<()> {
+    let report_id = match device_model.lift_off_report_id {
+        Some(report_id) => report_id,
+        None => return Ok(()),
+    };
+
+    set_feature_report(device_handle, iface, &[report_id, lift_off_distance])?;
+
+    Ok(())
+}
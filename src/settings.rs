@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lighting::LedColor;
+use crate::model::DeviceModel;
+use crate::polling;
+use crate::sensor::{DPI_RANGE, DPI_STEP, LIFT_OFF_DISTANCES, MAX_DPI_STAGES};
+
+// settings of the device itself, shared by all its profiles
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct DeviceSettings {
+    // color of each mode led, the device keeps its own colors when empty
+    #[serde(default)]
+    pub led_colors: Vec<LedColor>,
+    // in hz, applied when the mouse connects, the device keeps its own rate when unset
+    #[serde(default)]
+    pub polling_rate: Option<u16>,
+    // in dots per inch, cycled by the dpi button, the device keeps its own stages when empty
+    #[serde(default)]
+    pub dpi_stages: Vec<u16>,
+    // in millimeters, the device keeps its own distance when unset
+    #[serde(default)]
+    pub lift_off_distance: Option<u8>,
+    // multiplies the x and y movement after the response curve of the profile
+    #[serde(default)]
+    pub sensitivity: Option<[f32; 2]>,
+    // in milliseconds, how long the device thread waits for a report while no binding is held
+    #[serde(default)]
+    pub read_timeout: Option<u64>,
+    // in milliseconds, cadence of the held and repeated bindings, three report intervals when unset
+    #[serde(default)]
+    pub tick_interval: Option<u64>,
+}
+
+impl DeviceSettings {
+    // field name and message of each invalid setting, the led colors depend on the device model
+    pub fn check(&self) -> Vec<(&'static str, String)> {
+        let mut error_vec = vec![];
+
+        if let Some(polling_rate) = self.polling_rate {
+            if !polling::POLLING_RATES.contains(&polling_rate) {
+                error_vec.push((
+                    "polling_rate",
+                    format!("unsupported polling rate {}", polling_rate),
+                ));
+            }
+        }
+
+        if self.dpi_stages.len() > MAX_DPI_STAGES {
+            error_vec.push((
+                "dpi_stages",
+                format!("at most {} dpi stages", MAX_DPI_STAGES),
+            ));
+        }
+
+        for dpi in &self.dpi_stages {
+            if !DPI_RANGE.contains(dpi) || dpi % DPI_STEP != 0 {
+                error_vec.push((
+                    "dpi_stages",
+                    format!(
+                        "unsupported dpi {}, from {} to {} by steps of {}",
+                        dpi,
+                        DPI_RANGE.start(),
+                        DPI_RANGE.end(),
+                        DPI_STEP
+                    ),
+                ));
+            }
+        }
+
+        if let Some(lift_off_distance) = self.lift_off_distance {
+            if !LIFT_OFF_DISTANCES.contains(&lift_off_distance) {
+                error_vec.push((
+                    "lift_off_distance",
+                    format!("unsupported lift-off distance {}", lift_off_distance),
+                ));
+            }
+        }
+
+        if let Some(sensitivity) = self.sensitivity {
+            if sensitivity.iter().any(|multiplier| *multiplier <= 0.0) {
+                error_vec.push((
+                    "sensitivity",
+                    "the multipliers must be positive".to_string(),
+                ));
+            }
+        }
+
+        for (field, value, range) in [
+            (
+                "read_timeout",
+                self.read_timeout,
+                polling::READ_TIMEOUT_RANGE,
+            ),
+            (
+                "tick_interval",
+                self.tick_interval,
+                polling::TICK_INTERVAL_RANGE,
+            ),
+        ] {
+            if let Some(value) = value {
+                if !range.contains(&value) {
+                    error_vec.push((
+                        field,
+                        format!(
+                            "unsupported value {}, from {} to {} milliseconds",
+                            value,
+                            range.start(),
+                            range.end()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        error_vec
+    }
+    // field name and message of each setting the device model has no report for, which would
+    // be saved without ever reaching the device
+    pub fn unsupported(&self, device_model: &DeviceModel) -> Vec<(&'static str, String)> {
+        let mut error_vec = vec![];
+
+        if self.polling_rate.is_some() && device_model.polling_rate_report_id.is_none() {
+            error_vec.push((
+                "polling_rate",
+                format!("the {} polling rate can't be changed", device_model.name),
+            ));
+        }
+
+        if !self.dpi_stages.is_empty() && device_model.dpi_report_id.is_none() {
+            error_vec.push((
+                "dpi_stages",
+                format!("the {} dpi stages can't be changed", device_model.name),
+            ));
+        }
+
+        if self.lift_off_distance.is_some() && device_model.lift_off_report_id.is_none() {
+            error_vec.push((
+                "lift_off_distance",
+                format!(
+                    "the {} lift-off distance can't be changed",
+                    device_model.name
+                ),
+            ));
+        }
+
+        error_vec
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+use crate::{ButtonConfigs, MousesConfig};
+
+struct SyncedProfile {
+    button_configs: ButtonConfigs,
+    // compared with the copies of the devices to find the edited one
+    value: Value,
+    serial_numbers: BTreeSet<String>,
+}
+
+// content of each shared profile at the last sync, with the devices sharing it
+#[derive(Default)]
+pub struct SharedProfiles {
+    synced_map: BTreeMap<String, SyncedProfile>,
+}
+
+fn to_value(button_configs: &ButtonConfigs) -> Value {
+    serde_json::to_value(button_configs).unwrap_or_default()
+}
+
+impl SharedProfiles {
+    // the copy edited since the last sync is given to every device sharing the profile,
+    // devices which just started sharing it get the current copy instead,
+    // true when a device config changed
+    pub fn sync(&mut self, mouses_config: &mut MousesConfig) -> bool {
+        let mut serial_numbers_map: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+        for (serial_number, mouse_config) in mouses_config.iter() {
+            for profile in &mouse_config.shared_profiles {
+                serial_numbers_map
+                    .entry(profile.clone())
+                    .or_default()
+                    .insert(serial_number.clone());
+            }
+        }
+
+        let mut synced_map = BTreeMap::new();
+        let mut has_changed = false;
+
+        for (profile, serial_numbers) in serial_numbers_map {
+            let last_synced_profile = self.synced_map.remove(&profile);
+            let edited_profile = serial_numbers
+                .iter()
+                .filter(|serial_number| {
+                    last_synced_profile
+                        .as_ref()
+                        .map(|synced_profile| {
+                            synced_profile.serial_numbers.contains(*serial_number)
+                        })
+                        .unwrap_or(true)
+                })
+                .filter_map(|serial_number| {
+                    mouses_config.get(serial_number)?.profiles.get(&profile)
+                })
+                .map(|button_configs| (button_configs, to_value(button_configs)))
+                .find(|(_, value)| {
+                    last_synced_profile
+                        .as_ref()
+                        .map(|synced_profile| synced_profile.value != *value)
+                        .unwrap_or(true)
+                })
+                .map(|(button_configs, value)| SyncedProfile {
+                    button_configs: button_configs.clone(),
+                    value,
+                    serial_numbers: BTreeSet::new(),
+                });
+            let mut synced_profile = match edited_profile.or(last_synced_profile) {
+                Some(synced_profile) => synced_profile,
+                // none of the devices has it
+                None => continue,
+            };
+
+            for serial_number in &serial_numbers {
+                if let Some(mouse_config) = mouses_config.get_mut(serial_number) {
+                    if mouse_config.profiles.get(&profile).map(to_value)
+                        != Some(synced_profile.value.clone())
+                    {
+                        mouse_config
+                            .profiles
+                            .insert(profile.clone(), synced_profile.button_configs.clone());
+                        has_changed = true;
+                    }
+                }
+            }
+
+            synced_profile.serial_numbers = serial_numbers;
+            synced_map.insert(profile, synced_profile);
+        }
+
+        self.synced_map = synced_map;
+
+        has_changed
+    }
+}
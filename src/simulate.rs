@@ -0,0 +1,128 @@
+use std::fs;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::mapper::Mapper;
+use crate::snapshot::ConfigSnapshot;
+use crate::websocket::event_channel;
+use crate::{restore_selected_profiles, MousesConfig};
+
+use tokio::sync::mpsc::unbounded_channel;
+use util::config::ConfigManager;
+
+// serial number used when none is given and the profiles file is empty
+const SIMULATED_SERIAL_NUMBER: &str = "simulated";
+// same as the read timeout of a real device, so repeats and holds behave the same
+const TICK_INTERVAL: Duration = Duration::from_millis(25);
+
+// a report per line, its time in milliseconds from the start then its bytes in hex,
+// like `120 01 00 00 00 00 00 00 00`, anything after a `#` is ignored
+struct Report {
+    time: Duration,
+    buffer: Vec<u8>,
+}
+
+fn parse_reports(data: &str) -> Result<Vec<Report>, String> {
+    let mut report_vec = vec![];
+
+    for (index, line) in data.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut field_iter = line.split_whitespace();
+        let time = field_iter
+            .next()
+            .and_then(|millis| millis.parse::<u64>().ok())
+            .map(Duration::from_millis);
+        let buffer = field_iter
+            .map(|byte| u8::from_str_radix(byte, 16).ok())
+            .collect::<Option<Vec<u8>>>();
+
+        match (time, buffer) {
+            (Some(time), Some(buffer)) => report_vec.push(Report { time, buffer }),
+            _ => return Err(format!("invalid report at line {}", index + 1)),
+        }
+    }
+
+    Ok(report_vec)
+}
+
+// feeds the captured reports of a file to a mapper using the profiles of the device,
+// the bindings being played like with the real mouse
+pub fn run_simulation(path: &str, serial_number: Option<&str>) -> bool {
+    let report_vec = match fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|data| parse_reports(&data))
+    {
+        Ok(report_vec) => report_vec,
+        Err(err) => {
+            println!("{} : {}", path, err);
+            return false;
+        }
+    };
+    let mut mouses_config: MousesConfig =
+        ConfigManager::<MousesConfig>::new("mmo7_profiles").config;
+    let serial_number = serial_number
+        .map(str::to_string)
+        .or_else(|| mouses_config.keys().next().cloned())
+        .unwrap_or_else(|| SIMULATED_SERIAL_NUMBER.to_string());
+
+    restore_selected_profiles(&mut mouses_config);
+    mouses_config.entry(serial_number.clone()).or_default();
+
+    let device_model = mouses_config[&serial_number].device_model();
+    let config_snapshot = ConfigSnapshot::default();
+    let (profile_switch_tx, mut profile_switch_rx) = unbounded_channel();
+
+    config_snapshot.publish(&mouses_config);
+
+    let mut mapper = Mapper::new(
+        device_model,
+        config_snapshot.clone(),
+        profile_switch_tx,
+        serial_number.clone(),
+        event_channel(),
+    );
+    let mut buffer = vec![0; device_model.report.length];
+    let start = Instant::now();
+
+    println!(
+        "simulating {} reports on {} ({})",
+        report_vec.len(),
+        serial_number,
+        device_model.name
+    );
+
+    for report in report_vec {
+        // the previous report stays held until the next one
+        while start.elapsed() + TICK_INTERVAL < report.time {
+            sleep(TICK_INTERVAL);
+            mapper.emulate_only_mapped(&buffer);
+        }
+
+        sleep(report.time.saturating_sub(start.elapsed()));
+
+        let length = report.buffer.len().min(buffer.len());
+
+        buffer.fill(0);
+        buffer[..length].copy_from_slice(&report.buffer[..length]);
+        mapper.emulate(&buffer);
+
+        while let Ok((serial_number, profile_switch)) = profile_switch_rx.try_recv() {
+            if let Some(mouse_config) = mouses_config.get_mut(&serial_number) {
+                mouse_config.switch_profile(&profile_switch);
+                config_snapshot.publish(&mouses_config);
+            }
+        }
+    }
+
+    // let the workers play the last tokens before releasing everything
+    sleep(TICK_INTERVAL);
+    drop(mapper);
+    sleep(TICK_INTERVAL);
+
+    true
+}
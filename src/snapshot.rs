@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backend::BackendKind;
+use crate::lighting::LedColor;
+use crate::mapper::ButtonConfigsToken;
+use crate::polling;
+use crate::tokenizer::ProfileSwitch;
+use crate::MousesConfig;
+
+use arc_swap::ArcSwap;
+use hashbrown::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
+
+// profile switches pressed on a device, applied to the config outside of the device thread
+pub type ProfileSwitchSender = UnboundedSender<(String, ProfileSwitch)>;
+
+// tokenized config of a device, everything its thread reads while emulating
+#[derive(Debug)]
+pub struct DeviceSnapshot {
+    pub button_configs_token: ButtonConfigsToken,
+    pub active_profile: Option<String>,
+    pub led_colors: Vec<LedColor>,
+    pub polling_rate: Option<u16>,
+    pub dpi_stages: Vec<u16>,
+    pub lift_off_distance: Option<u8>,
+    pub read_timeout: Duration,
+    pub tick_interval: Duration,
+    pub backend: BackendKind,
+}
+
+// published after each config change, so the device threads never wait on the config lock
+#[derive(Clone, Default)]
+pub struct ConfigSnapshot(Arc<ArcSwap<HashMap<String, Arc<DeviceSnapshot>>>>);
+
+impl ConfigSnapshot {
+    pub fn publish(&self, mouses_config: &MousesConfig) {
+        let device_snapshot_map = mouses_config
+            .iter()
+            .map(|(serial_number, mouse_config)| {
+                (
+                    serial_number.clone(),
+                    Arc::new(DeviceSnapshot {
+                        button_configs_token: ButtonConfigsToken::from_config(
+                            mouse_config.active_button_configs(),
+                            mouse_config.device_model(),
+                            &mouse_config.settings,
+                        ),
+                        active_profile: mouse_config.active_profile.clone(),
+                        led_colors: mouse_config.active_led_colors().clone(),
+                        polling_rate: mouse_config.settings.polling_rate,
+                        dpi_stages: mouse_config.settings.dpi_stages.clone(),
+                        lift_off_distance: mouse_config.settings.lift_off_distance,
+                        read_timeout: polling::read_timeout(mouse_config.settings.read_timeout),
+                        tick_interval: polling::tick_interval(
+                            mouse_config.settings.tick_interval,
+                            mouse_config.settings.polling_rate,
+                        ),
+                        backend: mouse_config.backend,
+                    }),
+                )
+            })
+            .collect();
+
+        self.0.store(Arc::new(device_snapshot_map));
+    }
+
+    pub fn get(&self, serial_number: &str) -> Option<Arc<DeviceSnapshot>> {
+        self.0.load().get(serial_number).cloned()
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+use std::thread::spawn;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::websocket::{DriverEvent, EventSender};
+
+// plays the sound of the system instead of a file
+const BEEP: &str = "beep";
+
+// path of a sound file, or `beep`, silent when unset
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct SoundSettings {
+    #[serde(default)]
+    pub mode_change: Option<String>,
+    #[serde(default)]
+    pub profile_change: Option<String>,
+}
+
+// the feedback of the mode and profile changes, without looking at the mouse or the screen
+pub async fn run_sound(sound_settings: SoundSettings, event_tx: EventSender) {
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        // devices whose first profile event, sent once connected, isn't a change yet
+        let mut connecting_set = BTreeSet::new();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let sound = match event {
+                DriverEvent::Device {
+                    serial_number,
+                    connected: true,
+                } => {
+                    connecting_set.insert(serial_number);
+                    continue;
+                }
+                DriverEvent::Profile { serial_number, .. }
+                    if connecting_set.remove(&serial_number) =>
+                {
+                    continue
+                }
+                DriverEvent::Mode { .. } => &sound_settings.mode_change,
+                DriverEvent::Profile { .. } => &sound_settings.profile_change,
+                _ => continue,
+            };
+
+            if let Some(sound) = sound {
+                play(sound.clone());
+            }
+        }
+    });
+}
+
+// played from its own thread, errors are ignored as there might be no audio session
+fn play(sound: String) {
+    spawn(move || platform::play(&sound));
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::{Command, Stdio};
+
+    use super::BEEP;
+
+    // players of the session, tried in order
+    pub fn play(sound: &str) {
+        let player_vec: Vec<(&str, Vec<&str>)> = if sound == BEEP {
+            vec![("canberra-gtk-play", vec!["--id", "bell"])]
+        } else {
+            vec![
+                ("paplay", vec![sound]),
+                ("pw-play", vec![sound]),
+                ("aplay", vec!["--quiet", sound]),
+            ]
+        };
+
+        for (program, args) in player_vec {
+            let status = Command::new(program)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+
+            if let Ok(status) = status {
+                if status.success() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::Media::Audio::{
+        PlaySoundW, SND_ALIAS, SND_FILENAME, SND_NODEFAULT, SND_SYNC,
+    };
+
+    use super::BEEP;
+
+    pub fn play(sound: &str) {
+        let (sound, flags) = if sound == BEEP {
+            ("SystemDefault", SND_ALIAS)
+        } else {
+            (sound, SND_FILENAME | SND_NODEFAULT)
+        };
+        let sound: Vec<u16> = sound.encode_utf16().chain([0]).collect();
+
+        unsafe { PlaySoundW(sound.as_ptr(), 0, flags | SND_SYNC) };
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn play(_sound: &str) {}
+}
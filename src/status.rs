@@ -0,0 +1,49 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use util::thread::MutexTrait;
+
+use crate::backend::BackendKind;
+
+// the oldest errors are dropped
+const ERROR_COUNT: usize = 20;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static ERRORS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+// backend resolved for each device, auto being replaced by the one in use
+static BACKENDS: Mutex<BTreeMap<String, BackendKind>> = Mutex::new(BTreeMap::new());
+
+pub fn start() {
+    STARTED.get_or_init(Instant::now);
+}
+
+pub fn uptime() -> Duration {
+    STARTED.get().map(Instant::elapsed).unwrap_or_default()
+}
+
+// printed as before, and kept for the diagnostics of the clients
+pub fn error(message: String) {
+    println!("{}", message);
+
+    let mut error_deque = ERRORS.lock_poisoned();
+
+    if error_deque.len() == ERROR_COUNT {
+        error_deque.pop_front();
+    }
+
+    error_deque.push_back(message);
+}
+
+// oldest first
+pub fn last_errors() -> Vec<String> {
+    ERRORS.lock_poisoned().iter().cloned().collect()
+}
+
+pub fn set_backend(serial_number: String, backend_kind: BackendKind) {
+    BACKENDS.lock_poisoned().insert(serial_number, backend_kind);
+}
+
+pub fn backend(serial_number: &str) -> Option<BackendKind> {
+    BACKENDS.lock_poisoned().get(serial_number).copied()
+}
@@ -0,0 +1,469 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::spawn_blocking;
+use tokio::time::{interval, MissedTickBehavior};
+use util::config::ConfigManager;
+use util::thread::MutexTrait;
+
+use crate::backup;
+use crate::command::{SyncAction, SyncState, SyncStatus};
+use crate::config_watcher::profiles_path;
+use crate::notification::{self, NotificationKind};
+use crate::MousesConfig;
+
+const DEFAULT_INTERVAL: u64 = 60;
+const FILE_NAME: &str = "mmo7_profiles.json";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncRemote {
+    // url of the profiles file, read with GET and written with PUT, like a webdav server
+    // or a presigned s3 url
+    Http {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    // repository holding the profiles file, committed and pushed with the git command
+    Git {
+        url: String,
+        // defaults to `main`
+        #[serde(default)]
+        branch: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SyncSettings {
+    pub remote: SyncRemote,
+    // in seconds, defaults to 60s
+    #[serde(default)]
+    pub interval: Option<u64>,
+}
+
+// state of both sides at the last sync, to tell which one changed since
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+struct LastSync {
+    local_hash: Option<u64>,
+    remote_version: Option<String>,
+}
+
+struct RemoteFile {
+    data: String,
+    // etag or commit, compared with the one of the last sync
+    version: String,
+}
+
+static STATUS: Mutex<Option<SyncStatus>> = Mutex::new(None);
+static ACTION_TX: OnceLock<UnboundedSender<SyncAction>> = OnceLock::new();
+
+pub fn status() -> SyncStatus {
+    STATUS
+        .lock_poisoned()
+        .clone()
+        .unwrap_or_else(|| SyncStatus::new(SyncState::Disabled, None))
+}
+
+// false when the sync isn't configured
+pub fn request(sync_action: SyncAction) -> bool {
+    ACTION_TX
+        .get()
+        .map(|action_tx| action_tx.send(sync_action).is_ok())
+        .unwrap_or(false)
+}
+
+// syncs on each interval, or right away when a client asks
+pub fn run_sync(sync_settings: SyncSettings) {
+    let (action_tx, mut action_rx) = unbounded_channel();
+
+    ACTION_TX.set(action_tx).ok();
+
+    tokio::spawn(async move {
+        let mut interval_ = interval(Duration::from_secs(
+            sync_settings.interval.unwrap_or(DEFAULT_INTERVAL).max(10),
+        ));
+
+        interval_.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            let sync_action = tokio::select! {
+                _ = interval_.tick() => SyncAction::Now,
+                Some(sync_action) = action_rx.recv() => sync_action,
+            };
+            let remote = sync_settings.remote.clone();
+
+            if let Ok(sync_status) = spawn_blocking(move || sync(&remote, sync_action)).await {
+                update_status(sync_status);
+            }
+        }
+    });
+}
+
+// conflicts and errors are only reported when they change
+fn update_status(sync_status: SyncStatus) {
+    let mut last_status = STATUS.lock_poisoned();
+
+    if last_status.as_ref() != Some(&sync_status) {
+        if let Some(message) = &sync_status.message {
+            println!("Profiles sync : {}", message);
+
+            if sync_status.state != SyncState::Synced {
+                notification::notify(
+                    NotificationKind::Error,
+                    format!("Profiles sync : {}", message),
+                );
+            }
+        }
+    }
+
+    *last_status = Some(sync_status);
+}
+
+fn hash(data: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn sync(remote: &SyncRemote, sync_action: SyncAction) -> SyncStatus {
+    match try_sync(remote, sync_action) {
+        Ok(sync_status) => sync_status,
+        Err(err) => SyncStatus::new(SyncState::Error, Some(err)),
+    }
+}
+
+// the side which changed since the last sync is copied to the other,
+// when both changed the user chooses which one to keep
+fn try_sync(remote: &SyncRemote, sync_action: SyncAction) -> Result<SyncStatus, String> {
+    let path = profiles_path().ok_or_else(|| "no config directory".to_string())?;
+    let mut last_sync = ConfigManager::<LastSync>::new("mmo7_sync_state");
+    let local_data = fs::read_to_string(&path).ok();
+    let remote_file = remote.fetch()?;
+    let local_changed = local_data.as_deref().map(hash) != last_sync.config.local_hash;
+    let remote_changed = remote_file.as_ref().map(|remote_file| &remote_file.version)
+        != last_sync.config.remote_version.as_ref();
+    let same_data = local_data.as_deref()
+        == remote_file
+            .as_ref()
+            .map(|remote_file| remote_file.data.as_str());
+    let (push, pull) = match sync_action {
+        SyncAction::KeepLocal => (true, false),
+        SyncAction::KeepRemote => (false, true),
+        SyncAction::Now if same_data => (false, false),
+        SyncAction::Now if local_changed && remote_changed => {
+            return Ok(SyncStatus::new(
+                SyncState::Conflict,
+                Some("the profiles changed here and on the remote, keep one of them".to_string()),
+            ));
+        }
+        SyncAction::Now => (local_changed, remote_changed),
+    };
+    let mut remote_version = remote_file
+        .as_ref()
+        .map(|remote_file| remote_file.version.clone());
+    let mut message = None;
+
+    match (&local_data, &remote_file) {
+        (Some(local_data), _) if push && !same_data => {
+            let expected_version = remote_file.as_ref().map(|remote_file| &remote_file.version);
+
+            match remote.push(local_data, expected_version.map(String::as_str))? {
+                Some(version) => {
+                    remote_version = Some(version);
+                    message = Some("local profiles pushed".to_string());
+                }
+                // changed again since it was read
+                None => {
+                    return Ok(SyncStatus::new(
+                        SyncState::Conflict,
+                        Some("the remote profiles changed while pushing".to_string()),
+                    ));
+                }
+            }
+        }
+        (_, Some(remote_file)) if pull && !same_data => {
+            serde_json::from_str::<MousesConfig>(&remote_file.data)
+                .map_err(|err| format!("remote profiles can't be parsed : {}", err))?;
+            // the watcher reloads the file
+            backup::backup();
+            fs::write(&path, &remote_file.data).map_err(|err| err.to_string())?;
+            message = Some("remote profiles pulled".to_string());
+        }
+        _ => {}
+    }
+
+    last_sync.config.remote_version = remote_version;
+    last_sync.config.local_hash = fs::read_to_string(&path).ok().as_deref().map(hash);
+    last_sync.save();
+
+    Ok(SyncStatus::new(SyncState::Synced, message))
+}
+
+impl SyncRemote {
+    // none when there's nothing on the remote yet
+    fn fetch(&self) -> Result<Option<RemoteFile>, String> {
+        match self {
+            Self::Http {
+                url,
+                username,
+                password,
+            } => http_fetch(url, username.as_deref(), password.as_deref()),
+            Self::Git { url, branch } => git_fetch(url, branch.as_deref().unwrap_or("main")),
+        }
+    }
+
+    // the new version, none when the remote isn't at the expected version anymore
+    fn push(&self, data: &str, expected_version: Option<&str>) -> Result<Option<String>, String> {
+        match self {
+            Self::Http {
+                url,
+                username,
+                password,
+            } => http_push(
+                url,
+                username.as_deref(),
+                password.as_deref(),
+                data,
+                expected_version,
+            ),
+            Self::Git { branch, .. } => {
+                git_push(branch.as_deref().unwrap_or("main"), data, expected_version)
+            }
+        }
+    }
+}
+
+// versions of the servers without etags
+fn content_version(data: &str) -> String {
+    format!("content:{:x}", hash(data))
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+            bits | ((*byte as u32) << (16 - index * 8))
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - index * 6)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn http_request(
+    method: &str,
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> ureq::Request {
+    let request = ureq::request(method, url).timeout(Duration::from_secs(30));
+
+    match username {
+        Some(username) => request.set(
+            "Authorization",
+            &format!(
+                "Basic {}",
+                base64(format!("{}:{}", username, password.unwrap_or_default()).as_bytes())
+            ),
+        ),
+        None => request,
+    }
+}
+
+fn http_fetch(
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Result<Option<RemoteFile>, String> {
+    match http_request("GET", url, username, password).call() {
+        Ok(response) => {
+            let etag = response.header("ETag").map(str::to_string);
+            let data = response.into_string().map_err(|err| err.to_string())?;
+
+            Ok(Some(RemoteFile {
+                version: etag.unwrap_or_else(|| content_version(&data)),
+                data,
+            }))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn http_push(
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    data: &str,
+    expected_version: Option<&str>,
+) -> Result<Option<String>, String> {
+    let request =
+        http_request("PUT", url, username, password).set("Content-Type", "application/json");
+    // the content versions can't be checked by the server
+    let request = match expected_version {
+        Some(version) if version.starts_with("content:") => request,
+        Some(version) => request.set("If-Match", version),
+        None => request.set("If-None-Match", "*"),
+    };
+
+    match request.send_string(data) {
+        Ok(response) => Ok(Some(
+            response
+                .header("ETag")
+                .map(str::to_string)
+                .unwrap_or_else(|| content_version(data)),
+        )),
+        Err(ureq::Error::Status(412, _)) => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// the repository is only fetched into, the working tree is never checked out
+fn git_dir() -> Result<PathBuf, String> {
+    profiles_path()
+        .and_then(|path| Some(path.parent()?.join("mmo7_sync_repository")))
+        .ok_or_else(|| "no config directory".to_string())
+}
+
+// the output is left as is, as it can be the content of the profiles file
+fn git(dir: &Path, args: &[&str], input: Option<&str>) -> Result<String, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // credentials are never asked, the driver running in the background
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("git not run : {}", err))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!(
+            "git {} failed : {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn git_fetch(url: &str, branch: &str) -> Result<Option<RemoteFile>, String> {
+    let dir = git_dir()?;
+
+    if !dir.join(".git").exists() {
+        fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        git(&dir, &["init", "-q"], None)?;
+        git(&dir, &["remote", "add", "origin", url], None)?;
+    }
+
+    git(&dir, &["remote", "set-url", "origin", url], None)?;
+    git(&dir, &["fetch", "-q", "origin"], None)?;
+
+    let remote_branch = format!("refs/remotes/origin/{}", branch);
+    let version = match git(&dir, &["rev-parse", "--verify", "-q", &remote_branch], None) {
+        Ok(version) => version.trim().to_string(),
+        // empty repository
+        Err(_) => return Ok(None),
+    };
+
+    match git(&dir, &["show", &format!("{}:{}", version, FILE_NAME)], None) {
+        Ok(data) => Ok(Some(RemoteFile { data, version })),
+        Err(_) => Ok(None),
+    }
+}
+
+// commits the file on top of the fetched branch, keeping the other files of the repository,
+// the push being refused when the branch moved since
+fn git_push(
+    branch: &str,
+    data: &str,
+    expected_version: Option<&str>,
+) -> Result<Option<String>, String> {
+    let dir = git_dir()?;
+    let blob = git(&dir, &["hash-object", "-w", "--stdin"], Some(data))?
+        .trim()
+        .to_string();
+
+    match expected_version {
+        Some(version) => git(&dir, &["read-tree", version], None)?,
+        None => git(&dir, &["read-tree", "--empty"], None)?,
+    };
+    git(
+        &dir,
+        &[
+            "update-index",
+            "--add",
+            "--cacheinfo",
+            &format!("100644,{},{}", blob, FILE_NAME),
+        ],
+        None,
+    )?;
+
+    let tree = git(&dir, &["write-tree"], None)?.trim().to_string();
+    let mut commit_args = vec![];
+
+    // a default identity when the user has none
+    if git(&dir, &["config", "user.email"], None).is_err() {
+        commit_args.extend([
+            "-c",
+            "user.name=Mad Rust",
+            "-c",
+            "user.email=mad-rust@localhost",
+        ]);
+    }
+
+    commit_args.extend(["commit-tree", &tree, "-m", "Update profiles"]);
+
+    if let Some(version) = expected_version {
+        commit_args.extend(["-p", version]);
+    }
+
+    let commit = git(&dir, &commit_args, None)?.trim().to_string();
+
+    match git(
+        &dir,
+        &[
+            "push",
+            "-q",
+            "origin",
+            &format!("{}:refs/heads/{}", commit, branch),
+        ],
+        None,
+    ) {
+        Ok(_) => Ok(Some(commit)),
+        Err(err) if err.contains("rejected") || err.contains("non-fast-forward") => Ok(None),
+        Err(err) => Err(err),
+    }
+}
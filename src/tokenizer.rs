@@ -0,0 +1,1111 @@
+use std::time::Duration;
+
+use crate::exec;
+use crate::gamepad::GamepadInput;
+use crate::midi::MidiMessage;
+use crate::plugin;
+use crate::scancode::char_to_scancode;
+#[cfg(feature = "wasm")]
+use crate::wasm::WasmCall;
+
+pub use util::tokenizer::Key;
+
+// driver side tokens are swapped for private use characters before running the util tokenizer,
+// then swapped back in the resulting text tokens
+const PLACEHOLDER_START: u32 = 0xE000;
+const PLACEHOLDER_END: u32 = 0xF8FF;
+
+// makes the binding latch, a first press plays the down tokens and a second press the up tokens
+pub const TOGGLE_FLAG: &str = "{toggle}";
+// `{interval:100}` sets the repeat interval of the binding in milliseconds
+const INTERVAL_FLAG: &str = "interval";
+// `{turbo:20}` replays the binding 20 times per second while held, pressed half of each period,
+// `{turbo:20,25}` pressed for 25% of it
+const TURBO_FLAG: &str = "turbo";
+const MAX_TURBO_RATE: f32 = 100.0;
+// `{gamepad:a}` holds a virtual controller input while the binding is held
+const GAMEPAD_FLAG: &str = "gamepad";
+// `{hscroll:3}` scrolls 3 steps right on press and on each repeat while held, negative going left
+const HSCROLL_FLAG: &str = "hscroll";
+// `{scroll:3}` scrolls 3 steps up the same way, negative going down
+const SCROLL_FLAG: &str = "scroll";
+// `{axislock}` locks the movement to the axis it starts along while the binding is held,
+// `{axislock:45}` to the closest 45° direction
+const AXIS_LOCK_FLAG: &str = "axislock";
+// `{sens:0.25}` multiplies the movement by 0.25 while the binding is held,
+// the multipliers of several held bindings being stacked
+const SENSITIVITY_FLAG: &str = "sens";
+const MAX_SENSITIVITY: f32 = 10.0;
+// `{repeat:3}(ab)` is played as `ababab`, groups can be nested
+const REPEAT_FLAG: &str = "repeat";
+const MAX_REPEAT_COUNT: usize = 100;
+// nested groups multiply their counts, so the expanded binding is bounded too
+const MAX_EXPANDED_LENGTH: usize = 100_000;
+// `{if:shift}a{else}b{end}` keeps the branch matching the mode of the binding,
+// with the `shift`, `normal` and `mode1` to `mode3` conditions, blocks can be nested
+const IF_FLAG: &str = "if";
+pub const IF_PREFIX: &str = "{if:";
+const ELSE_FLAG: &str = "{else}";
+const END_FLAG: &str = "{end}";
+// step of the plain axis lock, in degrees
+const DEFAULT_AXIS_LOCK_STEP: f32 = 90.0;
+
+#[derive(Debug, Clone)]
+pub enum Token {
+    Sequence(String),
+    // typed by the keys at the position of the characters on a us keyboard
+    Scancode(String),
+    Unicode(String),
+    // any key, the util tokenizer only producing modifiers
+    KeyUp(enigo::Key),
+    KeyDown(enigo::Key),
+    MouseUp(Button),
+    MouseDown(Button),
+    Click(Button),
+    // with the jitter it's randomly lengthened or shortened by
+    Delay(Duration, Duration),
+    Profile(ProfileSwitch),
+    Gamepad(GamepadInput, bool),
+    // positive lengths scroll right
+    ScrollX(i32),
+    // positive lengths scroll down, like enigo
+    ScrollY(i32),
+    // step in degrees when locking, none when unlocking
+    AxisLock(Option<f32>),
+    // multiplier applied to the movement, removed when false
+    Sensitivity(f32, bool),
+    MoveRelative(i32, i32),
+    // pressed, then released once the duration is over, delaying the next tokens
+    Hold(enigo::Key, Duration),
+    // in screen coordinates
+    MoveTo(i32, i32),
+    SetVariable(String, String),
+    AddVariable(String, i64),
+    // typed with the variables inserted when played
+    Type(String),
+    // in fractions of a notch delta, the scroll tokens of the profiles scrolling smoothly
+    SmoothScrollX(i32),
+    SmoothScrollY(i32),
+    // command line spawned when played, if its program is allowed by the driver settings
+    Exec(String),
+    // window activated by part of its title or class
+    Focus(String),
+    // command line run unless a window of its program can be focused
+    Launch(String),
+    // text set as the clipboard content
+    CopyText(String),
+    // pastes the clipboard with ctrl+v
+    Paste,
+    // name of the plugin and the arguments it receives
+    Plugin(String, String),
+    // sent to the midi output of the driver
+    Midi(MidiMessage),
+    // module run by the emulation worker, its tokens being played in place of this one
+    #[cfg(feature = "wasm")]
+    Wasm(WasmCall),
+}
+
+#[derive(Debug, Clone)]
+pub enum ProfileSwitch {
+    Next,
+    Previous,
+    Default,
+    Named(String),
+}
+
+impl ProfileSwitch {
+    // `next`, `previous`, `default` or the name of a profile
+    pub fn parse(string: &str) -> Self {
+        match string.trim().trim_matches('"') {
+            "next" => Self::Next,
+            "previous" => Self::Previous,
+            "default" => Self::Default,
+            profile => Self::Named(profile.to_string()),
+        }
+    }
+}
+
+// mouse buttons, with the side ones the util tokenizer can't produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+    Back,
+    Forward,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+impl From<util::tokenizer::Button> for Button {
+    fn from(button: util::tokenizer::Button) -> Self {
+        match button {
+            util::tokenizer::Button::Left => Self::Left,
+            util::tokenizer::Button::Middle => Self::Middle,
+            util::tokenizer::Button::Right => Self::Right,
+            util::tokenizer::Button::ScrollUp => Self::ScrollUp,
+            util::tokenizer::Button::ScrollDown => Self::ScrollDown,
+            util::tokenizer::Button::ScrollLeft => Self::ScrollLeft,
+            util::tokenizer::Button::ScrollRight => Self::ScrollRight,
+        }
+    }
+}
+
+impl From<util::tokenizer::Token> for Token {
+    fn from(token: util::tokenizer::Token) -> Self {
+        match token {
+            util::tokenizer::Token::Sequence(sequence) => Self::Sequence(sequence),
+            util::tokenizer::Token::Unicode(unicode_sequence) => Self::Unicode(unicode_sequence),
+            util::tokenizer::Token::KeyUp(key) => Self::KeyUp(key_to_enigo(&key)),
+            util::tokenizer::Token::KeyDown(key) => Self::KeyDown(key_to_enigo(&key)),
+            util::tokenizer::Token::MouseUp(button) => Self::MouseUp(button.into()),
+            util::tokenizer::Token::MouseDown(button) => Self::MouseDown(button.into()),
+            util::tokenizer::Token::Click(button) => Self::Click(button.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StateToken {
+    pub down: Vec<Token>,
+    pub repeat: Vec<Token>,
+    pub up: Vec<Token>,
+    pub toggle: bool,
+    pub repeat_interval: Option<Duration>,
+    pub turbo: Option<Turbo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Turbo {
+    pub pressed: Duration,
+    pub released: Duration,
+}
+
+pub fn tokenize(string: String) -> StateToken {
+    let mut driver_token_vec = vec![];
+    let toggle = string.contains(TOGGLE_FLAG);
+    // a binding expanding beyond the limit does nothing, it's reported by the config check
+    let mut string = expand_repeat(&string.replace(TOGGLE_FLAG, "")).unwrap_or_default();
+    let repeat_interval = take_flag(&mut string, INTERVAL_FLAG)
+        .and_then(|millis| millis.trim().parse::<u64>().ok())
+        .map(Duration::from_millis);
+    let turbo = take_flag(&mut string, TURBO_FLAG).and_then(|argument| parse_turbo(&argument));
+    let mut gamepad_input_vec = vec![];
+
+    while let Some(input) = take_flag(&mut string, GAMEPAD_FLAG) {
+        if let Some(input) = GamepadInput::parse(&input) {
+            gamepad_input_vec.push(input);
+        }
+    }
+
+    let axis_lock = take_axis_lock(&mut string);
+    let sensitivity = take_flag(&mut string, SENSITIVITY_FLAG)
+        .and_then(|multiplier| parse_sensitivity(&multiplier));
+    let mut scroll_vec = vec![];
+
+    while let Some(length) = take_flag(&mut string, HSCROLL_FLAG) {
+        if let Ok(length) = length.trim().parse::<i32>() {
+            scroll_vec.push(Token::ScrollX(length));
+        }
+    }
+    while let Some(length) = take_flag(&mut string, SCROLL_FLAG) {
+        if let Ok(length) = length.trim().parse::<i32>() {
+            scroll_vec.push(Token::ScrollY(-length));
+        }
+    }
+
+    let string = extract_driver_token(&string, &mut driver_token_vec);
+    let state_token = util::tokenizer::tokenize(string);
+    let mut down = restore_driver_token(state_token.down, &driver_token_vec);
+    let mut up = restore_driver_token(state_token.up, &driver_token_vec);
+
+    down.extend(
+        gamepad_input_vec
+            .iter()
+            .map(|input| Token::Gamepad(*input, true)),
+    );
+    up.extend(
+        gamepad_input_vec
+            .iter()
+            .map(|input| Token::Gamepad(*input, false)),
+    );
+
+    release_held_outputs(&down, &mut up);
+
+    if let Some(step) = axis_lock {
+        down.push(Token::AxisLock(Some(step)));
+        up.push(Token::AxisLock(None));
+    }
+
+    if let Some(multiplier) = sensitivity {
+        down.push(Token::Sensitivity(multiplier, true));
+        up.push(Token::Sensitivity(multiplier, false));
+    }
+
+    let mut repeat = restore_driver_token(state_token.repeat, &driver_token_vec);
+
+    down.extend(scroll_vec.iter().cloned());
+    repeat.extend(scroll_vec);
+
+    StateToken {
+        down,
+        repeat,
+        up,
+        toggle,
+        repeat_interval,
+        turbo,
+    }
+}
+
+// the keys and buttons left pressed by the down tokens are released by the up tokens,
+// so `{keydown:w}` on the hat holds w for as long as the hat is held
+fn release_held_outputs(down: &[Token], up: &mut Vec<Token>) {
+    let mut held_key_vec = vec![];
+    let mut held_button_vec = vec![];
+    let mut held_note_vec = vec![];
+
+    for token in down {
+        match token {
+            Token::KeyDown(key) if !held_key_vec.contains(key) => held_key_vec.push(*key),
+            Token::KeyUp(key) => held_key_vec.retain(|held_key| held_key != key),
+            Token::MouseDown(button) if !held_button_vec.contains(button) => {
+                held_button_vec.push(*button)
+            }
+            Token::MouseUp(button) => held_button_vec.retain(|held_button| held_button != button),
+            Token::Midi(MidiMessage::NoteOn { channel, note, .. }) => {
+                held_note_vec.push(MidiMessage::NoteOff {
+                    channel: *channel,
+                    note: *note,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    for key in held_key_vec {
+        if !up
+            .iter()
+            .any(|token| matches!(token, Token::KeyUp(up_key) if *up_key == key))
+        {
+            up.push(Token::KeyUp(key));
+        }
+    }
+
+    for button in held_button_vec {
+        if !up
+            .iter()
+            .any(|token| matches!(token, Token::MouseUp(up_button) if *up_button == button))
+        {
+            up.push(Token::MouseUp(button));
+        }
+    }
+
+    // notes played while the binding is held
+    for note_off in held_note_vec {
+        if !up
+            .iter()
+            .any(|token| matches!(token, Token::Midi(up_message) if *up_message == note_off))
+        {
+            up.push(Token::Midi(note_off));
+        }
+    }
+}
+
+// removes a `{name:value}` flag from the string, returning its value
+fn take_flag(string: &mut String, name: &str) -> Option<String> {
+    let prefix = format!("{{{}:", name);
+    let start = string.find(&prefix)?;
+    let end = start + string[start..].find('}')?;
+    let value = string[start + prefix.len()..end].to_string();
+
+    string.replace_range(start..=end, "");
+
+    Some(value)
+}
+
+// removes the branches of the conditional blocks not matching the mode
+pub fn resolve_conditions(string: &str, shift: bool, mode: usize) -> String {
+    let mut output = String::new();
+    // whether each open block keeps its current branch
+    let mut branch_vec: Vec<bool> = vec![];
+    let mut rest = string;
+
+    while let Some(start) = rest.find('{') {
+        let is_kept = branch_vec.iter().all(|is_kept| *is_kept);
+
+        if is_kept {
+            output.push_str(&rest[..start]);
+        }
+
+        rest = &rest[start..];
+
+        if let Some(end) = rest
+            .starts_with(IF_PREFIX)
+            .then(|| rest.find('}'))
+            .flatten()
+        {
+            branch_vec
+                .push(condition_matches(&rest[IF_PREFIX.len()..end], shift, mode) == Some(true));
+            rest = &rest[end + 1..];
+        } else if rest.starts_with(ELSE_FLAG) {
+            if let Some(is_kept) = branch_vec.last_mut() {
+                *is_kept = !*is_kept;
+            }
+
+            rest = &rest[ELSE_FLAG.len()..];
+        } else if rest.starts_with(END_FLAG) {
+            branch_vec.pop();
+            rest = &rest[END_FLAG.len()..];
+        } else {
+            if is_kept {
+                output.push('{');
+            }
+
+            rest = &rest[1..];
+        }
+    }
+
+    if branch_vec.iter().all(|is_kept| *is_kept) {
+        output.push_str(rest);
+    }
+
+    output
+}
+
+// none for unknown conditions
+fn condition_matches(condition: &str, shift: bool, mode: usize) -> Option<bool> {
+    match condition.trim() {
+        "shift" => Some(shift),
+        "normal" => Some(!shift),
+        condition => condition
+            .strip_prefix("mode")?
+            .parse::<usize>()
+            .ok()
+            .filter(|number| (1..=3).contains(number))
+            .map(|number| number - 1 == mode),
+    }
+}
+
+// none when the expanded string would be longer than the limit
+fn expand_repeat(string: &str) -> Option<String> {
+    let prefix = format!("{{{}:", REPEAT_FLAG);
+    let mut output = String::new();
+    let mut rest = string;
+
+    while let Some(start) = rest.find(&prefix) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match parse_repeat_group(rest) {
+            Some((count, group, length)) => {
+                let group = expand_repeat(group)?;
+
+                if output.len() + group.len() * count > MAX_EXPANDED_LENGTH {
+                    return None;
+                }
+
+                output.push_str(&group.repeat(count));
+                rest = &rest[length..];
+            }
+            None => {
+                output.push_str(&prefix);
+                rest = &rest[prefix.len()..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+
+    (output.len() <= MAX_EXPANDED_LENGTH).then_some(output)
+}
+
+// count, content and length of the `{repeat:N}(...)` group starting the string
+fn parse_repeat_group(string: &str) -> Option<(usize, &str, usize)> {
+    let prefix = format!("{{{}:", REPEAT_FLAG);
+    let count_end = prefix.len() + string.strip_prefix(&prefix)?.find('}')?;
+    let count = string[prefix.len()..count_end]
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|count| *count <= MAX_REPEAT_COUNT)?;
+    let group_start = count_end + 1;
+    let mut depth = 0;
+
+    if !string[group_start..].starts_with('(') {
+        return None;
+    }
+
+    for (index, char) in string[group_start..].char_indices() {
+        match char {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some((
+                        count,
+                        &string[group_start + 1..group_start + index],
+                        group_start + index + 1,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// the step of the axis lock flag, removed from the string
+fn take_axis_lock(string: &mut String) -> Option<f32> {
+    let flag = format!("{{{}}}", AXIS_LOCK_FLAG);
+
+    if string.contains(&flag) {
+        *string = string.replace(&flag, "");
+
+        return Some(DEFAULT_AXIS_LOCK_STEP);
+    }
+
+    take_flag(string, AXIS_LOCK_FLAG).and_then(|step| parse_axis_lock_step(&step))
+}
+
+fn parse_sensitivity(string: &str) -> Option<f32> {
+    string
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|multiplier| *multiplier > 0.0 && *multiplier <= MAX_SENSITIVITY)
+}
+
+// `rate` per second with an optional `,duty` cycle in percent
+fn parse_turbo(string: &str) -> Option<Turbo> {
+    let (rate, duty) = string.split_once(',').unwrap_or((string, "50"));
+    let rate = rate
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|rate| *rate > 0.0 && *rate <= MAX_TURBO_RATE)?;
+    let duty = duty
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|duty| *duty > 0.0 && *duty < 100.0)?;
+    let period = Duration::from_secs_f32(1.0 / rate);
+    let pressed = period.mul_f32(duty / 100.0);
+
+    Some(Turbo {
+        pressed,
+        released: period - pressed,
+    })
+}
+
+// the name of the plugin, then its arguments after the first comma
+fn parse_plugin(argument: &str) -> (String, String) {
+    let (name, arguments) = argument.split_once(',').unwrap_or((argument, ""));
+
+    (name.trim().to_string(), arguments.to_string())
+}
+
+// `60`, with an optional velocity from 1 to 127 and channel from 1 to 16, like `60,100,2`
+fn parse_midi_note(string: &str) -> Option<MidiMessage> {
+    let mut values = string.split(',');
+    let note = parse_midi_value(values.next()?)?;
+    let velocity = match values.next() {
+        Some(velocity) => parse_midi_value(velocity).filter(|velocity| *velocity > 0)?,
+        None => 127,
+    };
+    let channel = parse_midi_channel(values.next())?;
+
+    values.next().is_none().then_some(MidiMessage::NoteOn {
+        channel,
+        note,
+        velocity,
+    })
+}
+
+// `7,127`, the controller then its value, with an optional channel from 1 to 16
+fn parse_midi_control(string: &str) -> Option<MidiMessage> {
+    let mut values = string.split(',');
+    let controller = parse_midi_value(values.next()?)?;
+    let value = parse_midi_value(values.next()?)?;
+    let channel = parse_midi_channel(values.next())?;
+
+    values
+        .next()
+        .is_none()
+        .then_some(MidiMessage::ControlChange {
+            channel,
+            controller,
+            value,
+        })
+}
+
+fn parse_midi_value(string: &str) -> Option<u8> {
+    string
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .filter(|value| *value <= 127)
+}
+
+// the first channel when unset
+fn parse_midi_channel(string: Option<&str>) -> Option<u8> {
+    match string {
+        Some(channel) => channel
+            .trim()
+            .parse::<u8>()
+            .ok()
+            .filter(|channel| (1..=16).contains(channel))
+            .map(|channel| channel - 1),
+        None => Some(0),
+    }
+}
+
+// between 0 and 90 degrees
+fn parse_axis_lock_step(string: &str) -> Option<f32> {
+    string
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|step| *step > 0.0 && *step <= 90.0)
+}
+
+// problems with the driver tokens of a binding, other tokens are left to the util tokenizer
+pub fn check(string: &str, profile_exists: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut error_vec = vec![];
+    let mut rest = string;
+    let mut condition_depth = 0;
+
+    if expand_repeat(string).is_none() {
+        error_vec.push(format!(
+            "repeated groups expand beyond {} characters",
+            MAX_EXPANDED_LENGTH
+        ));
+    }
+
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start..];
+
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => {
+                error_vec.push(format!("unclosed token {}", rest));
+                break;
+            }
+        };
+        let token = &rest[..=end];
+        let (name, argument) = rest[1..end].split_once(':').unwrap_or((&rest[1..end], ""));
+        let error = match name.trim() {
+            "delay" if parse_delay(argument).is_none() => {
+                Some("expects a number of milliseconds, with an optional ±jitter")
+            }
+            "interval" if argument.trim().parse::<u64>().is_err() => {
+                Some("expects a number of milliseconds")
+            }
+            SENSITIVITY_FLAG if parse_sensitivity(argument).is_none() => {
+                Some("expects a multiplier above 0 and up to 10")
+            }
+            TURBO_FLAG if parse_turbo(argument).is_none() => {
+                Some("expects a rate up to 100 per second and an optional duty cycle in percent")
+            }
+            GAMEPAD_FLAG if GamepadInput::parse(argument).is_none() => {
+                Some("unknown gamepad input")
+            }
+            HSCROLL_FLAG | SCROLL_FLAG if argument.trim().parse::<i32>().is_err() => {
+                Some("expects a number of steps")
+            }
+            AXIS_LOCK_FLAG
+                if !argument.trim().is_empty() && parse_axis_lock_step(argument).is_none() =>
+            {
+                Some("expects a step between 0 and 90 degrees")
+            }
+            IF_FLAG => {
+                condition_depth += 1;
+
+                condition_matches(argument, false, 0)
+                    .is_none()
+                    .then_some("unknown condition")
+            }
+            "else" if condition_depth == 0 => Some("outside of a conditional block"),
+            "end" if condition_depth == 0 => Some("outside of a conditional block"),
+            "end" => {
+                condition_depth -= 1;
+                None
+            }
+            "exec" | "launch" if exec::split_command_line(argument).is_empty() => {
+                Some("expects a command")
+            }
+            "plugin" => match parse_plugin(argument) {
+                (name, _) if name.is_empty() => Some("expects a plugin name"),
+                (name, _) if !plugin::exists(&name) => Some("unknown plugin"),
+                _ => None,
+            },
+            "midinote" if parse_midi_note(argument).is_none() => {
+                Some("expects a note from 0 to 127, with an optional velocity and channel")
+            }
+            "midicc" if parse_midi_control(argument).is_none() => {
+                Some("expects a controller and a value from 0 to 127, with an optional channel")
+            }
+            "focus" if argument.trim().is_empty() => {
+                Some("expects part of a window title or class")
+            }
+            "set" if !argument.contains('=') => Some("expects name=value"),
+            "inc" | "dec" if argument.trim().is_empty() => Some("expects a variable name"),
+            REPEAT_FLAG if parse_repeat_group(rest).is_none() => {
+                Some("expects a count up to 100 followed by a group in parentheses")
+            }
+            "move" | "moveto" if parse_point(argument).is_none() => Some("expects x,y coordinates"),
+            "keydown" | "keyup" if parse_key(argument).is_none() => Some("unknown key"),
+            "click" | "mousedown" | "mouseup" if parse_button(argument).is_none() => {
+                Some("unknown mouse button")
+            }
+            "hold" if parse_hold(argument).is_none() => {
+                Some("expects a key and a number of milliseconds")
+            }
+            "scancode"
+                if argument
+                    .chars()
+                    .any(|char| char_to_scancode(char).is_none()) =>
+            {
+                Some("expects characters of a us keyboard")
+            }
+            "profile" => match ProfileSwitch::parse(argument) {
+                ProfileSwitch::Named(profile) if !profile_exists(&profile) => {
+                    Some("unknown profile")
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(error) = error {
+            error_vec.push(format!("{} {}", token, error));
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    if condition_depth > 0 {
+        error_vec.push(format!("{} without {}", IF_PREFIX, END_FLAG));
+    }
+
+    error_vec
+}
+
+fn parse_driver_token(content: &str) -> Option<Vec<Token>> {
+    let (name, argument) = content.split_once(':').unwrap_or((content, ""));
+
+    let token = match name.trim() {
+        "delay" => parse_delay(argument).map(|(delay, jitter)| Token::Delay(delay, jitter)),
+        "profile" => Some(Token::Profile(ProfileSwitch::parse(argument))),
+        "move" => parse_point(argument).map(|(x, y)| Token::MoveRelative(x, y)),
+        "moveto" => parse_point(argument).map(|(x, y)| Token::MoveTo(x, y)),
+        // `{set:count=0}`, `{inc:count}`, `{dec:count}` and `{type:$count}` use the variables of the device
+        "set" => argument
+            .split_once('=')
+            .map(|(name, value)| Token::SetVariable(name.trim().to_string(), value.to_string())),
+        "inc" => Some(Token::AddVariable(argument.trim().to_string(), 1)),
+        "dec" => Some(Token::AddVariable(argument.trim().to_string(), -1)),
+        "type" => Some(Token::Type(argument.to_string())),
+        "exec" => Some(Token::Exec(argument.to_string())),
+        // `{focus:discord}` jumps to a window, `{launch:discord}` too, running it when it's not open
+        "focus" => Some(Token::Focus(argument.trim().to_string())),
+        "launch" => Some(Token::Launch(argument.to_string())),
+        // `{copytext:text}` sets the clipboard, `{paste}` pastes it and `{paste:text}` both,
+        // far faster than typing long texts
+        "copytext" => Some(Token::CopyText(argument.to_string())),
+        "paste" if argument.is_empty() => Some(Token::Paste),
+        "paste" => return Some(vec![Token::CopyText(argument.to_string()), Token::Paste]),
+        // `{plugin:obs,scene2}` hands `scene2` to the obs plugin
+        "plugin" => {
+            let (name, arguments) = parse_plugin(argument);
+
+            Some(Token::Plugin(name, arguments))
+        }
+        // `{midinote:60}` plays a note while the binding is held, `{midicc:7,127}` sets a controller
+        "midinote" => parse_midi_note(argument).map(Token::Midi),
+        "midicc" => parse_midi_control(argument).map(Token::Midi),
+        // `{keydown:w}` and `{keyup:w}` hold and release any key separately,
+        // the keys still held being released with the binding
+        "keydown" => parse_key(argument).map(Token::KeyDown),
+        "keyup" => parse_key(argument).map(Token::KeyUp),
+        // `{click:back}`, `{mousedown:forward}` and `{mouseup:forward}` for any mouse button,
+        // the buttons still held being released with the binding too
+        "click" => parse_button(argument).map(Token::Click),
+        "mousedown" => parse_button(argument).map(Token::MouseDown),
+        "mouseup" => parse_button(argument).map(Token::MouseUp),
+        "hold" => parse_hold(argument).map(|(key, duration)| Token::Hold(key, duration)),
+        "scancode" => Some(Token::Scancode(argument.to_string())),
+        _ => return parse_key_combination(content),
+    };
+
+    token.map(|token| vec![token])
+}
+
+// `{ctrl+shift+s}` holds the modifiers in order while typing the key, then releases them,
+// the key can be a named one too, like `{alt+tab}`
+fn parse_key_combination(content: &str) -> Option<Vec<Token>> {
+    let (modifiers, key) = content.rsplit_once('+')?;
+    let mut modifier_vec = modifiers
+        .split('+')
+        .map(parse_modifier)
+        .collect::<Option<Vec<enigo::Key>>>()?;
+    let mut key_chars = key.chars();
+    let key_vec = match (key_chars.next(), key_chars.next()) {
+        (Some(key), None) => vec![Token::Sequence(key.to_string())],
+        _ => match parse_key(key)? {
+            // modifiers alone, like `{ctrl+shift}`, are pressed then released
+            key if is_modifier(key) => {
+                modifier_vec.push(key);
+                vec![]
+            }
+            key => vec![Token::KeyDown(key), Token::KeyUp(key)],
+        },
+    };
+
+    Some(
+        modifier_vec
+            .iter()
+            .map(|modifier| Token::KeyDown(*modifier))
+            .chain(key_vec)
+            .chain(
+                modifier_vec
+                    .iter()
+                    .rev()
+                    .map(|modifier| Token::KeyUp(*modifier)),
+            )
+            .collect(),
+    )
+}
+
+fn parse_modifier(string: &str) -> Option<enigo::Key> {
+    parse_key(string).filter(|key| is_modifier(*key))
+}
+
+fn is_modifier(key: enigo::Key) -> bool {
+    matches!(
+        key,
+        enigo::Key::Shift | enigo::Key::Control | enigo::Key::Alt | enigo::Key::Meta
+    )
+}
+
+fn key_to_enigo(key: &Key) -> enigo::Key {
+    match key {
+        Key::Shift => enigo::Key::Shift,
+        Key::Control => enigo::Key::Control,
+        Key::Alt => enigo::Key::Alt,
+        Key::Command => enigo::Key::Meta,
+    }
+}
+
+// modifiers, named keys and single characters, like `ctrl`, `space`, `f5` or `e`
+pub fn parse_key(string: &str) -> Option<enigo::Key> {
+    let string = string.trim();
+    let mut chars = string.chars();
+
+    if let (Some(char), None) = (chars.next(), chars.next()) {
+        return Some(enigo::Key::Layout(char.to_ascii_lowercase()));
+    }
+
+    let key = match string.to_lowercase().as_str() {
+        "ctrl" | "control" => enigo::Key::Control,
+        "shift" => enigo::Key::Shift,
+        "alt" => enigo::Key::Alt,
+        "cmd" | "command" | "meta" | "super" | "win" => enigo::Key::Meta,
+        "space" => enigo::Key::Space,
+        "enter" | "return" => enigo::Key::Return,
+        "tab" => enigo::Key::Tab,
+        "esc" | "escape" => enigo::Key::Escape,
+        "backspace" => enigo::Key::Backspace,
+        "delete" | "del" => enigo::Key::Delete,
+        "home" => enigo::Key::Home,
+        "end" => enigo::Key::End,
+        "pageup" => enigo::Key::PageUp,
+        "pagedown" => enigo::Key::PageDown,
+        "up" => enigo::Key::UpArrow,
+        "down" => enigo::Key::DownArrow,
+        "left" => enigo::Key::LeftArrow,
+        "right" => enigo::Key::RightArrow,
+        "capslock" => enigo::Key::CapsLock,
+        "f1" => enigo::Key::F1,
+        "f2" => enigo::Key::F2,
+        "f3" => enigo::Key::F3,
+        "f4" => enigo::Key::F4,
+        "f5" => enigo::Key::F5,
+        "f6" => enigo::Key::F6,
+        "f7" => enigo::Key::F7,
+        "f8" => enigo::Key::F8,
+        "f9" => enigo::Key::F9,
+        "f10" => enigo::Key::F10,
+        "f11" => enigo::Key::F11,
+        "f12" => enigo::Key::F12,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+// `back` and `forward` are the side buttons, also called x1 and x2
+pub fn parse_button(string: &str) -> Option<Button> {
+    let button = match string.trim().to_lowercase().as_str() {
+        "left" => Button::Left,
+        "middle" => Button::Middle,
+        "right" => Button::Right,
+        "back" | "x1" => Button::Back,
+        "forward" | "x2" => Button::Forward,
+        _ => return None,
+    };
+
+    Some(button)
+}
+
+// the mouse buttons and the scroll directions of the script functions
+#[cfg(any(feature = "rhai", feature = "wasm"))]
+pub fn parse_click_button(input: &str) -> Option<Button> {
+    match input.to_lowercase().as_str() {
+        "scroll_up" => Some(Button::ScrollUp),
+        "scroll_down" => Some(Button::ScrollDown),
+        "scroll_left" => Some(Button::ScrollLeft),
+        "scroll_right" => Some(Button::ScrollRight),
+        _ => parse_button(input),
+    }
+}
+
+// the mouse buttons come first, so `left` and `right` aren't the arrows
+#[cfg(any(feature = "rhai", feature = "wasm"))]
+pub fn input_token(input: &str, pressed: bool) -> Option<Token> {
+    if let Some(button) = parse_click_button(input) {
+        return match button {
+            Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight => {
+                None
+            }
+            _ => Some(if pressed {
+                Token::MouseDown(button)
+            } else {
+                Token::MouseUp(button)
+            }),
+        };
+    }
+
+    let key = parse_key(input)?;
+
+    Some(if pressed {
+        Token::KeyDown(key)
+    } else {
+        Token::KeyUp(key)
+    })
+}
+
+// `space,500`, the key then the milliseconds it's held
+// `100±30` waits between 70 and 130 milliseconds, `100+-30` too for the keyboards without ±
+fn parse_delay(string: &str) -> Option<(Duration, Duration)> {
+    let (millis, jitter_millis) = string
+        .split_once('±')
+        .or_else(|| string.split_once("+-"))
+        .unwrap_or((string, "0"));
+
+    Some((
+        Duration::from_millis(millis.trim().parse().ok()?),
+        Duration::from_millis(jitter_millis.trim().parse().ok()?),
+    ))
+}
+
+fn parse_hold(string: &str) -> Option<(enigo::Key, Duration)> {
+    let (key, millis) = string.rsplit_once(',')?;
+
+    Some((
+        parse_key(key)?,
+        Duration::from_millis(millis.trim().parse().ok()?),
+    ))
+}
+
+// `x,y`, relative values can be signed like `+100,-20`
+fn parse_point(string: &str) -> Option<(i32, i32)> {
+    let (x, y) = string.split_once(',')?;
+
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn extract_driver_token(string: &str, driver_token_vec: &mut Vec<Vec<Token>>) -> String {
+    let mut output = String::new();
+    let mut rest = string;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let placeholder = rest.find('}').and_then(|end| {
+            let token_vec = parse_driver_token(&rest[1..end])?;
+            let placeholder = char::from_u32(PLACEHOLDER_START + driver_token_vec.len() as u32)
+                .filter(|placeholder| *placeholder as u32 <= PLACEHOLDER_END)?;
+
+            driver_token_vec.push(token_vec);
+
+            Some((placeholder, end))
+        });
+
+        match placeholder {
+            Some((placeholder, end)) => {
+                output.push(placeholder);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                // not a driver token, leave it to the util tokenizer
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn restore_driver_token(
+    token_vec: Vec<util::tokenizer::Token>,
+    driver_token_vec: &[Vec<Token>],
+) -> Vec<Token> {
+    let mut output = vec![];
+
+    for token in token_vec {
+        let (text, is_unicode) = match token {
+            util::tokenizer::Token::Sequence(sequence) => (sequence, false),
+            util::tokenizer::Token::Unicode(unicode_sequence) => (unicode_sequence, true),
+            token => {
+                output.push(token.into());
+                continue;
+            }
+        };
+        let mut segment = String::new();
+
+        for char in text.chars() {
+            let driver_token = (PLACEHOLDER_START..=PLACEHOLDER_END)
+                .contains(&(char as u32))
+                .then(|| driver_token_vec.get((char as u32 - PLACEHOLDER_START) as usize))
+                .flatten();
+
+            match driver_token {
+                Some(driver_token_vec) => {
+                    push_text(&mut output, &mut segment, is_unicode);
+                    output.extend(driver_token_vec.iter().cloned());
+                }
+                None => segment.push(char),
+            }
+        }
+
+        push_text(&mut output, &mut segment, is_unicode);
+    }
+
+    output
+}
+
+fn push_text(output: &mut Vec<Token>, segment: &mut String, is_unicode: bool) {
+    if !segment.is_empty() {
+        let text = std::mem::take(segment);
+
+        output.push(if is_unicode {
+            Token::Unicode(text)
+        } else {
+            Token::Sequence(text)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_expands_nested_groups() {
+        assert_eq!(
+            expand_repeat("x{repeat:2}(a{repeat:3}(b))y").as_deref(),
+            Some("xabbbabbby")
+        );
+    }
+
+    #[test]
+    fn repeat_keeps_unbalanced_groups_as_text() {
+        assert_eq!(
+            expand_repeat("{repeat:2}(ab").as_deref(),
+            Some("{repeat:2}(ab")
+        );
+        assert_eq!(expand_repeat("{repeat:2}(a))").as_deref(), Some("aa)"));
+        assert_eq!(
+            expand_repeat("{repeat:2}ab").as_deref(),
+            Some("{repeat:2}ab")
+        );
+    }
+
+    #[test]
+    fn repeat_zero_removes_the_group() {
+        assert_eq!(expand_repeat("a{repeat:0}(bc)d").as_deref(), Some("ad"));
+    }
+
+    #[test]
+    fn repeat_count_over_the_limit_is_ignored() {
+        let limit = format!("{{repeat:{}}}(a)", MAX_REPEAT_COUNT);
+        let over = format!("{{repeat:{}}}(a)", MAX_REPEAT_COUNT + 1);
+
+        assert_eq!(expand_repeat(&limit), Some("a".repeat(MAX_REPEAT_COUNT)));
+        assert_eq!(expand_repeat(&over), Some(over.clone()));
+        assert_eq!(
+            expand_repeat("{repeat:x}(a)").as_deref(),
+            Some("{repeat:x}(a)")
+        );
+    }
+
+    #[test]
+    fn repeat_beyond_the_expanded_length_is_refused() {
+        let binding = "{repeat:100}({repeat:100}({repeat:100}(a)))";
+
+        assert!(expand_repeat(binding).is_none());
+        assert!(tokenize(binding.to_string()).down.is_empty());
+        assert!(check(binding, |_| true)
+            .iter()
+            .any(|error| error.contains("repeated groups expand beyond")));
+    }
+
+    #[test]
+    fn conditions_keep_the_matching_branch() {
+        let binding = "{if:shift}a{else}b{end}";
+
+        assert_eq!(resolve_conditions(binding, true, 0), "a");
+        assert_eq!(resolve_conditions(binding, false, 0), "b");
+        assert_eq!(
+            resolve_conditions("{if:unknown}a{else}b{end}", true, 0),
+            "b"
+        );
+    }
+
+    #[test]
+    fn conditions_can_be_nested() {
+        let binding = "x{if:shift}{if:mode2}a{else}b{end}{else}c{end}y";
+
+        assert_eq!(resolve_conditions(binding, true, 1), "xay");
+        assert_eq!(resolve_conditions(binding, true, 0), "xby");
+        assert_eq!(resolve_conditions(binding, false, 1), "xcy");
+        // the other tokens are left to the tokenizer
+        assert_eq!(
+            resolve_conditions("{if:normal}{click}{end}", false, 0),
+            "{click}"
+        );
+    }
+
+    #[test]
+    fn conditions_without_end_run_to_the_end_of_the_binding() {
+        let binding = "x{if:shift}a{else}b";
+
+        assert_eq!(resolve_conditions(binding, true, 0), "xa");
+        assert_eq!(resolve_conditions(binding, false, 0), "xb");
+        assert_eq!(resolve_conditions("x{if:shift}a", false, 0), "x");
+    }
+
+    #[test]
+    fn stray_else_and_end_are_removed() {
+        assert_eq!(resolve_conditions("a{else}b", true, 0), "ab");
+        assert_eq!(resolve_conditions("a{end}b", true, 0), "ab");
+        assert_eq!(resolve_conditions("{if:shift}a{end}{else}b", true, 0), "ab");
+    }
+}
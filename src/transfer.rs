@@ -0,0 +1,186 @@
+use std::ffi::{c_int, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::OnceLock;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use rusb::ffi::constants::{
+    LIBUSB_ERROR_NO_DEVICE, LIBUSB_TRANSFER_CANCELLED, LIBUSB_TRANSFER_COMPLETED,
+    LIBUSB_TRANSFER_NO_DEVICE, LIBUSB_TRANSFER_OVERFLOW, LIBUSB_TRANSFER_STALL,
+};
+use rusb::ffi::{
+    libusb_alloc_transfer, libusb_cancel_transfer, libusb_fill_interrupt_transfer,
+    libusb_free_transfer, libusb_submit_transfer, libusb_transfer,
+};
+use rusb::{Context, DeviceHandle, UsbContext};
+
+use crate::DeviceRequest;
+
+// a cancelled transfer is leaked rather than freed while libusb might still use it
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(1);
+
+static CONTEXT: OnceLock<Context> = OnceLock::new();
+
+// context shared by the device threads, its events being handled by a single thread
+// which calls back the transfers of every device
+pub fn context() -> rusb::Result<Context> {
+    if let Some(context) = CONTEXT.get() {
+        return Ok(context.clone());
+    }
+
+    let new_context = Context::new()?;
+    let mut is_new = false;
+    let context = CONTEXT
+        .get_or_init(|| {
+            is_new = true;
+            new_context
+        })
+        .clone();
+
+    if is_new {
+        let context = context.clone();
+
+        spawn(move || loop {
+            if let Err(err) = context.handle_events(None) {
+                if err != rusb::Error::Interrupted {
+                    println!("USB events not handled : {}", err);
+                    sleep(Duration::from_secs(1));
+                }
+            }
+        });
+    }
+
+    Ok(context)
+}
+
+struct TransferState {
+    device_request_tx: Sender<DeviceRequest>,
+    buffer: Vec<u8>,
+    cancelled: AtomicBool,
+    // set once the transfer won't be submitted again, so it can be freed
+    finished: AtomicBool,
+}
+
+// interrupt transfer kept submitted, each report being sent to the device thread as a request,
+// so the thread only wakes up on reports, requests, or when bindings have to be played
+pub struct InterruptTransfer {
+    transfer: *mut libusb_transfer,
+    state: *mut TransferState,
+}
+
+impl InterruptTransfer {
+    pub fn submit<T: UsbContext>(
+        device_handle: &DeviceHandle<T>,
+        endpoint: u8,
+        length: usize,
+        device_request_tx: Sender<DeviceRequest>,
+    ) -> rusb::Result<Self> {
+        let transfer = unsafe { libusb_alloc_transfer(0) };
+
+        if transfer.is_null() {
+            return Err(rusb::Error::NoMem);
+        }
+
+        let state = Box::into_raw(Box::new(TransferState {
+            device_request_tx,
+            buffer: vec![0; length],
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }));
+
+        unsafe {
+            libusb_fill_interrupt_transfer(
+                transfer,
+                device_handle.as_raw(),
+                endpoint,
+                (*state).buffer.as_mut_ptr(),
+                length as c_int,
+                transfer_callback,
+                state as *mut c_void,
+                0,
+            );
+
+            let result = libusb_submit_transfer(transfer);
+
+            if result != 0 {
+                libusb_free_transfer(transfer);
+                drop(Box::from_raw(state));
+
+                return Err(from_libusb(result));
+            }
+        }
+
+        Ok(Self { transfer, state })
+    }
+}
+
+impl Drop for InterruptTransfer {
+    fn drop(&mut self) {
+        let start = Instant::now();
+
+        unsafe {
+            (*self.state).cancelled.store(true, Ordering::SeqCst);
+
+            // cancelled again in case it was being submitted again from the callback
+            while !(*self.state).finished.load(Ordering::SeqCst) {
+                if start.elapsed() > CANCEL_TIMEOUT {
+                    return;
+                }
+
+                libusb_cancel_transfer(self.transfer);
+                sleep(Duration::from_millis(10));
+            }
+
+            libusb_free_transfer(self.transfer);
+            drop(Box::from_raw(self.state));
+        }
+    }
+}
+
+// called from the events thread
+extern "system" fn transfer_callback(transfer: *mut libusb_transfer) {
+    unsafe {
+        let state = &*((*transfer).user_data as *const TransferState);
+        let device_request = match (*transfer).status {
+            LIBUSB_TRANSFER_COMPLETED => Some(DeviceRequest::Report(
+                state.buffer[..(*transfer).actual_length.max(0) as usize].to_vec(),
+            )),
+            LIBUSB_TRANSFER_CANCELLED => None,
+            LIBUSB_TRANSFER_NO_DEVICE => Some(DeviceRequest::ReadError(rusb::Error::NoDevice)),
+            LIBUSB_TRANSFER_STALL => Some(DeviceRequest::ReadError(rusb::Error::Pipe)),
+            LIBUSB_TRANSFER_OVERFLOW => Some(DeviceRequest::ReadError(rusb::Error::Overflow)),
+            _ => Some(DeviceRequest::ReadError(rusb::Error::Io)),
+        };
+        // the report is copied before the buffer is handed back to libusb
+        let submit_result = match device_request {
+            Some(DeviceRequest::Report(_)) if !state.cancelled.load(Ordering::SeqCst) => {
+                Some(libusb_submit_transfer(transfer))
+            }
+            _ => None,
+        };
+
+        if let Some(device_request) = device_request {
+            state.device_request_tx.send(device_request).ok();
+        }
+
+        match submit_result {
+            Some(0) => {}
+            Some(result) => {
+                state
+                    .device_request_tx
+                    .send(DeviceRequest::ReadError(from_libusb(result)))
+                    .ok();
+                state.finished.store(true, Ordering::SeqCst);
+            }
+            None => state.finished.store(true, Ordering::SeqCst),
+        }
+    }
+}
+
+fn from_libusb(result: c_int) -> rusb::Error {
+    match result {
+        LIBUSB_ERROR_NO_DEVICE => rusb::Error::NoDevice,
+        _ => rusb::Error::Io,
+    }
+}
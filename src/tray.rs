@@ -0,0 +1,563 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::mapper;
+use crate::websocket::{DriverEvent, EventSender};
+use crate::{ConnectedDevices, MousesConfig};
+
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use util::config::ConfigManager;
+use util::thread::MutexTrait;
+use zbus::zvariant::{ObjectPath, OwnedValue, StructureBuilder, Type, Value};
+use zbus::{dbus_interface, Connection, ConnectionBuilder, SignalContext};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+// opened from the tray, expected in the path
+const CLIENT_COMMAND: &str = "mad-rust";
+
+#[derive(Clone)]
+pub struct TrayService {
+    pub device_list_mutex: Arc<Mutex<ConnectedDevices>>,
+    pub mouses_config_mutex: Arc<tokio::sync::Mutex<ConfigManager<MousesConfig>>>,
+    pub mouses_config_state_id: Arc<AtomicU32>,
+}
+
+impl TrayService {
+    // name, serial number, active profile and profiles of the connected devices
+    async fn connected_devices(&self) -> Vec<(String, String, Option<String>, Vec<String>)> {
+        // the device list is released before awaiting the config, as the device detection
+        // locks them the other way around
+        let connected_vec: Vec<String> = self
+            .device_list_mutex
+            .lock_poisoned()
+            .keys()
+            .cloned()
+            .collect();
+        let mouses_config = self.mouses_config_mutex.lock().await;
+
+        mouses_config
+            .config
+            .iter()
+            .filter(|(serial_number, _)| connected_vec.contains(serial_number))
+            .map(|(serial_number, mouse_config)| {
+                (
+                    mouse_config.display_name(serial_number),
+                    serial_number.clone(),
+                    mouse_config.active_profile.clone(),
+                    mouse_config.profiles.keys().cloned().collect(),
+                )
+            })
+            .collect()
+    }
+
+    async fn tool_tip(&self) -> String {
+        let line_vec: Vec<String> = self
+            .connected_devices()
+            .await
+            .into_iter()
+            .map(|(name, _, active_profile, _)| {
+                format!(
+                    "{} : {}",
+                    name,
+                    active_profile.as_deref().unwrap_or("default profile")
+                )
+            })
+            .collect();
+        let mut tool_tip = if line_vec.is_empty() {
+            "No device connected".to_string()
+        } else {
+            line_vec.join("\n")
+        };
+
+        if mapper::is_paused() {
+            tool_tip.push_str("\nEmulation paused");
+        }
+
+        tool_tip
+    }
+
+    async fn menu(&self) -> Menu {
+        let mut menu = Menu::new();
+        let device_vec = self.connected_devices().await;
+
+        if device_vec.is_empty() {
+            menu.push(0, Menu::label("No device connected", false), None);
+        }
+
+        for (name, serial_number, active_profile, profile_vec) in device_vec {
+            let label = format!(
+                "{} : {}",
+                name,
+                active_profile.as_deref().unwrap_or("default profile")
+            );
+            let device_id = menu.push(0, Menu::submenu(&label), None);
+
+            menu.push(
+                device_id,
+                Menu::toggle("Default profile", "radio", active_profile.is_none()),
+                Some(TrayAction::ActivateProfile(serial_number.clone(), None)),
+            );
+
+            for profile in profile_vec {
+                menu.push(
+                    device_id,
+                    Menu::toggle(&profile, "radio", active_profile.as_ref() == Some(&profile)),
+                    Some(TrayAction::ActivateProfile(
+                        serial_number.clone(),
+                        Some(profile),
+                    )),
+                );
+            }
+        }
+
+        menu.push(0, Menu::separator(), None);
+        menu.push(
+            0,
+            Menu::toggle("Pause emulation", "checkmark", mapper::is_paused()),
+            Some(TrayAction::TogglePause),
+        );
+        menu.push(
+            0,
+            Menu::label("Open Mad Rust", true),
+            Some(TrayAction::OpenClient),
+        );
+
+        menu
+    }
+
+    async fn run_action(&self, action: TrayAction) {
+        match action {
+            TrayAction::ActivateProfile(serial_number, profile) => {
+                let mut mouses_config = self.mouses_config_mutex.lock().await;
+
+                if let Some(mouse_config) = mouses_config.config.get_mut(&serial_number) {
+                    if mouse_config.activate_profile(profile.as_deref()).is_ok() {
+                        self.mouses_config_state_id.fetch_add(1, Ordering::SeqCst);
+                        mouses_config.save();
+                    }
+                }
+            }
+            TrayAction::TogglePause => mapper::set_paused(!mapper::is_paused()),
+            TrayAction::OpenClient => open_client(),
+        }
+    }
+}
+
+fn open_client() {
+    if let Err(err) = Command::new(CLIENT_COMMAND).spawn() {
+        println!("{} not opened : {}", CLIENT_COMMAND, err);
+    }
+}
+
+#[derive(Clone, Debug)]
+enum TrayAction {
+    // the default profile when none
+    ActivateProfile(String, Option<String>),
+    TogglePause,
+    OpenClient,
+}
+
+// the entries of the menu by id, the root being 0
+struct Menu {
+    entry_vec: Vec<MenuEntry>,
+}
+
+struct MenuEntry {
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<i32>,
+    action: Option<TrayAction>,
+}
+
+// id, properties and children of an entry, as expected by the dbusmenu protocol
+#[derive(Serialize, Type)]
+struct MenuLayout {
+    id: i32,
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<OwnedValue>,
+}
+
+impl Menu {
+    fn new() -> Self {
+        Self {
+            entry_vec: vec![MenuEntry {
+                properties: Self::submenu(""),
+                children: vec![],
+                action: None,
+            }],
+        }
+    }
+
+    fn push(
+        &mut self,
+        parent: i32,
+        properties: HashMap<String, OwnedValue>,
+        action: Option<TrayAction>,
+    ) -> i32 {
+        let id = self.entry_vec.len() as i32;
+
+        self.entry_vec[parent as usize].children.push(id);
+        self.entry_vec.push(MenuEntry {
+            properties,
+            children: vec![],
+            action,
+        });
+
+        id
+    }
+
+    fn label(label: &str, enabled: bool) -> HashMap<String, OwnedValue> {
+        HashMap::from([
+            ("label".to_string(), Value::from(label).into()),
+            ("enabled".to_string(), Value::from(enabled).into()),
+        ])
+    }
+
+    fn submenu(label: &str) -> HashMap<String, OwnedValue> {
+        let mut properties = Self::label(label, true);
+
+        properties.insert(
+            "children-display".to_string(),
+            Value::from("submenu").into(),
+        );
+        properties
+    }
+
+    // radio or checkmark
+    fn toggle(label: &str, toggle_type: &str, state: bool) -> HashMap<String, OwnedValue> {
+        let mut properties = Self::label(label, true);
+
+        properties.insert("toggle-type".to_string(), Value::from(toggle_type).into());
+        properties.insert("toggle-state".to_string(), Value::from(state as i32).into());
+        properties
+    }
+
+    fn separator() -> HashMap<String, OwnedValue> {
+        HashMap::from([("type".to_string(), Value::from("separator").into())])
+    }
+
+    fn entry(&self, id: i32) -> Option<&MenuEntry> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|index| self.entry_vec.get(index))
+    }
+
+    // down to the given depth, the whole tree when negative
+    fn layout(&self, id: i32, depth: i32) -> MenuLayout {
+        let entry = match self.entry(id) {
+            Some(entry) => entry,
+            None => return self.layout(0, depth),
+        };
+        let children = if depth == 0 {
+            vec![]
+        } else {
+            entry
+                .children
+                .iter()
+                .map(|child_id| {
+                    let layout = self.layout(*child_id, depth - 1);
+
+                    Value::from(
+                        StructureBuilder::new()
+                            .add_field(layout.id)
+                            .add_field(layout.properties)
+                            .add_field(layout.children)
+                            .build(),
+                    )
+                    .into()
+                })
+                .collect()
+        };
+
+        MenuLayout {
+            id,
+            properties: entry.properties.clone(),
+            children,
+        }
+    }
+}
+
+// the status notifier item shown by the tray of the desktop
+struct TrayItem {
+    tray_service: TrayService,
+}
+
+#[dbus_interface(name = "org.kde.StatusNotifierItem")]
+impl TrayItem {
+    #[dbus_interface(property)]
+    fn category(&self) -> &str {
+        "Hardware"
+    }
+
+    #[dbus_interface(property)]
+    fn id(&self) -> &str {
+        "mad-rust-mmo7-driver"
+    }
+
+    #[dbus_interface(property)]
+    fn title(&self) -> &str {
+        "Mad Rust MMO7"
+    }
+
+    #[dbus_interface(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[dbus_interface(property)]
+    fn icon_name(&self) -> &str {
+        "input-mouse"
+    }
+
+    #[dbus_interface(property)]
+    fn icon_pixmap(&self) -> Vec<(i32, i32, Vec<u8>)> {
+        vec![]
+    }
+
+    #[dbus_interface(property)]
+    async fn tool_tip(&self) -> (String, Vec<(i32, i32, Vec<u8>)>, String, String) {
+        (
+            String::new(),
+            vec![],
+            "Mad Rust MMO7".to_string(),
+            self.tray_service.tool_tip().await,
+        )
+    }
+
+    #[dbus_interface(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn menu(&self) -> ObjectPath<'_> {
+        ObjectPath::from_static_str_unchecked(MENU_PATH)
+    }
+
+    fn activate(&self, _x: i32, _y: i32) {
+        open_client();
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[dbus_interface(signal)]
+    async fn new_tool_tip(context: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+// the menu of the item, following the dbusmenu protocol
+struct TrayMenu {
+    tray_service: TrayService,
+    revision: Arc<AtomicU32>,
+    // actions of the last menu sent, by id
+    action_vec_mutex: Arc<Mutex<Vec<Option<TrayAction>>>>,
+}
+
+impl TrayMenu {
+    async fn menu(&self) -> Menu {
+        let menu = self.tray_service.menu().await;
+
+        *self.action_vec_mutex.lock_poisoned() = menu
+            .entry_vec
+            .iter()
+            .map(|entry| entry.action.clone())
+            .collect();
+
+        menu
+    }
+
+    async fn click(&self, id: i32, event_id: &str) -> bool {
+        let action = self
+            .action_vec_mutex
+            .lock_poisoned()
+            .get(id as usize)
+            .cloned();
+
+        match action {
+            Some(Some(action)) if event_id == "clicked" => {
+                self.tray_service.run_action(action).await;
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+}
+
+#[dbus_interface(name = "com.canonical.dbusmenu")]
+impl TrayMenu {
+    #[dbus_interface(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[dbus_interface(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[dbus_interface(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[dbus_interface(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        vec![]
+    }
+
+    async fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> (u32, MenuLayout) {
+        (
+            self.revision.load(Ordering::SeqCst),
+            self.menu().await.layout(parent_id, recursion_depth),
+        )
+    }
+
+    async fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let menu = self.menu().await;
+
+        ids.into_iter()
+            .filter_map(|id| menu.entry(id).map(|entry| (id, entry.properties.clone())))
+            .collect()
+    }
+
+    async fn get_property(&self, id: i32, name: &str) -> OwnedValue {
+        self.menu()
+            .await
+            .entry(id)
+            .and_then(|entry| entry.properties.get(name).cloned())
+            .unwrap_or_else(|| Value::from("").into())
+    }
+
+    async fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+        self.click(id, event_id).await;
+    }
+
+    // returns the ids which weren't found
+    async fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        let mut error_vec = vec![];
+
+        for (id, event_id, _, _) in events {
+            if !self.click(id, &event_id).await {
+                error_vec.push(id);
+            }
+        }
+
+        error_vec
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (vec![], vec![])
+    }
+
+    #[dbus_interface(signal)]
+    async fn layout_updated(
+        context: &SignalContext<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}
+
+// only shown in a desktop session having a tray, the item is registered on the session bus
+pub async fn run_tray(tray_service: TrayService, event_tx: EventSender) {
+    let revision = Arc::new(AtomicU32::new(0));
+    let connection = match connect(tray_service.clone(), revision.clone()).await {
+        Ok(connection) => connection,
+        Err(err) => {
+            println!("Tray icon not shown : {}", err);
+            return;
+        }
+    };
+    let mut event_rx = event_tx.subscribe();
+
+    tokio::spawn(async move {
+        let (item_context, menu_context) = match (
+            SignalContext::new(&connection, ITEM_PATH),
+            SignalContext::new(&connection, MENU_PATH),
+        ) {
+            (Ok(item_context), Ok(menu_context)) => (item_context, menu_context),
+            _ => return,
+        };
+        let mut paused = mapper::is_paused();
+
+        loop {
+            // the pause isn't an event, it's checked along with them
+            let event = tokio::select! {
+                event = event_rx.recv() => Some(event),
+                _ = tokio::time::sleep(util::time::TIMEOUT_1S) => None,
+            };
+
+            match event {
+                Some(Ok(DriverEvent::Device { .. })) | Some(Ok(DriverEvent::Profile { .. })) => {}
+                Some(Ok(_)) | Some(Err(RecvError::Lagged(_))) => continue,
+                Some(Err(RecvError::Closed)) => return,
+                None if paused == mapper::is_paused() => continue,
+                None => {}
+            }
+
+            paused = mapper::is_paused();
+
+            let revision = revision.fetch_add(1, Ordering::SeqCst) + 1;
+
+            TrayMenu::layout_updated(&menu_context, revision, 0)
+                .await
+                .ok();
+            TrayItem::new_tool_tip(&item_context).await.ok();
+        }
+    });
+}
+
+async fn connect(tray_service: TrayService, revision: Arc<AtomicU32>) -> zbus::Result<Connection> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let connection = ConnectionBuilder::session()?
+        .name(name.as_str())?
+        .serve_at(
+            ITEM_PATH,
+            TrayItem {
+                tray_service: tray_service.clone(),
+            },
+        )?
+        .serve_at(
+            MENU_PATH,
+            TrayMenu {
+                tray_service,
+                revision,
+                action_vec_mutex: Arc::new(Mutex::new(vec![])),
+            },
+        )?
+        .build()
+        .await?;
+
+    connection
+        .call_method(
+            Some(WATCHER_NAME),
+            WATCHER_PATH,
+            Some(WATCHER_NAME),
+            "RegisterStatusNotifierItem",
+            &(name.as_str()),
+        )
+        .await?;
+
+    Ok(connection)
+}
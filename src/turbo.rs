@@ -0,0 +1,125 @@
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::spawn;
+use std::time::Instant;
+
+use crate::metrics::Metrics;
+use crate::tokenizer::{StateToken, Token, Turbo};
+
+use thread_priority::{set_current_thread_priority, ThreadPriority};
+
+pub enum TurboCommand {
+    // with the down and up tokens already handled by the mapper
+    Start(Arc<StateToken>, Turbo, Vec<Token>, Vec<Token>),
+    Stop(Arc<StateToken>),
+    StopAll,
+}
+
+struct ActiveTurbo {
+    state_token: Arc<StateToken>,
+    turbo: Turbo,
+    down: Vec<Token>,
+    up: Vec<Token>,
+    pressed: bool,
+    next_toggle: Instant,
+}
+
+// plays the turbo bindings on their own schedule, the mapper only being woken up
+// by the reports and its tick, the tokens going through the emulation worker like the others
+pub fn spawn_worker(
+    emulation_worker_rx: Sender<(Instant, Vec<Token>)>,
+    metrics: Arc<Metrics>,
+) -> Sender<TurboCommand> {
+    let (turbo_worker_rx, turbo_worker_tx) = channel();
+
+    spawn(move || {
+        set_current_thread_priority(ThreadPriority::Max).ok();
+
+        let queue_token_vec = |token_vec: Vec<Token>| {
+            metrics.token_vec_queued();
+            emulation_worker_rx.send((Instant::now(), token_vec)).ok();
+        };
+        let mut active_turbo_vec: Vec<ActiveTurbo> = vec![];
+
+        loop {
+            let next_toggle = active_turbo_vec
+                .iter()
+                .map(|active_turbo| active_turbo.next_toggle)
+                .min();
+            let command = match next_toggle {
+                Some(next_toggle) => turbo_worker_tx
+                    .recv_timeout(next_toggle.saturating_duration_since(Instant::now())),
+                None => turbo_worker_tx
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let disconnected = matches!(command, Err(RecvTimeoutError::Disconnected));
+            // the stopped bindings are released if they were pressed
+            let stopped_vec: Vec<ActiveTurbo> = match command {
+                Ok(TurboCommand::Start(state_token, turbo, down, up)) => {
+                    active_turbo_vec.push(ActiveTurbo {
+                        state_token,
+                        turbo,
+                        down,
+                        up,
+                        pressed: false,
+                        next_toggle: Instant::now(),
+                    });
+                    vec![]
+                }
+                Ok(TurboCommand::Stop(state_token)) => {
+                    let (stopped_vec, active_vec) =
+                        active_turbo_vec.into_iter().partition(|active_turbo| {
+                            Arc::ptr_eq(&active_turbo.state_token, &state_token)
+                        });
+
+                    active_turbo_vec = active_vec;
+                    stopped_vec
+                }
+                Ok(TurboCommand::StopAll) | Err(RecvTimeoutError::Disconnected) => {
+                    std::mem::take(&mut active_turbo_vec)
+                }
+                Err(RecvTimeoutError::Timeout) => vec![],
+            };
+
+            for stopped in stopped_vec {
+                if stopped.pressed {
+                    queue_token_vec(stopped.up);
+                }
+            }
+
+            // the mapper is gone
+            if disconnected {
+                break;
+            }
+
+            let now = Instant::now();
+
+            for active_turbo in &mut active_turbo_vec {
+                if active_turbo.next_toggle > now {
+                    continue;
+                }
+
+                let duration = if active_turbo.pressed {
+                    queue_token_vec(active_turbo.up.clone());
+                    active_turbo.turbo.released
+                } else {
+                    queue_token_vec(active_turbo.down.clone());
+                    active_turbo.turbo.pressed
+                };
+
+                // restarts from now when late, rather than catching up with a burst
+                let next_toggle = active_turbo.next_toggle + duration;
+
+                active_turbo.pressed = !active_turbo.pressed;
+                active_turbo.next_toggle = if next_toggle > now {
+                    next_toggle
+                } else {
+                    now + duration
+                };
+            }
+        }
+    });
+
+    turbo_worker_rx
+}
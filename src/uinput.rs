@@ -0,0 +1,297 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem::size_of;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+
+use crate::backend::{Backend, NOTCH_DELTA};
+use crate::clipboard;
+use crate::keycode::{
+    button_code, key_code, BTN_EXTRA, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, BTN_SIDE, EV_ABS, EV_KEY,
+    EV_REL, EV_SYN, GAMEPAD_AXES, GAMEPAD_BUTTONS, KEY_LEFTCTRL, KEY_RANGE, KEY_V, REL_HWHEEL,
+    REL_HWHEEL_HI_RES, REL_WHEEL, REL_WHEEL_HI_RES, REL_X, REL_Y, SYN_REPORT,
+};
+use crate::scancode::{char_to_scancode, type_char};
+use crate::tokenizer::Button;
+
+use enigo::Key;
+use util::thread::MutexTrait;
+
+const DEVICE_NAME: &[u8] = b"Mad Rust MMO7 Driver";
+const GAMEPAD_NAME: &[u8] = b"Mad Rust MMO7 Driver Gamepad";
+
+// ioctl requests from linux/uinput.h
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+const UI_SET_EVBIT: libc::c_ulong = 0x40045564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x40045565;
+const UI_SET_RELBIT: libc::c_ulong = 0x40045566;
+const UI_SET_ABSBIT: libc::c_ulong = 0x40045567;
+
+const BUS_VIRTUAL: u16 = 0x06;
+
+// virtual input device, writes are atomic so it is shared without locking
+pub struct UinputDevice {
+    file: File,
+    // smooth scroll deltas not adding up to a notch yet, for the readers of the notches only
+    scroll_remainder: Mutex<(i32, i32)>,
+}
+
+impl UinputDevice {
+    // mouse and keyboard
+    pub fn new() -> io::Result<Self> {
+        Self::create(
+            DEVICE_NAME,
+            &KEY_RANGE
+                .chain([BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_SIDE, BTN_EXTRA])
+                .collect::<Vec<u16>>(),
+            &[
+                REL_X,
+                REL_Y,
+                REL_HWHEEL,
+                REL_WHEEL,
+                REL_HWHEEL_HI_RES,
+                REL_WHEEL_HI_RES,
+            ],
+            &[],
+        )
+    }
+
+    // xbox like controller
+    pub fn new_gamepad() -> io::Result<Self> {
+        Self::create(GAMEPAD_NAME, &GAMEPAD_BUTTONS, &[], &GAMEPAD_AXES)
+    }
+
+    // axes are given with their code, minimum and maximum
+    fn create(
+        name: &[u8],
+        key_codes: &[u16],
+        rel_codes: &[u16],
+        abs_axes: &[(u16, i32, i32)],
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")?;
+        let fd = file.as_raw_fd();
+        let ioctl = |request: libc::c_ulong, value: u16| -> io::Result<()> {
+            if unsafe { libc::ioctl(fd, request, value as libc::c_int) } < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        };
+        let mut user_dev: libc::uinput_user_dev = unsafe { std::mem::zeroed() };
+
+        if !key_codes.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_KEY)?;
+        }
+        if !rel_codes.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_REL)?;
+        }
+        if !abs_axes.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_ABS)?;
+        }
+
+        for code in key_codes {
+            ioctl(UI_SET_KEYBIT, *code)?;
+        }
+        for code in rel_codes {
+            ioctl(UI_SET_RELBIT, *code)?;
+        }
+        for (code, minimum, maximum) in abs_axes {
+            ioctl(UI_SET_ABSBIT, *code)?;
+            user_dev.absmin[*code as usize] = *minimum;
+            user_dev.absmax[*code as usize] = *maximum;
+        }
+
+        for (index, byte) in name.iter().enumerate() {
+            user_dev.name[index] = *byte as libc::c_char;
+        }
+        user_dev.id.bustype = BUS_VIRTUAL;
+
+        (&file).write_all(unsafe { as_bytes(&user_dev) })?;
+
+        if unsafe { libc::ioctl(fd, UI_DEV_CREATE) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            file,
+            scroll_remainder: Mutex::new((0, 0)),
+        })
+    }
+
+    // events are followed by a sync report and written in one go
+    pub fn emit(&self, events: &[(u16, u16, i32)]) {
+        let mut data = vec![];
+
+        for (type_, code, value) in events.iter().copied().chain([(EV_SYN, SYN_REPORT, 0)]) {
+            let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+
+            event.type_ = type_;
+            event.code = code;
+            event.value = value;
+            data.extend_from_slice(unsafe { as_bytes(&event) });
+        }
+
+        if let Err(err) = (&self.file).write_all(&data) {
+            println!("uinput write error : {}", err);
+        }
+    }
+
+    fn key(&self, code: u16, pressed: bool) {
+        self.emit(&[(EV_KEY, code, pressed as i32)]);
+    }
+
+    fn type_char(&self, char: char) {
+        type_char(char, |code, pressed| self.key(code, pressed));
+    }
+
+    // the virtual keyboard only has the keys of a us keyboard, so the other characters are
+    // pasted with the rest of the text, keeping it in order
+    fn paste(&self, text: &str) {
+        if let Err(err) = clipboard::copy(text) {
+            println!("text not pasted : {}", err);
+            return;
+        }
+
+        sleep(clipboard::PASTE_DELAY);
+        self.key(KEY_LEFTCTRL, true);
+        self.key(KEY_V, true);
+        self.key(KEY_V, false);
+        self.key(KEY_LEFTCTRL, false);
+    }
+}
+
+impl Drop for UinputDevice {
+    fn drop(&mut self) {
+        unsafe { libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY) };
+    }
+}
+
+unsafe fn as_bytes<T>(value: &T) -> &[u8] {
+    std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>())
+}
+
+impl Backend for Arc<UinputDevice> {
+    fn mouse_move_relative(&mut self, x: i32, y: i32) {
+        self.emit(&[(EV_REL, REL_X, x), (EV_REL, REL_Y, y)]);
+    }
+
+    fn mouse_down(&mut self, button: Button) {
+        if let Some(code) = button_code(button) {
+            self.key(code, true);
+        }
+    }
+
+    fn mouse_up(&mut self, button: Button) {
+        if let Some(code) = button_code(button) {
+            self.key(code, false);
+        }
+    }
+
+    fn mouse_click(&mut self, button: Button) {
+        if let Some(code) = button_code(button) {
+            self.key(code, true);
+            self.key(code, false);
+        }
+    }
+
+    // enigo scrolls right and down on positive lengths, libinput only reading the high
+    // resolution events of the devices having them
+    fn mouse_scroll_x(&mut self, length: i32) {
+        self.emit(&[
+            (EV_REL, REL_HWHEEL, length),
+            (EV_REL, REL_HWHEEL_HI_RES, length * NOTCH_DELTA),
+        ]);
+    }
+
+    fn mouse_scroll_y(&mut self, length: i32) {
+        self.emit(&[
+            (EV_REL, REL_WHEEL, -length),
+            (EV_REL, REL_WHEEL_HI_RES, -length * NOTCH_DELTA),
+        ]);
+    }
+
+    // the notch is sent along once the deltas add up to one, like the high resolution wheels
+    fn mouse_smooth_scroll_x(&mut self, delta: i32) {
+        let length = {
+            let mut scroll_remainder = self.scroll_remainder.lock_poisoned();
+
+            scroll_remainder.0 += delta;
+
+            let length = scroll_remainder.0 / NOTCH_DELTA;
+
+            scroll_remainder.0 -= length * NOTCH_DELTA;
+            length
+        };
+
+        self.emit(&[
+            (EV_REL, REL_HWHEEL, length),
+            (EV_REL, REL_HWHEEL_HI_RES, delta),
+        ]);
+    }
+
+    fn mouse_smooth_scroll_y(&mut self, delta: i32) {
+        let length = {
+            let mut scroll_remainder = self.scroll_remainder.lock_poisoned();
+
+            scroll_remainder.1 += delta;
+
+            let length = scroll_remainder.1 / NOTCH_DELTA;
+
+            scroll_remainder.1 -= length * NOTCH_DELTA;
+            length
+        };
+
+        self.emit(&[
+            (EV_REL, REL_WHEEL, -length),
+            (EV_REL, REL_WHEEL_HI_RES, -delta),
+        ]);
+    }
+
+    fn key_sequence(&mut self, sequence: &str) {
+        if !sequence
+            .chars()
+            .all(|char| char_to_scancode(char).is_some())
+        {
+            return self.paste(sequence);
+        }
+
+        for char in sequence.chars() {
+            self.type_char(char);
+        }
+    }
+
+    fn key_down(&mut self, key: Key) {
+        if let Some(code) = key_code(key) {
+            self.key(code, true);
+        }
+    }
+
+    fn key_up(&mut self, key: Key) {
+        if let Some(code) = key_code(key) {
+            self.key(code, false);
+        }
+    }
+
+    // the layout mode types the same keys, the system layout applying to the virtual keyboard
+    fn scancode_click(&mut self, char: char) {
+        self.type_char(char);
+    }
+
+    fn key_click(&mut self, key: Key) {
+        match key {
+            Key::Layout(char) => self.type_char(char),
+            key => {
+                if let Some(code) = key_code(key) {
+                    self.key(code, true);
+                    self.key(code, false);
+                }
+            }
+        }
+    }
+}
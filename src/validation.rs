@@ -0,0 +1,315 @@
+use std::fmt;
+use std::fs;
+
+use crate::config_watcher::profiles_path;
+use crate::mapper::check_binding;
+use crate::model::{DEVICE_MODELS, WHEEL_KEYS};
+use crate::tokenizer::IF_PREFIX;
+use crate::{ButtonConfigs, MouseConfig, MousesConfig};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    // the file for syntax errors, the path of the field otherwise
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {}", self.location, self.message)
+    }
+}
+
+// syntax error of the profiles file, with its line and column
+pub fn check_file() -> Option<ConfigError> {
+    let path = profiles_path()?;
+    let data = fs::read_to_string(&path).ok()?;
+
+    serde_json::from_str::<MousesConfig>(&data)
+        .err()
+        .map(|err| ConfigError {
+            location: path.display().to_string(),
+            message: err.to_string(),
+        })
+}
+
+// everything the driver would otherwise silently ignore
+pub fn config_errors(mouses_config: &MousesConfig) -> Vec<ConfigError> {
+    let mut error_vec: Vec<ConfigError> = check_file().into_iter().collect();
+
+    for (serial_number, mouse_config) in mouses_config {
+        check_mouse_config(serial_number, mouse_config, &mut error_vec);
+    }
+
+    error_vec
+}
+
+// the bindings of a profile about to be added to the mouse under this name
+pub fn profile_errors(
+    location: &str,
+    profile: &str,
+    button_configs: &ButtonConfigs,
+    mouse_config: &MouseConfig,
+) -> Vec<ConfigError> {
+    let mut error_vec = vec![];
+    let profile_exists = |name: &str| name == profile || mouse_config.profiles.contains_key(name);
+
+    check_button_configs(
+        location,
+        button_configs,
+        mouse_config,
+        &profile_exists,
+        &mut |location, message| error_vec.push(ConfigError { location, message }),
+    );
+
+    error_vec
+}
+
+fn check_mouse_config(
+    serial_number: &str,
+    mouse_config: &MouseConfig,
+    error_vec: &mut Vec<ConfigError>,
+) {
+    let mut push = |location: String, message: String| {
+        error_vec.push(ConfigError { location, message });
+    };
+
+    if let Some(model) = &mouse_config.model {
+        if !DEVICE_MODELS
+            .iter()
+            .any(|device_model| device_model.name == model)
+        {
+            push(
+                format!("{}.model", serial_number),
+                format!("unknown model {}", model),
+            );
+        }
+    }
+
+    let device_model = mouse_config.device_model();
+    let profile_exists = |profile: &str| mouse_config.profiles.contains_key(profile);
+    let profile_vec = [(serial_number.to_string(), &mouse_config.button_configs)]
+        .into_iter()
+        .chain(
+            mouse_config
+                .profiles
+                .iter()
+                .map(|(profile, button_configs)| {
+                    (
+                        format!("{}.profiles.{}", serial_number, profile),
+                        button_configs,
+                    )
+                }),
+        );
+
+    for (location, button_configs) in profile_vec {
+        check_button_configs(
+            &location,
+            button_configs,
+            mouse_config,
+            &profile_exists,
+            &mut push,
+        );
+    }
+
+    for (index, window_rule) in mouse_config.window_rules.iter().enumerate() {
+        if !profile_exists(&window_rule.profile) {
+            push(
+                format!("{}.window_rules[{}]", serial_number, index),
+                format!("unknown profile {}", window_rule.profile),
+            );
+        }
+    }
+
+    for (index, process_rule) in mouse_config.process_rules.iter().enumerate() {
+        if !profile_exists(&process_rule.profile) {
+            push(
+                format!("{}.process_rules[{}]", serial_number, index),
+                format!("unknown profile {}", process_rule.profile),
+            );
+        }
+    }
+
+    for profile in &mouse_config.shared_profiles {
+        if !profile_exists(profile) {
+            push(
+                format!("{}.shared_profiles", serial_number),
+                format!("unknown profile {}", profile),
+            );
+        }
+    }
+
+    for (field, profile) in [
+        ("selected_profile", &mouse_config.selected_profile),
+        (
+            "process_fallback_profile",
+            &mouse_config.process_fallback_profile,
+        ),
+    ] {
+        if let Some(profile) = profile {
+            if !profile_exists(profile) {
+                push(
+                    format!("{}.{}", serial_number, field),
+                    format!("unknown profile {}", profile),
+                );
+            }
+        }
+    }
+
+    let setting_error_vec = mouse_config
+        .settings
+        .check()
+        .into_iter()
+        .chain(mouse_config.settings.unsupported(device_model));
+
+    for (field, message) in setting_error_vec {
+        push(format!("{}.{}", serial_number, field), message);
+    }
+
+    let led_colors_vec = [(
+        format!("{}.led_colors", serial_number),
+        &mouse_config.settings.led_colors,
+    )]
+    .into_iter()
+    .chain(
+        mouse_config
+            .profile_led_colors
+            .iter()
+            .map(|(profile, led_colors)| {
+                (
+                    format!("{}.profile_led_colors.{}", serial_number, profile),
+                    led_colors,
+                )
+            }),
+    );
+
+    for (location, led_colors) in led_colors_vec {
+        if led_colors.is_empty() {
+            continue;
+        }
+
+        if device_model.led_report_id.is_none() {
+            push(
+                location,
+                format!("the {} leds can't be changed", device_model.name),
+            );
+        } else if led_colors.len() > device_model.mode_count as usize {
+            push(
+                location,
+                format!(
+                    "the {} has {} modes",
+                    device_model.name, device_model.mode_count
+                ),
+            );
+        }
+    }
+
+    for profile in mouse_config.profile_led_colors.keys() {
+        if !profile_exists(profile) {
+            push(
+                format!("{}.profile_led_colors.{}", serial_number, profile),
+                format!("unknown profile {}", profile),
+            );
+        }
+    }
+}
+
+fn check_button_configs(
+    location: &str,
+    button_configs: &ButtonConfigs,
+    mouse_config: &MouseConfig,
+    profile_exists: &impl Fn(&str) -> bool,
+    push: &mut impl FnMut(String, String),
+) {
+    let device_model = mouse_config.device_model();
+
+    let key_vec = device_model
+        .buttons
+        .iter()
+        .map(|button| button.key)
+        .chain(WHEEL_KEYS);
+
+    for key in key_vec {
+        let button_config = match button_configs.get(key) {
+            Some(button_config) => button_config,
+            None => continue,
+        };
+
+        for (shift_index, binding_vec) in button_config.iter().enumerate() {
+            for (mode_index, binding) in binding_vec.iter().enumerate() {
+                for message in check_binding(binding, profile_exists) {
+                    push(
+                        format!("{}.{}[{}][{}]", location, key, shift_index, mode_index),
+                        message,
+                    );
+                }
+            }
+        }
+    }
+
+    for (field, key_vec) in [
+        ("repeat_interval", button_configs.repeat_interval.keys()),
+        ("debounce", button_configs.debounce.keys()),
+    ] {
+        for key in key_vec {
+            if device_model.button_index(key).is_none() {
+                push(
+                    format!("{}.{}", location, field),
+                    format!("unknown button {}", key),
+                );
+            }
+        }
+    }
+    if let Some(factor) = button_configs.precision_aim_factor {
+        if factor <= 0.0 {
+            push(
+                format!("{}.precision_aim_factor", location),
+                "expects a factor above 0".to_string(),
+            );
+        }
+    }
+    if let Some(lines) = button_configs.smooth_scroll {
+        if lines <= 0.0 {
+            push(
+                format!("{}.smooth_scroll", location),
+                "expects a number of lines above 0".to_string(),
+            );
+        }
+    }
+    if let Some(message) = button_configs
+        .response_curve
+        .as_ref()
+        .and_then(|response_curve| response_curve.check())
+    {
+        push(format!("{}.response_curve", location), message.to_string());
+    }
+
+    for (index, chord) in button_configs.chords.iter().enumerate() {
+        let location = format!("{}.chords[{}]", location, index);
+
+        if chord.buttons.len() < 2 {
+            push(
+                location.clone(),
+                "a chord needs two buttons or more".to_string(),
+            );
+        }
+
+        for button in &chord.buttons {
+            if device_model.button_index(button).is_none() {
+                push(location.clone(), format!("unknown button {}", button));
+            }
+        }
+
+        for message in check_binding(&chord.binding, profile_exists) {
+            push(location.clone(), message);
+        }
+
+        // chords don't depend on the mode
+        if chord.binding.contains(IF_PREFIX) {
+            push(
+                location.clone(),
+                "conditional blocks only work in button bindings".to_string(),
+            );
+        }
+    }
+}
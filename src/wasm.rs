@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use wasmtime::{Caller, Config, Engine, Linker, Module, Store};
+
+use crate::status;
+use crate::tokenizer::{input_token, parse_click_button, Token};
+
+// a binding starting with this prefix runs the on_press and on_release functions of a wasm module
+pub const WASM_PREFIX: &str = "{wasm}";
+// namespace of the host functions imported by the modules
+const HOST_MODULE: &str = "mmo7";
+
+// bound the work a single press can do, an instruction costing about one unit
+const MAX_FUEL: u64 = 10_000_000;
+
+// compiled once, the modules being sandboxed by the engine, with no access to the system
+static ENGINE: OnceLock<Option<Engine>> = OnceLock::new();
+
+fn engine() -> Option<&'static Engine> {
+    ENGINE
+        .get_or_init(|| {
+            let mut config = Config::new();
+
+            config.consume_fuel(true);
+
+            match Engine::new(&config) {
+                Ok(engine) => Some(engine),
+                Err(err) => {
+                    status::error(format!("Wasm engine not started : {}", err));
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+// call of a module, run by the emulation worker, so it never blocks the reports
+#[derive(Debug, Clone)]
+pub struct WasmCall {
+    pub module: Arc<Module>,
+    pub function: &'static str,
+    pub mode: u8,
+    pub shift: bool,
+}
+
+// relative paths are looked for in the module directory
+pub fn module_path(path: &str) -> PathBuf {
+    let path = PathBuf::from(path.trim());
+
+    if path.is_absolute() {
+        return path;
+    }
+
+    dirs::config_dir()
+        .map(|config_dir| config_dir.join("mad-rust").join("mmo7_wasm").join(&path))
+        .unwrap_or(path)
+}
+
+pub fn check(path: &str) -> Vec<String> {
+    if path.trim().is_empty() {
+        return vec![format!("{} expects the path of a module", WASM_PREFIX)];
+    }
+
+    let module_path = module_path(path);
+    let engine = match engine() {
+        Some(engine) => engine,
+        None => return vec!["wasm engine not started".to_string()],
+    };
+
+    match Module::from_file(engine, &module_path) {
+        Ok(module)
+            if ["on_press", "on_release"]
+                .iter()
+                .all(|function| module.get_export(function).is_none()) =>
+        {
+            vec![format!(
+                "module {} exports neither on_press nor on_release",
+                module_path.display()
+            )]
+        }
+        Ok(_) => vec![],
+        Err(err) => vec![format!(
+            "module {} not loaded : {}",
+            module_path.display(),
+            err
+        )],
+    }
+}
+
+pub fn load(path: &str) -> Option<Arc<Module>> {
+    let module_path = module_path(path);
+
+    match Module::from_file(engine()?, &module_path) {
+        Ok(module) => Some(Arc::new(module)),
+        Err(err) => {
+            println!("Wasm module {} not loaded : {}", module_path.display(), err);
+            None
+        }
+    }
+}
+
+// instantiated for each call, so nothing is kept between two calls,
+// the tokens pushed by the host functions being returned once it's over
+pub fn run(wasm_call: &WasmCall) -> Vec<Token> {
+    let engine = match engine() {
+        Some(engine) => engine,
+        None => return vec![],
+    };
+    let mut store = Store::new(engine, vec![]);
+    let mut linker = Linker::new(engine);
+
+    store.set_fuel(MAX_FUEL).ok();
+
+    if let Err(err) = link_host_functions(&mut linker) {
+        status::error(format!("Wasm host functions not linked : {}", err));
+        return vec![];
+    }
+
+    let result = linker
+        .instantiate(&mut store, &wasm_call.module)
+        .and_then(|instance| {
+            // a module can leave out one of the functions
+            match instance.get_typed_func::<(i32, i32), ()>(&mut store, wasm_call.function) {
+                Ok(function) => function.call(
+                    &mut store,
+                    (wasm_call.mode as i32 + 1, wasm_call.shift as i32),
+                ),
+                Err(_) => Ok(()),
+            }
+        });
+
+    if let Err(err) = result {
+        status::error(format!("Wasm module error : {}", err));
+    }
+
+    store.into_data()
+}
+
+// `press`, `release`, `click` and `type` take the address and the length of a utf-8 string
+// in the memory exported by the module, like the functions of the rhai bindings
+fn link_host_functions(linker: &mut Linker<Vec<Token>>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "press",
+        |mut caller: Caller<'_, Vec<Token>>, address: i32, length: i32| {
+            if let Some(token) = read_string(&mut caller, address, length)
+                .and_then(|input| input_token(&input, true))
+            {
+                caller.data_mut().push(token);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "release",
+        |mut caller: Caller<'_, Vec<Token>>, address: i32, length: i32| {
+            if let Some(token) = read_string(&mut caller, address, length)
+                .and_then(|input| input_token(&input, false))
+            {
+                caller.data_mut().push(token);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "click",
+        |mut caller: Caller<'_, Vec<Token>>, address: i32, length: i32| {
+            if let Some(button) = read_string(&mut caller, address, length)
+                .and_then(|input| parse_click_button(&input))
+            {
+                caller.data_mut().push(Token::Click(button));
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "type",
+        |mut caller: Caller<'_, Vec<Token>>, address: i32, length: i32| {
+            if let Some(text) = read_string(&mut caller, address, length) {
+                caller.data_mut().push(Token::Unicode(text));
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "move",
+        |mut caller: Caller<'_, Vec<Token>>, x: i32, y: i32| {
+            caller.data_mut().push(Token::MoveRelative(x, y));
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "delay",
+        |mut caller: Caller<'_, Vec<Token>>, millis: i32| {
+            caller.data_mut().push(Token::Delay(
+                Duration::from_millis(millis.max(0) as u64),
+                Duration::ZERO,
+            ));
+        },
+    )?;
+
+    Ok(())
+}
+
+fn read_string(caller: &mut Caller<'_, Vec<Token>>, address: i32, length: i32) -> Option<String> {
+    let memory = caller.get_export("memory")?.into_memory()?;
+    let start = address as u32 as usize;
+    let end = start.checked_add(length as u32 as usize)?;
+    let bytes = memory.data(&caller).get(start..end)?;
+
+    String::from_utf8(bytes.to_vec()).ok()
+}
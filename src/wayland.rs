@@ -0,0 +1,581 @@
+use std::env;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use crate::backend::{Backend, NOTCH_DELTA};
+use crate::keycode::{button_code, key_code};
+use crate::scancode::{char_to_scancode, type_char};
+use crate::tokenizer::Button;
+
+use enigo::Key;
+
+// minimal client of the wayland wire protocol, only speaking the virtual input protocols
+const DISPLAY_ID: u32 = 1;
+
+// requests
+const DISPLAY_SYNC: u16 = 0;
+const DISPLAY_GET_REGISTRY: u16 = 1;
+const REGISTRY_BIND: u16 = 0;
+const MANAGER_CREATE: u16 = 0;
+const POINTER_MOTION: u16 = 0;
+const POINTER_BUTTON: u16 = 2;
+const POINTER_AXIS: u16 = 3;
+const POINTER_FRAME: u16 = 4;
+const POINTER_AXIS_SOURCE: u16 = 5;
+const POINTER_AXIS_DISCRETE: u16 = 7;
+const KEYBOARD_KEYMAP: u16 = 0;
+const KEYBOARD_KEY: u16 = 1;
+
+// events
+const DISPLAY_ERROR: u16 = 0;
+const REGISTRY_GLOBAL: u16 = 0;
+const CALLBACK_DONE: u16 = 0;
+
+const AXIS_VERTICAL: u32 = 0;
+const AXIS_HORIZONTAL: u32 = 1;
+const AXIS_SOURCE_WHEEL: u32 = 0;
+// length of a wheel click, the same as libinput
+const AXIS_STEP: f64 = 15.0;
+const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+// the key codes are evdev ones, typed with a us layout like the uinput backend
+const KEYMAP: &str = "xkb_keymap {
+    xkb_keycodes { include \"evdev\" };
+    xkb_types { include \"complete\" };
+    xkb_compat { include \"complete\" };
+    xkb_symbols { include \"pc+us+inet(evdev)\" };
+};
+";
+// characters given a key by each keymap typing text, the xkb keycodes going up to 255
+const MAX_TEXT_KEYS: usize = 200;
+
+enum Argument<'a> {
+    Uint(u32),
+    Int(i32),
+    Fixed(f64),
+    Str(&'a str),
+}
+
+fn message(object: u32, opcode: u16, arguments: &[Argument]) -> Vec<u8> {
+    let mut data = vec![];
+
+    data.extend(object.to_ne_bytes());
+    data.extend([0; 4]);
+
+    for argument in arguments {
+        match argument {
+            Argument::Uint(value) => data.extend(value.to_ne_bytes()),
+            Argument::Int(value) => data.extend(value.to_ne_bytes()),
+            Argument::Fixed(value) => data.extend(((value * 256.0) as i32).to_ne_bytes()),
+            Argument::Str(value) => {
+                data.extend((value.len() as u32 + 1).to_ne_bytes());
+                data.extend(value.as_bytes());
+                data.push(0);
+                data.resize((data.len() + 3) & !3, 0);
+            }
+        }
+    }
+
+    let header = ((data.len() as u32) << 16) | opcode as u32;
+
+    data[4..8].copy_from_slice(&header.to_ne_bytes());
+    data
+}
+
+struct Global {
+    name: u32,
+    interface: String,
+    version: u32,
+}
+
+struct Connection {
+    stream: UnixStream,
+    next_id: u32,
+}
+
+impl Connection {
+    fn connect() -> io::Result<Self> {
+        let display = env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".to_string());
+        let path = match PathBuf::from(&display) {
+            path if path.is_absolute() => path,
+            path => PathBuf::from(
+                env::var("XDG_RUNTIME_DIR")
+                    .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "no XDG_RUNTIME_DIR"))?,
+            )
+            .join(path),
+        };
+
+        Ok(Self {
+            stream: UnixStream::connect(path)?,
+            next_id: DISPLAY_ID + 1,
+        })
+    }
+
+    fn new_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(data)
+    }
+
+    // file descriptors travel next to the message, not in it
+    fn send_with_fd(&mut self, data: &[u8], fd: RawFd) -> io::Result<()> {
+        let mut control = [0u64; 4];
+        let mut iov = libc::iovec {
+            iov_base: data.as_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+
+        unsafe {
+            let mut msghdr: libc::msghdr = std::mem::zeroed();
+
+            msghdr.msg_iov = &mut iov;
+            msghdr.msg_iovlen = 1;
+            msghdr.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msghdr.msg_controllen = libc::CMSG_SPACE(size_of::<RawFd>() as u32) as _;
+
+            let cmsghdr = libc::CMSG_FIRSTHDR(&msghdr);
+
+            (*cmsghdr).cmsg_level = libc::SOL_SOCKET;
+            (*cmsghdr).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsghdr).cmsg_len = libc::CMSG_LEN(size_of::<RawFd>() as u32) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsghdr) as *mut RawFd, fd);
+
+            if libc::sendmsg(self.stream.as_raw_fd(), &msghdr, 0) != data.len() as isize {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<(u32, u16, Vec<u8>)> {
+        let mut header = [0; 8];
+
+        self.stream.read_exact(&mut header)?;
+
+        let object = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+        let size_opcode = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+        let mut body = vec![0; ((size_opcode >> 16) as usize).saturating_sub(8)];
+
+        self.stream.read_exact(&mut body)?;
+
+        Ok((object, size_opcode as u16, body))
+    }
+
+    // waits for the compositor to process every request sent, collecting the globals
+    fn roundtrip(&mut self, registry: Option<u32>) -> io::Result<Vec<Global>> {
+        let callback = self.new_id();
+        let mut global_vec = vec![];
+
+        self.send(&message(
+            DISPLAY_ID,
+            DISPLAY_SYNC,
+            &[Argument::Uint(callback)],
+        ))?;
+
+        loop {
+            let (object, opcode, body) = self.read_event()?;
+
+            if object == DISPLAY_ID && opcode == DISPLAY_ERROR {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "wayland protocol error",
+                ));
+            }
+            if Some(object) == registry && opcode == REGISTRY_GLOBAL {
+                if let Some(global) = parse_global(&body) {
+                    global_vec.push(global);
+                }
+            }
+            if object == callback && opcode == CALLBACK_DONE {
+                return Ok(global_vec);
+            }
+        }
+    }
+}
+
+fn parse_global(body: &[u8]) -> Option<Global> {
+    let uint = |offset: usize| -> Option<u32> {
+        Some(u32::from_ne_bytes(
+            body.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    let length = uint(4)? as usize;
+    let interface = body.get(8..8 + length.checked_sub(1)?)?;
+
+    Some(Global {
+        name: uint(0)?,
+        interface: String::from_utf8_lossy(interface).to_string(),
+        version: uint(8 + ((length + 3) & !3))?,
+    })
+}
+
+// virtual pointer and keyboard, for compositors implementing the wlroots protocols
+pub struct WaylandDevice {
+    connection: Mutex<Connection>,
+    pointer: u32,
+    keyboard: u32,
+    start: Instant,
+}
+
+impl WaylandDevice {
+    pub fn new() -> io::Result<Self> {
+        let mut connection = Connection::connect()?;
+        let registry = connection.new_id();
+
+        connection.send(&message(
+            DISPLAY_ID,
+            DISPLAY_GET_REGISTRY,
+            &[Argument::Uint(registry)],
+        ))?;
+
+        let global_vec = connection.roundtrip(Some(registry))?;
+        let mut bind = |interface: &str| -> io::Result<u32> {
+            let global = global_vec
+                .iter()
+                .find(|global| global.interface == interface)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("compositor without {}", interface),
+                    )
+                })?;
+            let id = connection.new_id();
+
+            connection.send(&message(
+                registry,
+                REGISTRY_BIND,
+                &[
+                    Argument::Uint(global.name),
+                    Argument::Str(interface),
+                    Argument::Uint(global.version.min(1)),
+                    Argument::Uint(id),
+                ],
+            ))?;
+
+            Ok(id)
+        };
+        let seat = bind("wl_seat")?;
+        let pointer_manager = bind("zwlr_virtual_pointer_manager_v1")?;
+        let keyboard_manager = bind("zwp_virtual_keyboard_manager_v1")?;
+        let pointer = connection.new_id();
+        let keyboard = connection.new_id();
+
+        connection.send(&message(
+            pointer_manager,
+            MANAGER_CREATE,
+            &[Argument::Uint(seat), Argument::Uint(pointer)],
+        ))?;
+        connection.send(&message(
+            keyboard_manager,
+            MANAGER_CREATE,
+            &[Argument::Uint(seat), Argument::Uint(keyboard)],
+        ))?;
+
+        send_keymap(&mut connection, keyboard, KEYMAP)?;
+        connection.roundtrip(None)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            pointer,
+            keyboard,
+            start: Instant::now(),
+        })
+    }
+
+    fn time(&self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        match self.connection.lock() {
+            Ok(connection) => connection,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn send(&self, message_vec: &[Vec<u8>]) {
+        if let Err(err) = self.connection().send(&message_vec.concat()) {
+            println!("wayland write error : {}", err);
+        }
+    }
+
+    // the characters missing from the us layout are typed through keymaps giving a key to
+    // each character of the text, like wtype does, then the us layout is restored
+    fn type_text(&self, text: &str) {
+        let mut connection = self.connection();
+        let char_vec: Vec<char> = text.chars().collect();
+        let result = char_vec
+            .chunks(MAX_TEXT_KEYS)
+            .try_for_each(|segment| self.type_segment(&mut connection, segment))
+            .and_then(|_| send_keymap(&mut connection, self.keyboard, KEYMAP));
+
+        if let Err(err) = result {
+            println!("wayland write error : {}", err);
+        }
+    }
+
+    fn type_segment(&self, connection: &mut Connection, segment: &[char]) -> io::Result<()> {
+        let mut key_vec: Vec<char> = vec![];
+        let mut message_vec = vec![];
+
+        for char in segment {
+            let index = match key_vec.iter().position(|key| key == char) {
+                Some(index) => index,
+                None => {
+                    key_vec.push(*char);
+                    key_vec.len() - 1
+                }
+            };
+
+            for pressed in [true, false] {
+                message_vec.push(message(
+                    self.keyboard,
+                    KEYBOARD_KEY,
+                    &[
+                        Argument::Uint(self.time()),
+                        Argument::Uint(index as u32 + 1),
+                        Argument::Uint(pressed as u32),
+                    ],
+                ));
+            }
+        }
+
+        send_keymap(connection, self.keyboard, &text_keymap(&key_vec))?;
+        connection.send(&message_vec.concat())
+    }
+
+    fn pointer_button(&self, button: Button, pressed: bool) {
+        if let Some(code) = button_code(button) {
+            self.send(&[
+                message(
+                    self.pointer,
+                    POINTER_BUTTON,
+                    &[
+                        Argument::Uint(self.time()),
+                        Argument::Uint(code as u32),
+                        Argument::Uint(pressed as u32),
+                    ],
+                ),
+                message(self.pointer, POINTER_FRAME, &[]),
+            ]);
+        }
+    }
+
+    fn pointer_axis(&self, axis: u32, length: i32) {
+        self.send(&[
+            message(
+                self.pointer,
+                POINTER_AXIS_SOURCE,
+                &[Argument::Uint(AXIS_SOURCE_WHEEL)],
+            ),
+            message(
+                self.pointer,
+                POINTER_AXIS_DISCRETE,
+                &[
+                    Argument::Uint(self.time()),
+                    Argument::Uint(axis),
+                    Argument::Fixed(length as f64 * AXIS_STEP),
+                    Argument::Int(length),
+                ],
+            ),
+            message(self.pointer, POINTER_FRAME, &[]),
+        ]);
+    }
+
+    // without a discrete step, so the clients scroll by the exact distance
+    fn pointer_smooth_axis(&self, axis: u32, delta: i32) {
+        self.send(&[
+            message(
+                self.pointer,
+                POINTER_AXIS_SOURCE,
+                &[Argument::Uint(AXIS_SOURCE_WHEEL)],
+            ),
+            message(
+                self.pointer,
+                POINTER_AXIS,
+                &[
+                    Argument::Uint(self.time()),
+                    Argument::Uint(axis),
+                    Argument::Fixed(delta as f64 * AXIS_STEP / NOTCH_DELTA as f64),
+                ],
+            ),
+            message(self.pointer, POINTER_FRAME, &[]),
+        ]);
+    }
+
+    fn key(&self, code: u16, pressed: bool) {
+        self.send(&[message(
+            self.keyboard,
+            KEYBOARD_KEY,
+            &[
+                Argument::Uint(self.time()),
+                Argument::Uint(code as u32),
+                Argument::Uint(pressed as u32),
+            ],
+        )]);
+    }
+}
+
+fn send_keymap(connection: &mut Connection, keyboard: u32, keymap: &str) -> io::Result<()> {
+    let keymap_file = create_keymap_file(keymap)?;
+
+    connection.send_with_fd(
+        &message(
+            keyboard,
+            KEYBOARD_KEYMAP,
+            &[
+                Argument::Uint(KEYMAP_FORMAT_XKB_V1),
+                Argument::Uint(keymap.len() as u32 + 1),
+            ],
+        ),
+        keymap_file.as_raw_fd(),
+    )
+}
+
+// the key of each character sends the evdev code of its index plus one, so the xkb keycode
+// of its index plus nine
+fn text_keymap(key_vec: &[char]) -> String {
+    let mut keycodes = String::new();
+    let mut symbols = String::new();
+
+    for (index, char) in key_vec.iter().enumerate() {
+        let keysym = match char {
+            '\n' => "Return".to_string(),
+            '\t' => "Tab".to_string(),
+            char => format!("U{:04X}", *char as u32),
+        };
+
+        keycodes.push_str(&format!("        <T{}> = {};\n", index, index + 9));
+        symbols.push_str(&format!("        key <T{}> {{ [ {} ] }};\n", index, keysym));
+    }
+
+    format!(
+        "xkb_keymap {{
+    xkb_keycodes {{
+        minimum = 8;
+        maximum = 255;
+{}    }};
+    xkb_types {{ include \"complete\" }};
+    xkb_compat {{ include \"complete\" }};
+    xkb_symbols {{
+{}    }};
+}};
+",
+        keycodes, symbols
+    )
+}
+
+fn create_keymap_file(keymap: &str) -> io::Result<File> {
+    let name = CString::new("mad-rust-keymap")?;
+    let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut file = unsafe { File::from_raw_fd(fd) };
+
+    // the compositor expects a null terminated string
+    file.write_all(keymap.as_bytes())?;
+    file.write_all(&[0])?;
+
+    Ok(file)
+}
+
+impl Backend for Arc<WaylandDevice> {
+    fn mouse_move_relative(&mut self, x: i32, y: i32) {
+        self.send(&[
+            message(
+                self.pointer,
+                POINTER_MOTION,
+                &[
+                    Argument::Uint(self.time()),
+                    Argument::Fixed(x as f64),
+                    Argument::Fixed(y as f64),
+                ],
+            ),
+            message(self.pointer, POINTER_FRAME, &[]),
+        ]);
+    }
+
+    fn mouse_down(&mut self, button: Button) {
+        self.pointer_button(button, true);
+    }
+
+    fn mouse_up(&mut self, button: Button) {
+        self.pointer_button(button, false);
+    }
+
+    fn mouse_click(&mut self, button: Button) {
+        self.pointer_button(button, true);
+        self.pointer_button(button, false);
+    }
+
+    // like enigo, positive lengths scroll right and down
+    fn mouse_scroll_x(&mut self, length: i32) {
+        self.pointer_axis(AXIS_HORIZONTAL, length);
+    }
+
+    fn mouse_scroll_y(&mut self, length: i32) {
+        self.pointer_axis(AXIS_VERTICAL, length);
+    }
+
+    fn mouse_smooth_scroll_x(&mut self, delta: i32) {
+        self.pointer_smooth_axis(AXIS_HORIZONTAL, delta);
+    }
+
+    fn mouse_smooth_scroll_y(&mut self, delta: i32) {
+        self.pointer_smooth_axis(AXIS_VERTICAL, delta);
+    }
+
+    fn key_sequence(&mut self, sequence: &str) {
+        if !sequence
+            .chars()
+            .all(|char| char_to_scancode(char).is_some())
+        {
+            return self.type_text(sequence);
+        }
+
+        for char in sequence.chars() {
+            type_char(char, |code, pressed| self.key(code, pressed));
+        }
+    }
+
+    fn key_down(&mut self, key: Key) {
+        if let Some(code) = key_code(key) {
+            self.key(code, true);
+        }
+    }
+
+    fn key_up(&mut self, key: Key) {
+        if let Some(code) = key_code(key) {
+            self.key(code, false);
+        }
+    }
+
+    fn scancode_click(&mut self, char: char) {
+        type_char(char, |code, pressed| self.key(code, pressed));
+    }
+
+    fn key_click(&mut self, key: Key) {
+        match key {
+            Key::Layout(char) => type_char(char, |code, pressed| self.key(code, pressed)),
+            key => {
+                if let Some(code) = key_code(key) {
+                    self.key(code, true);
+                    self.key(code, false);
+                }
+            }
+        }
+    }
+}
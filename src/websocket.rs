@@ -0,0 +1,77 @@
+use futures_util::SinkExt;
+use serde::Serialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DriverEvent {
+    Device {
+        serial_number: String,
+        connected: bool,
+    },
+    // key of the button in the config file, or left, right and middle for the clicks
+    Button {
+        serial_number: String,
+        button: String,
+        pressed: bool,
+    },
+    Mode {
+        serial_number: String,
+        mode: u8,
+        shift: bool,
+    },
+    Profile {
+        serial_number: String,
+        profile: Option<String>,
+    },
+}
+
+// events are dropped when nobody is listening
+pub type EventSender = broadcast::Sender<DriverEvent>;
+
+pub fn event_channel() -> EventSender {
+    broadcast::channel(256).0
+}
+
+// only listens on localhost, each client receives every event as json
+pub async fn run_websocket(port: u16, event_tx: EventSender) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("WebSocket server not started : {}", err);
+            return;
+        }
+    };
+
+    println!("WebSocket server listening on 127.0.0.1:{}", port);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(stream_events(stream, event_tx.subscribe()));
+        }
+    });
+}
+
+async fn stream_events(stream: TcpStream, mut event_rx: broadcast::Receiver<DriverEvent>) {
+    let mut websocket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(websocket) => websocket,
+        Err(_) => return,
+    };
+
+    loop {
+        let event = match event_rx.recv().await {
+            Ok(event) => event,
+            // a slow client misses the oldest events
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+
+        if let Ok(json) = serde_json::to_string(&event) {
+            if websocket.send(Message::Text(json)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
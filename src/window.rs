@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveWindow {
+    pub process: String,
+    pub title: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct WindowRule {
+    // process name without extension, case insensitive
+    #[serde(default)]
+    pub process: Option<String>,
+    // part of the window title, case insensitive
+    #[serde(default)]
+    pub title: Option<String>,
+    pub profile: String,
+}
+
+impl WindowRule {
+    pub fn matches(&self, active_window: &ActiveWindow) -> bool {
+        if self.process.is_none() && self.title.is_none() {
+            return false;
+        }
+
+        let process_matches = self.process.iter().all(|process| {
+            let process = process.to_lowercase();
+
+            process.trim_end_matches(".exe") == active_window.process.to_lowercase()
+        });
+        let title_matches = self.title.iter().all(|title| {
+            active_window
+                .title
+                .to_lowercase()
+                .contains(&title.to_lowercase())
+        });
+
+        process_matches && title_matches
+    }
+}
+
+// part of the title or of the class of a window, case insensitive
+fn window_matches(query: &str, title: &str, class: &str) -> bool {
+    let query = query.trim().to_lowercase();
+
+    !query.is_empty()
+        && (title.to_lowercase().contains(&query) || class.to_lowercase().contains(&query))
+}
+
+pub use platform::{focus_window, ActiveWindowWatcher};
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{window_matches, ActiveWindow};
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window,
+    };
+    use x11rb::rust_connection::RustConnection;
+
+    // source indication of the pagers, window managers ignoring the focus stealing prevention
+    const PAGER_SOURCE: u32 = 2;
+
+    struct X11 {
+        connection: RustConnection,
+        root: Window,
+        net_active_window: Atom,
+        net_client_list: Atom,
+        net_wm_name: Atom,
+        net_wm_pid: Atom,
+        utf8_string: Atom,
+    }
+
+    // only sees X11 and XWayland windows, wayland compositors don't expose the focused window
+    #[derive(Default)]
+    pub struct ActiveWindowWatcher {
+        x11: Option<X11>,
+    }
+
+    impl ActiveWindowWatcher {
+        pub fn active_window(&mut self) -> Option<ActiveWindow> {
+            if self.x11.is_none() {
+                self.x11 = connect();
+            }
+
+            let active_window = query_active_window(self.x11.as_ref()?);
+
+            // the connection might be broken, retry on next call
+            if active_window.is_none() {
+                self.x11 = None;
+            }
+
+            active_window.flatten()
+        }
+    }
+
+    fn connect() -> Option<X11> {
+        let (connection, screen_num) = x11rb::connect(None).ok()?;
+        let root = connection.setup().roots.get(screen_num)?.root;
+        let intern_atom = |name: &[u8]| -> Option<Atom> {
+            Some(connection.intern_atom(false, name).ok()?.reply().ok()?.atom)
+        };
+        let net_active_window = intern_atom(b"_NET_ACTIVE_WINDOW")?;
+        let net_client_list = intern_atom(b"_NET_CLIENT_LIST")?;
+        let net_wm_name = intern_atom(b"_NET_WM_NAME")?;
+        let net_wm_pid = intern_atom(b"_NET_WM_PID")?;
+        let utf8_string = intern_atom(b"UTF8_STRING")?;
+
+        Some(X11 {
+            connection,
+            root,
+            net_active_window,
+            net_client_list,
+            net_wm_name,
+            net_wm_pid,
+            utf8_string,
+        })
+    }
+
+    // asks the window manager to activate the first window matching, false when none does
+    pub fn focus_window(query: &str) -> bool {
+        connect()
+            .and_then(|x11| activate_matching_window(&x11, query))
+            .unwrap_or(false)
+    }
+
+    fn activate_matching_window(x11: &X11, query: &str) -> Option<bool> {
+        let window_vec: Vec<Window> = x11
+            .connection
+            .get_property(
+                false,
+                x11.root,
+                x11.net_client_list,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .collect();
+
+        for window in window_vec {
+            let title = text_property(x11, window, x11.net_wm_name, x11.utf8_string)?;
+            // the instance and class names, separated by nulls
+            let class = text_property(
+                x11,
+                window,
+                AtomEnum::WM_CLASS.into(),
+                AtomEnum::STRING.into(),
+            )?
+            .replace('\0', " ");
+
+            if window_matches(query, &title, &class) {
+                let event = ClientMessageEvent::new(
+                    32,
+                    window,
+                    x11.net_active_window,
+                    [PAGER_SOURCE, 0, 0, 0, 0],
+                );
+
+                x11.connection
+                    .send_event(
+                        false,
+                        x11.root,
+                        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                        event,
+                    )
+                    .ok()?;
+                x11.connection.flush().ok()?;
+
+                return Some(true);
+            }
+        }
+
+        Some(false)
+    }
+
+    fn text_property(x11: &X11, window: Window, property: Atom, type_: Atom) -> Option<String> {
+        let value = x11
+            .connection
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?
+            .value;
+
+        Some(String::from_utf8_lossy(&value).to_string())
+    }
+
+    // returns none on connection errors, and some none when no window is focused
+    fn query_active_window(x11: &X11) -> Option<Option<ActiveWindow>> {
+        let window = x11
+            .connection
+            .get_property(
+                false,
+                x11.root,
+                x11.net_active_window,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|window| *window != 0);
+        let window = match window {
+            Some(window) => window,
+            None => return Some(None),
+        };
+        let title = x11
+            .connection
+            .get_property(false, window, x11.net_wm_name, x11.utf8_string, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?
+            .value;
+        let pid = x11
+            .connection
+            .get_property(false, window, x11.net_wm_pid, AtomEnum::CARDINAL, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()
+            .and_then(|mut value| value.next());
+        let process = pid
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+            .unwrap_or_default();
+
+        Some(Some(ActiveWindow {
+            process: process.trim().to_string(),
+            title: String::from_utf8_lossy(&title).to_string(),
+        }))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::path::Path;
+
+    use super::{window_matches, ActiveWindow};
+
+    use windows_sys::Win32::Foundation::{CloseHandle, BOOL, HWND, LPARAM};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetClassNameW, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+        IsIconic, IsWindowVisible, SetForegroundWindow, ShowWindow, SW_RESTORE,
+    };
+
+    struct WindowSearch {
+        query: String,
+        window: HWND,
+    }
+
+    // brings the first visible window matching to the foreground, restoring it when minimized
+    pub fn focus_window(query: &str) -> bool {
+        let mut window_search = WindowSearch {
+            query: query.to_string(),
+            window: 0,
+        };
+
+        unsafe {
+            EnumWindows(
+                Some(search_window),
+                &mut window_search as *mut WindowSearch as LPARAM,
+            );
+
+            if window_search.window == 0 {
+                return false;
+            }
+
+            if IsIconic(window_search.window) != 0 {
+                ShowWindow(window_search.window, SW_RESTORE);
+            }
+
+            SetForegroundWindow(window_search.window) != 0
+        }
+    }
+
+    // stops the enumeration once a window matches
+    unsafe extern "system" fn search_window(window: HWND, lparam: LPARAM) -> BOOL {
+        let window_search = &mut *(lparam as *mut WindowSearch);
+
+        if IsWindowVisible(window) == 0 {
+            return 1;
+        }
+
+        let mut title = [0u16; 512];
+        let title_length = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+        let mut class = [0u16; 256];
+        let class_length = GetClassNameW(window, class.as_mut_ptr(), class.len() as i32);
+
+        if window_matches(
+            &window_search.query,
+            &String::from_utf16_lossy(&title[..title_length.max(0) as usize]),
+            &String::from_utf16_lossy(&class[..class_length.max(0) as usize]),
+        ) {
+            window_search.window = window;
+            return 0;
+        }
+
+        1
+    }
+
+    #[derive(Default)]
+    pub struct ActiveWindowWatcher;
+
+    impl ActiveWindowWatcher {
+        pub fn active_window(&mut self) -> Option<ActiveWindow> {
+            unsafe {
+                let window = GetForegroundWindow();
+
+                if window == 0 {
+                    return None;
+                }
+
+                let mut title = [0u16; 512];
+                let title_length = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+                let mut process_id = 0;
+
+                GetWindowThreadProcessId(window, &mut process_id);
+
+                let mut process = String::new();
+                let process_handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+
+                if process_handle != 0 {
+                    let mut path = [0u16; 1024];
+                    let mut path_length = path.len() as u32;
+
+                    if QueryFullProcessImageNameW(
+                        process_handle,
+                        0,
+                        path.as_mut_ptr(),
+                        &mut path_length,
+                    ) != 0
+                    {
+                        let path = String::from_utf16_lossy(&path[..path_length as usize]);
+
+                        if let Some(file_stem) = Path::new(&path).file_stem() {
+                            process = file_stem.to_string_lossy().to_string();
+                        }
+                    }
+
+                    CloseHandle(process_handle);
+                }
+
+                Some(ActiveWindow {
+                    process,
+                    title: String::from_utf16_lossy(&title[..title_length.max(0) as usize]),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::ActiveWindow;
+
+    pub fn focus_window(_query: &str) -> bool {
+        false
+    }
+
+    #[derive(Default)]
+    pub struct ActiveWindowWatcher;
+
+    impl ActiveWindowWatcher {
+        pub fn active_window(&mut self) -> Option<ActiveWindow> {
+            None
+        }
+    }
+}